@@ -0,0 +1,168 @@
+use std::time::Duration;
+
+use anyhow::Context;
+
+use crate::failure::FailureKind;
+use crate::persistence::{AliasSwitch, BulkLoadGuard, CollectionSettings, DatabaseClient, UploadVerifier};
+use crate::upload_job::{UploadJob, UploadJobBuilder};
+
+const GREEN_STATUS_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Runs an [`UploadJob`] into a Qdrant collection, either in place or, with blue/green, into a copy of the
+/// collection behind the alias that is switched once the upload succeeded. Indexing can be turned off during the
+/// upload, and the uploaded rows are verified when the job has an [`UploadVerifier`].
+pub struct CollectionUpload<'a> {
+    database_client: &'a DatabaseClient,
+    collection_settings: &'a CollectionSettings,
+    bulk_load: bool,
+    blue_green: bool,
+    wait_for_green: Option<Duration>,
+    delete_previous_collection_after: Option<Duration>,
+}
+
+impl<'a> CollectionUpload<'a> {
+
+    /// The collection of the client is the alias to switch with blue/green. The indexing threshold and whether
+    /// indexing is deferred are taken from the settings, along with the configuration of a blue/green copy
+    pub fn new(database_client: &'a DatabaseClient, collection_settings: &'a CollectionSettings) -> Self {
+        CollectionUpload {
+            database_client,
+            collection_settings,
+            bulk_load: false,
+            blue_green: false,
+            wait_for_green: None,
+            delete_previous_collection_after: None,
+        }
+    }
+
+    /// Turns indexing off during an in place upload, as with deferred indexing
+    pub fn bulk_load(mut self, bulk_load: bool) -> Self {
+        self.bulk_load = bulk_load;
+        self
+    }
+
+    pub fn blue_green(mut self, blue_green: bool) -> Self {
+        self.blue_green = blue_green;
+        self
+    }
+
+    /// Once indexing is turned back on, waits up to this long for the collection to become green
+    pub fn wait_for_green(mut self, timeout: Option<Duration>) -> Self {
+        self.wait_for_green = timeout;
+        self
+    }
+
+    /// Deletes the collection previously behind the alias this long after a blue/green switch
+    pub fn delete_previous_collection_after(mut self, grace_period: Option<Duration>) -> Self {
+        self.delete_previous_collection_after = grace_period;
+        self
+    }
+
+    /// Sets the client of the target collection as the sink of the job and runs it. Errors are tagged with a
+    /// [`FailureKind`], and when cleaning up after a failed upload fails too, both errors are reported
+    pub async fn run(&self, upload_job_builder: UploadJobBuilder) -> anyhow::Result<UploadJob> {
+        if self.blue_green {
+            self.run_blue_green(upload_job_builder).await
+        } else {
+            self.run_in_place(upload_job_builder).await
+        }
+    }
+
+    async fn run_in_place(&self, upload_job_builder: UploadJobBuilder) -> anyhow::Result<UploadJob> {
+        let mut upload_job = upload_job_builder.sink(self.database_client.clone()).build()?;
+        let defers_indexing = self.bulk_load || self.collection_settings.defer_indexing;
+
+        let bulk_load_guard =
+            if defers_indexing {
+                Some(BulkLoadGuard::start(self.database_client, self.collection_settings.indexing_threshold).await.context(FailureKind::Qdrant)?)
+            } else {
+                None
+            };
+
+        let upload_result = upload_job.run().await;
+
+        let restore_result = match bulk_load_guard {
+            Some(bulk_load_guard) => bulk_load_guard.restore().await.context(FailureKind::Qdrant),
+            None => Ok(()),
+        };
+        match (upload_result, restore_result) {
+            (Err(upload_error), Err(restore_error)) => return Err(with_cleanup_error(upload_error, restore_error)),
+            (upload_result, restore_result) => {
+                upload_result?;
+                restore_result?;
+            }
+        }
+
+        if let Some(timeout) = self.wait_for_green.filter(|_| defers_indexing) {
+            self.database_client.wait_for_green(GREEN_STATUS_POLL_INTERVAL, timeout).await.context(FailureKind::Qdrant)?;
+        }
+
+        if let Some(verifier) = upload_job.verifier() {
+            verifier.verify(self.database_client).await?;
+        }
+
+        Ok(upload_job)
+    }
+
+    async fn run_blue_green(&self, upload_job_builder: UploadJobBuilder) -> anyhow::Result<UploadJob> {
+        let alias_switch = AliasSwitch::prepare(self.database_client, self.collection_settings).await.context(FailureKind::Qdrant)?;
+        let target_client = self.database_client.for_collection(alias_switch.target_collection());
+
+        let upload_job = match self.upload_before_switch(&alias_switch, &target_client, upload_job_builder).await {
+            Ok(upload_job) => upload_job,
+            Err(error) => return Err(alias_switch.abort_after(error).await),
+        };
+
+        if let Some(grace_period) = self.delete_previous_collection_after {
+            alias_switch.delete_previous_collection(grace_period).await.context(FailureKind::Qdrant)?;
+        }
+
+        Ok(upload_job)
+    }
+
+    /// Uploads into the collection created for blue/green and moves the alias to it. The distinct ids are always
+    /// counted for the check before the switch, while the sampled points are only verified when asked for
+    async fn upload_before_switch(&self, alias_switch: &AliasSwitch, target_client: &DatabaseClient,
+                                  upload_job_builder: UploadJobBuilder) -> anyhow::Result<UploadJob> {
+        let verifies = upload_job_builder.has_verifier();
+        let upload_job_builder = if verifies { upload_job_builder } else { upload_job_builder.verifier(UploadVerifier::new(0, 0.0)) };
+
+        let defer_indexing_guard =
+            if self.collection_settings.defer_indexing {
+                Some(BulkLoadGuard::start(target_client, self.collection_settings.indexing_threshold).await.context(FailureKind::Qdrant)?)
+            } else {
+                None
+            };
+
+        let mut upload_job = upload_job_builder.sink(target_client.clone()).build()?;
+        if let Err(error) = upload_job.run().await {
+            if let Some(defer_indexing_guard) = defer_indexing_guard {
+                defer_indexing_guard.dismiss();
+            }
+            return Err(error);
+        }
+
+        if let Some(defer_indexing_guard) = defer_indexing_guard {
+            defer_indexing_guard.restore().await.context(FailureKind::Qdrant)?;
+
+            if let Some(timeout) = self.wait_for_green {
+                target_client.wait_for_green(GREEN_STATUS_POLL_INTERVAL, timeout).await.context(FailureKind::Qdrant)?;
+            }
+        }
+
+        let verifier = upload_job.verifier().expect("blue/green upload always has a verifier");
+        if verifies {
+            verifier.verify(target_client).await?;
+        }
+        alias_switch.commit(target_client, verifier.distinct_ids()).await.context(FailureKind::Qdrant)?;
+
+        Ok(upload_job)
+    }
+}
+
+
+/// Keeps the error of the upload, which sets the exit code, and adds the error of the cleanup that followed it
+pub(crate) fn with_cleanup_error(upload_error: anyhow::Error, cleanup_error: anyhow::Error) -> anyhow::Error {
+    tracing::error!("Cleanup after the failed upload failed as well: {cleanup_error:#}");
+    upload_error.context(format!("{cleanup_error:#}, after the upload failed"))
+}
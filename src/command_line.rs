@@ -1,10 +1,24 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context;
 use clap::Parser;
-use crate::persistence::{files_system::FileType, vector_field_name::FieldName};
+use reqwest::header::HeaderMap;
+use qdrant_uploader::{PointMapper, RecordSource};
+use qdrant_uploader::monitoring::Metrics;
+use qdrant_uploader::watch::{ProcessedAction, WatchState, Watcher};
+use qdrant_uploader::embedding::{Embedder, EmbeddingStage, ProviderType};
+#[cfg(feature = "kafka")]
+use qdrant_uploader::persistence::kafka::{KafkaOptions, KafkaSource};
+use qdrant_uploader::persistence::{CollectionSettings, ConnectionOptions, QuantizationKind, TlsSettings, VectorDistance, WriteOrderingKind, files_system::{parse_header, Dataset, FileType, HttpTransport, LocalDirectory, PairedDataset, S3Options, S3Prefix, S3Transport, TransportRegistry, VectorFileType}, vector_field_name::FieldName};
+
+use crate::telemetry::LogFormat;
 
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about=None)]
 pub struct CommandLine {
-    /// Source path
+    /// Source path, a local path or URL, - to read from stdin, or kafka://host:port/topic to consume a Kafka topic
     #[clap(long, short, env = "SOURCE_PATH")]
     pub source_path: String,
 
@@ -12,6 +26,22 @@ pub struct CommandLine {
     #[clap(long, default_value = "json", env = "SOURCE_FILE_TYPE")]
     pub source_file_type: FileType,
 
+    /// Binary matrix file with the vector of each row of --source-path, in the same order
+    #[clap(long, env = "VECTOR_SOURCE_PATH")]
+    pub vector_source_path: Option<String>,
+
+    /// Type of --vector-source-path [default: detected from its extension]
+    #[clap(long, env = "VECTOR_FILE_TYPE")]
+    pub vector_file_type: Option<VectorFileType>,
+
+    /// Name of the array read from a .npz --vector-source-path
+    #[clap(long, default_value="arr_0", env = "NPZ_ARRAY")]
+    pub npz_array: String,
+
+    /// Field filled with the vectors of --vector-source-path [default: the first --vector-field-name]
+    #[clap(long, env = "VECTOR_SOURCE_FIELD")]
+    pub vector_source_field: Option<String>,
+
     /// QDrant connection String
     #[clap(long, env = "CONNECTION_STRING")]
     pub connection_string: String,
@@ -20,6 +50,46 @@ pub struct CommandLine {
     #[clap(long, env = "DATABASE_API_KEY")]
     pub api_key: Option<String>,
 
+    /// File holding the Qdrant API key, such as a mounted Kubernetes secret
+    #[clap(long, env = "DATABASE_API_KEY_FILE")]
+    pub api_key_file: Option<String>,
+
+    /// PEM bundle of the certificate authorities trusted for the Qdrant connection, instead of the system ones
+    #[clap(long, env = "TLS_CA_CERT")]
+    pub tls_ca_cert: Option<String>,
+
+    /// PEM certificate presented to Qdrant, which requires --tls-client-key
+    #[clap(long, env = "TLS_CLIENT_CERT")]
+    pub tls_client_cert: Option<String>,
+
+    /// PEM private key of --tls-client-cert
+    #[clap(long, env = "TLS_CLIENT_KEY")]
+    pub tls_client_key: Option<String>,
+
+    /// Name checked against the certificate of Qdrant, instead of the host of --connection-string
+    #[clap(long, env = "TLS_SERVER_NAME")]
+    pub tls_server_name: Option<String>,
+
+    /// Timeout of the connection to Qdrant, in seconds [default: 5]
+    #[clap(long, env = "CONNECT_TIMEOUT")]
+    pub connect_timeout: Option<u64>,
+
+    /// Timeout of each request to Qdrant, in seconds [default: 5]
+    #[clap(long, env = "REQUEST_TIMEOUT")]
+    pub request_timeout: Option<u64>,
+
+    /// Interval of HTTP/2 keep-alive pings to Qdrant, in seconds [default: no pings]
+    #[clap(long, env = "GRPC_KEEP_ALIVE_INTERVAL")]
+    pub grpc_keep_alive_interval: Option<u64>,
+
+    /// Time to wait for the answer to a keep-alive ping before closing the connection, in seconds [default: 20]
+    #[clap(long, env = "GRPC_KEEP_ALIVE_TIMEOUT")]
+    pub grpc_keep_alive_timeout: Option<u64>,
+
+    /// Send HTTP/2 keep-alive pings to Qdrant while no request is running, every 30 seconds without --grpc-keep-alive-interval
+    #[clap(long, default_value="false", env = "KEEP_ALIVE_WHILE_IDLE")]
+    pub keep_alive_while_idle: bool,
+
     /// Qdrant collection
     #[clap(long, env = "DATABASE_COLLECTION")]
     pub database_collection: String,
@@ -28,6 +98,14 @@ pub struct CommandLine {
     #[clap(long)]
     pub id_field_name: Option<String>,
 
+    /// Field with the shard key of each row, points are upserted separately for each shard key
+    #[clap(long, env = "SHARD_KEY_FIELD")]
+    pub shard_key_field: Option<String>,
+
+    /// Payload field used to partition tenants, the upload fails if the collection has no keyword or uuid payload index for it. A keyword index is created with --recreate-collection
+    #[clap(long, env = "TENANT_INDEX_FIELD")]
+    pub tenant_index_field: Option<String>,
+
     /// Names of the fields to be loaded as vectors
     #[clap(long)]
     #[arg(num_args(1..))]
@@ -47,9 +125,41 @@ pub struct CommandLine {
     pub upload_whole_field_as_payload: bool,
 
     /// The Qdrant database write chunk size
-    #[clap(long, default_value="256")]
+    #[clap(long, default_value="256", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
     pub chunk_size: usize,
 
+    /// Ordering guarantee of the upserts across the replicas of a shard [default: weak]
+    #[clap(long, env = "WRITE_ORDERING")]
+    pub write_ordering: Option<WriteOrderingKind>,
+
+    /// Do not wait for each upsert to be applied, only wait for all of them once the last batch is uploaded
+    #[clap(long, default_value="false", env = "NO_WAIT")]
+    pub no_wait: bool,
+
+    /// Maximum size in bytes of an upsert request, whose chunks are split to stay under it, 1 KiB of which is kept for the fields besides the points. Qdrant rejects gRPC messages over 32 MB by default
+    #[clap(long, env = "MAX_BATCH_BYTES", value_parser = clap::value_parser!(u64).range(1..))]
+    pub max_batch_bytes: Option<u64>,
+
+    /// Maximum number of points upserted per second
+    #[clap(long, env = "MAX_POINTS_PER_SECOND", value_parser = clap::value_parser!(u64).range(1..))]
+    pub max_points_per_second: Option<u64>,
+
+    /// Maximum number of bytes of encoded points upserted per second
+    #[clap(long, env = "MAX_BYTES_PER_SECOND", value_parser = clap::value_parser!(u64).range(1..))]
+    pub max_bytes_per_second: Option<u64>,
+
+    /// Shrink the chunk size when upserts get slow or fail, and grow it back up to --chunk-size while Qdrant is healthy
+    #[clap(long, default_value="false", env = "ADAPTIVE_CHUNK_SIZE")]
+    pub adaptive_chunk_size: bool,
+
+    /// Smallest chunk size used with --adaptive-chunk-size
+    #[clap(long, default_value="16", env = "MIN_CHUNK_SIZE", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    pub min_chunk_size: usize,
+
+    /// Upsert latency above which --adaptive-chunk-size shrinks the chunk size, in milliseconds
+    #[clap(long, default_value="1000", env = "TARGET_UPSERT_LATENCY_MS")]
+    pub target_upsert_latency_ms: u64,
+
     /// Database collection
     #[clap(long, env = "BATCH_SIZE")]
     pub batch_size: u32,
@@ -58,7 +168,7 @@ pub struct CommandLine {
     #[clap(long, env = "S3_ENDPOINT")]
     pub s3_endpoint: Option<String>,
 
-    /// S3 Access key
+    /// S3 Access key. If neither keys are provided, credentials are resolved with the default AWS provider chain
     #[clap(long, env = "S3_ACCESS_KEY")]
     pub s3_access_key: Option<String>,
 
@@ -66,13 +176,430 @@ pub struct CommandLine {
     #[clap(long, env = "S3_SECRET_ACCESS_KEY")]
     pub s3_secret_access_key: Option<String>,
 
-    /// S3 Region to connect
-    #[clap(long, default_value="minio", env = "S3_REGION")]
+    /// Session token of temporary S3 credentials given with --s3-access-key and --s3-secret-access-key
+    #[clap(long, env = "S3_SESSION_TOKEN")]
+    pub s3_session_token: Option<String>,
+
+    /// AWS profile used by the default provider chain
+    #[clap(long, env = "S3_PROFILE")]
+    pub s3_profile: Option<String>,
+
+    /// ARN of a role assumed with STS to read from S3
+    #[clap(long, env = "S3_ROLE_ARN")]
+    pub s3_role_arn: Option<String>,
+
+    /// Session name used when assuming --s3-role-arn
+    #[clap(long, default_value="qdrant-uploader", env = "S3_ROLE_SESSION_NAME")]
+    pub s3_role_session_name: String,
+
+    /// External id required by the trust policy of --s3-role-arn
+    #[clap(long, env = "S3_EXTERNAL_ID")]
+    pub s3_external_id: Option<String>,
+
+    /// Address buckets with virtual-hosted style (bucket.endpoint) instead of path style (endpoint/bucket)
+    #[clap(long, default_value="false", env = "S3_VIRTUAL_HOSTED_STYLE")]
+    pub s3_virtual_hosted_style: bool,
+
+    /// S3 Region to connect, defaults to the region of the environment or profile, then to us-east-1
+    #[clap(long, env = "S3_REGION")]
     pub s3_region: Option<String>,
+
+    /// Number of consecutive times an interrupted s3:// or gs:// download is resumed before failing
+    #[clap(long, default_value="5", env = "S3_MAX_RETRIES")]
+    pub s3_max_retries: u32,
+
+    /// S3-compatible endpoint used for gs:// source paths
+    #[clap(long, default_value="https://storage.googleapis.com", env = "GS_ENDPOINT")]
+    pub gs_endpoint: String,
+
+    /// Cloud Storage HMAC access key
+    #[clap(long, env = "GS_ACCESS_KEY")]
+    pub gs_access_key: Option<String>,
+
+    /// Cloud Storage HMAC secret
+    #[clap(long, env = "GS_SECRET_ACCESS_KEY")]
+    pub gs_secret_access_key: Option<String>,
+
+    /// Header sent with http(s):// source requests, as "Name: value", e.g. "Authorization: Bearer <token>"
+    #[clap(long)]
+    pub http_header: Vec<String>,
+
+    /// Maximum number of redirects followed by http(s):// source requests
+    #[clap(long, default_value="10", env = "HTTP_MAX_REDIRECTS")]
+    pub http_max_redirects: usize,
+
+    /// Number of consecutive times an interrupted http(s):// download is resumed before failing
+    #[clap(long, default_value="5", env = "HTTP_MAX_RETRIES")]
+    pub http_max_retries: u32,
+
+    /// Consumer group of a kafka:// source, whose committed offsets are where consumption resumes
+    #[clap(long, default_value="qdrant-uploader", env = "KAFKA_GROUP_ID")]
+    pub kafka_group_id: String,
+
+    /// Longest time in milliseconds a batch of a kafka:// source waits to be filled before being uploaded
+    #[clap(long, default_value="1000", env = "KAFKA_FLUSH_INTERVAL_MS")]
+    pub kafka_flush_interval_ms: u64,
+
+    /// librdkafka consumer setting of a kafka:// source as key=value, such as security.protocol=SASL_SSL
+    #[clap(long)]
+    pub kafka_setting: Vec<String>,
+
+    /// Keep polling --source-path, a local directory or an s3:// or gs:// prefix, and upload each new or changed file once
+    #[clap(long, default_value="false", env = "WATCH")]
+    pub watch: bool,
+
+    /// Seconds between two polls of --watch
+    #[clap(long, default_value="60", env = "WATCH_INTERVAL")]
+    pub watch_interval: u64,
+
+    /// Local file recording the path and ETag of every file uploaded by --watch
+    #[clap(long, default_value="qdrant-uploader-state.json", env = "WATCH_STATE_PATH")]
+    pub watch_state_path: String,
+
+    /// Only files ending with this suffix are uploaded by --watch, such as .jsonl
+    #[clap(long, env = "WATCH_SUFFIX")]
+    pub watch_suffix: Option<String>,
+
+    /// Directory or prefix of the same storage as --source-path where the files uploaded by --watch are moved
+    #[clap(long, env = "WATCH_MOVE_TO")]
+    pub watch_move_to: Option<String>,
+
+    /// Tag set as key=value on the S3 objects uploaded by --watch
+    #[clap(long)]
+    pub watch_tag: Vec<String>,
+
+    /// Failed uploads of a file after which --watch skips it until it changes, counted in --watch-state-path
+    #[clap(long, default_value="5", env = "WATCH_MAX_ATTEMPTS", value_parser = clap::builder::RangedU64ValueParser::<u32>::new().range(1..))]
+    pub watch_max_attempts: u32,
+
+    /// Address serving /health, /ready and /metrics, such as 0.0.0.0:9090
+    #[clap(long, env = "METRICS_ADDR")]
+    pub metrics_addr: Option<SocketAddr>,
+
+    /// Format of the lines logged to stderr, whose level is set by RUST_LOG
+    #[clap(long, default_value="text", env = "LOG_FORMAT")]
+    pub log_format: LogFormat,
+
+    /// Name of the upload, added to every log line and span
+    #[clap(long, default_value="qdrant-uploader", env = "JOB_NAME")]
+    pub job_name: String,
+
+    /// OTLP gRPC collector receiving the spans of source reads, point conversions and upserts, such as http://localhost:4317
+    #[clap(long, env = "OTEL_EXPORTER_OTLP_ENDPOINT")]
+    pub otlp_endpoint: Option<String>,
+
+    /// File receiving the JSON summary of the run, written to stdout if not provided
+    #[clap(long, env = "SUMMARY_PATH")]
+    pub summary_path: Option<String>,
+
+    /// Upload into a new collection and atomically move the --database-collection alias to it after verifying the point count
+    #[clap(long, default_value="false", env = "BLUE_GREEN")]
+    pub blue_green: bool,
+
+    /// Seconds to wait after a blue/green switch before deleting the previous collection. If not provided, it is kept
+    #[clap(long, env = "DELETE_PREVIOUS_COLLECTION_AFTER")]
+    pub delete_previous_collection_after: Option<u64>,
+
+    /// After uploading, check the collection point count against the distinct uploaded ids and compare a sample of points with the source
+    #[clap(long, default_value="false", env = "VERIFY")]
+    pub verify: bool,
+
+    /// Number of source rows sampled to be retrieved and compared when --verify is used
+    #[clap(long, default_value="100", env = "VERIFY_SAMPLE_SIZE")]
+    pub verify_sample_size: usize,
+
+    /// Maximum absolute difference accepted between source and stored vector coordinates when --verify is used
+    #[clap(long, default_value="0.0001", env = "VERIFY_TOLERANCE")]
+    pub verify_tolerance: f32,
+
+    /// Delete --database-collection, if it exists, and create it again before uploading
+    #[clap(long, default_value="false", env = "RECREATE_COLLECTION")]
+    pub recreate_collection: bool,
+
+    /// Size of the vectors when creating the collection, one per --vector-field-name or a single one for all of them
+    #[clap(long, env = "VECTOR_SIZE")]
+    #[arg(num_args(1..))]
+    pub vector_size: Vec<u64>,
+
+    /// Vector distance when creating the collection
+    #[clap(long, default_value="cosine", env = "DISTANCE")]
+    pub distance: VectorDistance,
+
+    /// HNSW number of edges per node for created collections
+    #[clap(long, env = "HNSW_M")]
+    pub hnsw_m: Option<u64>,
+
+    /// HNSW number of neighbours considered while building the index for created collections
+    #[clap(long, env = "HNSW_EF_CONSTRUCT")]
+    pub hnsw_ef_construct: Option<u64>,
+
+    /// Store the vectors of created collections on disk instead of in memory
+    #[clap(long, default_value="false", env = "ON_DISK_VECTORS")]
+    pub on_disk_vectors: bool,
+
+    /// Quantization of created collections (scalar uses int8, product uses x16 compression)
+    #[clap(long, env = "QUANTIZATION")]
+    pub quantization: Option<QuantizationKind>,
+
+    /// Keep quantized vectors always in memory
+    #[clap(long, default_value="false", env = "QUANTIZATION_ALWAYS_RAM")]
+    pub quantization_always_ram: bool,
+
+    /// Disable indexing of the created collection while uploading and enable it once the upload is over, also when it fails
+    #[clap(long, default_value="false", env = "DEFER_INDEXING")]
+    pub defer_indexing: bool,
+
+    /// Indexing threshold (in kilobytes) set at the end of a --defer-indexing or --bulk-load upload [default: the one of the collection]
+    #[clap(long, env = "INDEXING_THRESHOLD")]
+    pub indexing_threshold: Option<u64>,
+
+    /// Disable indexing of the existing collection while uploading and restore its optimizers configuration afterwards
+    #[clap(long, default_value="false", env = "BULK_LOAD")]
+    pub bulk_load: bool,
+
+    /// After indexing is turned back on, wait until the collection status is green before exiting
+    #[clap(long, default_value="false", env = "WAIT_FOR_GREEN")]
+    pub wait_for_green: bool,
+
+    /// Seconds after which --wait-for-green gives up, failing the run, if the collection is still not green
+    #[clap(long, default_value="3600", env = "WAIT_FOR_GREEN_TIMEOUT")]
+    pub wait_for_green_timeout: u64,
+
+    /// Embedding provider used to generate the vectors of rows missing --embedding-vector-field
+    #[clap(long, env = "EMBEDDING_PROVIDER")]
+    pub embedding_provider: Option<ProviderType>,
+
+    /// Field with the text to be embedded
+    #[clap(long, env = "EMBEDDING_TEXT_FIELD")]
+    pub embedding_text_field: Option<String>,
+
+    /// Vector field filled with the generated embeddings [default: the first --vector-field-name]
+    #[clap(long, env = "EMBEDDING_VECTOR_FIELD")]
+    pub embedding_vector_field: Option<String>,
+
+    /// Embedding model name for the http provider, or path of the model file for the onnx provider
+    #[clap(long, env = "EMBEDDING_MODEL")]
+    pub embedding_model: Option<String>,
+
+    /// Base URL of the OpenAI-compatible embeddings API
+    #[clap(long, default_value="https://api.openai.com/v1", env = "EMBEDDING_ENDPOINT")]
+    pub embedding_endpoint: String,
+
+    /// API key of the embeddings API
+    #[clap(long, env = "EMBEDDING_API_KEY")]
+    pub embedding_api_key: Option<String>,
+
+    /// Path of the tokenizer.json file used by the onnx provider
+    #[clap(long, env = "EMBEDDING_TOKENIZER_PATH")]
+    pub embedding_tokenizer_path: Option<String>,
+
+    /// Maximum number of texts sent in a single embedding request
+    #[clap(long, default_value="64", env = "EMBEDDING_REQUEST_SIZE")]
+    pub embedding_request_size: usize,
+
+    /// Maximum number of embeddings kept in the cache, indexed by text hash
+    #[clap(long, default_value="100000", env = "EMBEDDING_CACHE_SIZE")]
+    pub embedding_cache_size: usize,
 }
 
 
 impl CommandLine {
+    pub fn load_point_mapper(&self) -> anyhow::Result<PointMapper> {
+        let mut point_mapper_builder = PointMapper::builder().vector_field(self.load_vector_field_name()?);
+        if let Some(id_field_name) = self.id_field_name.as_ref() {
+            point_mapper_builder = point_mapper_builder.id_field(id_field_name);
+        }
+        if let Some(payload_fields) = self.load_payload_field()? {
+            point_mapper_builder = point_mapper_builder.payload_field(payload_fields);
+        }
+        if let Some(shard_key_field) = self.shard_key_field.as_ref() {
+            point_mapper_builder = point_mapper_builder.shard_key_field(shard_key_field);
+        }
+        point_mapper_builder.build()
+    }
+
+    /// API key, timeouts and TLS settings of the Qdrant connection. The key file and the PEM files are read here
+    pub async fn load_connection_options(&self) -> anyhow::Result<ConnectionOptions> {
+        let api_key = match (self.api_key.as_ref(), self.api_key_file.as_ref()) {
+            (Some(_), Some(_)) => anyhow::bail!("--api-key and --api-key-file can not be used together"),
+            (Some(api_key), None) => Some(api_key.to_owned()),
+            (None, Some(api_key_file)) => {
+                let api_key = tokio::fs::read_to_string(api_key_file).await
+                    .with_context(|| format!("Could not read the API key file {api_key_file}"))?;
+                Some(api_key.trim().to_owned())
+            },
+            (None, None) => None,
+        };
+
+        let uses_tls_settings = self.tls_ca_cert.is_some() || self.tls_client_cert.is_some()
+            || self.tls_client_key.is_some() || self.tls_server_name.is_some();
+        let tls =
+            if uses_tls_settings {
+                Some(TlsSettings::load(self.tls_ca_cert.as_deref(), self.tls_client_cert.as_deref(), self.tls_client_key.as_deref(),
+                    self.tls_server_name.clone())?)
+            } else {
+                None
+            };
+
+        Ok(ConnectionOptions {
+            api_key,
+            request_timeout: self.request_timeout.map(Duration::from_secs),
+            connect_timeout: self.connect_timeout.map(Duration::from_secs),
+            keep_alive_interval: self.grpc_keep_alive_interval.map(Duration::from_secs),
+            keep_alive_timeout: self.grpc_keep_alive_timeout.map(Duration::from_secs),
+            keep_alive_while_idle: self.keep_alive_while_idle,
+            tls,
+        })
+    }
+
+    /// Fails on upsert options that can not be combined
+    pub fn validate_upsert_options(&self) -> anyhow::Result<()> {
+        if self.no_wait && self.adaptive_chunk_size {
+            anyhow::bail!("--adaptive-chunk-size measures how long upserts take to be applied, it can not be used with --no-wait");
+        }
+        Ok(())
+    }
+
+    /// Fails on options that need the upload to end, when the source never ends
+    pub fn validate_source_options(&self) -> anyhow::Result<()> {
+        let finite_upload = self.blue_green || self.bulk_load || self.defer_indexing || self.vector_source_path.is_some() || self.verify;
+        if self.source_path.starts_with("kafka://") && finite_upload {
+            anyhow::bail!("A kafka:// source never ends, it can not be used with --blue-green, --bulk-load, --defer-indexing, \
+                --vector-source-path or --verify");
+        }
+        Ok(())
+    }
+
+    /// Default transports plus s3://, gs:// and http(s):// configured from the command line
+    pub async fn load_transport_registry(&self) -> anyhow::Result<TransportRegistry> {
+        let s3_transport = S3Transport::new(self.s3_options()).await?;
+        let gs_transport = S3Transport::new(self.gs_options()).await?;
+
+        let mut http_headers = HeaderMap::new();
+        for header in self.http_header.iter() {
+            let (name, value) = parse_header(header)?;
+            http_headers.append(name, value);
+        }
+
+        let mut transports = TransportRegistry::default();
+        transports
+            .register("s3", s3_transport)
+            .register("gs", gs_transport)
+            .register("http", HttpTransport::new(http_headers.clone(), self.http_max_redirects, self.http_max_retries)?)
+            .register("https", HttpTransport::new(http_headers, self.http_max_redirects, self.http_max_retries)?);
+        Ok(transports)
+    }
+
+    fn s3_options(&self) -> S3Options {
+        S3Options {
+            access_key: self.s3_access_key.clone(),
+            secret_key: self.s3_secret_access_key.clone(),
+            session_token: self.s3_session_token.clone(),
+            profile: self.s3_profile.clone(),
+            role_arn: self.s3_role_arn.clone(),
+            role_session_name: Some(self.s3_role_session_name.clone()),
+            external_id: self.s3_external_id.clone(),
+            region: self.s3_region.clone(),
+            endpoint: self.s3_endpoint.clone(),
+            virtual_hosted_style: self.s3_virtual_hosted_style,
+            max_retries: self.s3_max_retries,
+        }
+    }
+
+    /// HMAC keys only, Cloud Storage must never receive AWS credentials from the default chain
+    fn gs_options(&self) -> S3Options {
+        S3Options {
+            access_key: Some(self.gs_access_key.clone().unwrap_or_default()),
+            secret_key: Some(self.gs_secret_access_key.clone().unwrap_or_default()),
+            region: Some("auto".to_owned()),
+            endpoint: Some(self.gs_endpoint.clone()),
+            max_retries: self.s3_max_retries,
+            ..S3Options::default()
+        }
+    }
+
+    /// A watcher of --source-path, the directory or prefix given to --watch
+    pub async fn load_watcher(&self, metrics: Arc<Metrics>) -> anyhow::Result<Watcher> {
+        if self.blue_green || self.bulk_load || self.defer_indexing || self.vector_source_path.is_some() || self.verify
+            || self.tenant_index_field.is_some() || self.shard_key_field.is_some() {
+            anyhow::bail!("--watch can not be used with --blue-green, --bulk-load, --defer-indexing, --vector-source-path, --verify, \
+                --tenant-index-field or --shard-key-field");
+        }
+
+        let processed_action = match (self.watch_move_to.as_ref(), self.watch_tag.is_empty()) {
+            (Some(_), false) => anyhow::bail!("--watch-move-to and --watch-tag can not be used together"),
+            (Some(destination), true) if destination.trim_end_matches('/') == self.source_path.trim_end_matches('/') => {
+                anyhow::bail!("--watch-move-to must be another location than --source-path")
+            },
+            (Some(destination), true) => ProcessedAction::MoveTo(destination.clone()),
+            (None, false) => {
+                let tags = self.watch_tag.iter()
+                    .map(|tag| match tag.split_once('=') {
+                        Some((key, value)) => Ok((key.trim().to_owned(), value.trim().to_owned())),
+                        None => anyhow::bail!("Invalid tag {tag}, expected key=value"),
+                    })
+                    .collect::<anyhow::Result<_>>()?;
+                ProcessedAction::Tag(tags)
+            },
+            (None, true) => ProcessedAction::Keep,
+        };
+
+        let state = WatchState::load(&self.watch_state_path).await?;
+        let poll_interval = Duration::from_secs(self.watch_interval);
+        let watcher = match self.source_path.split_once("://") {
+            Some(("s3", _)) => Watcher::new(S3Prefix::new(&self.source_path, self.s3_options()).await?, state, poll_interval),
+            Some(("gs", _)) => Watcher::new(S3Prefix::new(&self.source_path, self.gs_options()).await?, state, poll_interval),
+            Some(("file", _)) | None => Watcher::new(LocalDirectory::new(&self.source_path), state, poll_interval),
+            Some((scheme, _)) => anyhow::bail!("--watch supports local directories and s3:// or gs:// prefixes, not {scheme}://"),
+        };
+
+        let watcher = watcher.processed_action(processed_action).max_attempts(self.watch_max_attempts).metrics(metrics);
+        match self.watch_suffix.as_ref() {
+            Some(suffix) => Ok(watcher.suffix(suffix)),
+            None => Ok(watcher),
+        }
+    }
+
+    /// The source rows, paired with the vectors of --vector-source-path if given
+    pub async fn load_record_source(&self, transports: &TransportRegistry) -> anyhow::Result<Box<dyn RecordSource>> {
+        if self.source_path.starts_with("kafka://") {
+            return self.load_kafka_source();
+        }
+
+        let Some(vector_source_path) = self.vector_source_path.as_ref() else {
+            let dataset = Dataset::load(&self.source_path, &self.source_file_type, transports).await?;
+            return Ok(Box::new(dataset));
+        };
+
+        let vector_field = match self.vector_source_field.as_ref().or(self.vector_field_name.first()) {
+            Some(vector_field) if self.vector_field_name.contains(vector_field) => vector_field,
+            _ => anyhow::bail!("--vector-source-field must be one of the --vector-field-name values")
+        };
+
+        let dataset = PairedDataset::load(&self.source_path, &self.source_file_type, vector_source_path, self.vector_file_type,
+            &self.npz_array, vector_field, transports).await?;
+        Ok(Box::new(dataset))
+    }
+
+    #[cfg(feature = "kafka")]
+    fn load_kafka_source(&self) -> anyhow::Result<Box<dyn RecordSource>> {
+        let flush_interval = Duration::from_millis(self.kafka_flush_interval_ms);
+        let mut kafka_options = KafkaOptions::from_source_path(&self.source_path, &self.kafka_group_id, flush_interval)?;
+        for setting in self.kafka_setting.iter() {
+            let Some((key, value)) = setting.split_once('=') else {
+                anyhow::bail!("Invalid Kafka setting {setting}, expected key=value");
+            };
+            kafka_options.client_settings.push((key.trim().to_owned(), value.trim().to_owned()));
+        }
+
+        Ok(Box::new(KafkaSource::new(kafka_options)?))
+    }
+
+    #[cfg(not(feature = "kafka"))]
+    fn load_kafka_source(&self) -> anyhow::Result<Box<dyn RecordSource>> {
+        anyhow::bail!("kafka:// sources require qdrant-uploader to be built with the kafka feature")
+    }
+
     pub fn load_payload_field(&self) -> anyhow::Result<Option<FieldName>> {
         if self.payload_field.is_none() {
             Ok(None)
@@ -92,6 +619,58 @@ impl CommandLine {
         }
     }
 
+    pub fn load_collection_settings(&self) -> anyhow::Result<CollectionSettings> {
+        if self.recreate_collection && self.blue_green {
+            anyhow::bail!("--recreate-collection and --blue-green can not be used together");
+        } else if self.recreate_collection && self.shard_key_field.is_some() {
+            anyhow::bail!("--shard-key-field requires a collection created with custom sharding and its shard keys, it can not be used with --recreate-collection");
+        } else if self.recreate_collection && self.vector_size.is_empty() {
+            anyhow::bail!("When using --recreate-collection=true, --vector-size must be provided");
+        } else if self.bulk_load && (self.recreate_collection || self.blue_green) {
+            anyhow::bail!("--bulk-load applies to an existing collection, use --defer-indexing with --recreate-collection or --blue-green");
+        } else if self.defer_indexing && !(self.recreate_collection || self.blue_green) {
+            anyhow::bail!("--defer-indexing is only available when the collection is created, with --recreate-collection or --blue-green");
+        } else {
+            let settings = CollectionSettings {
+                vector_sizes: self.vector_size.clone(),
+                distance: Some(self.distance),
+                hnsw_m: self.hnsw_m,
+                hnsw_ef_construct: self.hnsw_ef_construct,
+                on_disk_vectors: self.on_disk_vectors,
+                quantization: self.quantization,
+                quantization_always_ram: self.quantization_always_ram,
+                defer_indexing: self.defer_indexing,
+                indexing_threshold: self.indexing_threshold,
+            };
+            Ok(settings)
+        }
+    }
+
+    pub fn load_embedding_stage(&self) -> anyhow::Result<Option<EmbeddingStage>> {
+        let Some(provider_type) = self.embedding_provider.as_ref() else {
+            return Ok(None);
+        };
+
+        let Some(text_field) = self.embedding_text_field.as_ref() else {
+            anyhow::bail!("When using --embedding-provider, --embedding-text-field must be provided");
+        };
+
+        let Some(model) = self.embedding_model.as_ref() else {
+            anyhow::bail!("When using --embedding-provider, --embedding-model must be provided");
+        };
+
+        let vector_field = match self.embedding_vector_field.as_ref().or(self.vector_field_name.first()) {
+            Some(vector_field) if self.vector_field_name.contains(vector_field) => vector_field,
+            _ => anyhow::bail!("--embedding-vector-field must be one of the --vector-field-name values")
+        };
+
+        let embedder = Embedder::load(provider_type, model, &self.embedding_endpoint, self.embedding_api_key.clone(),
+            self.embedding_tokenizer_path.clone())?;
+        let embedding_stage = EmbeddingStage::new(embedder, text_field, vector_field, self.embedding_request_size, self.embedding_cache_size);
+
+        Ok(Some(embedding_stage))
+    }
+
     pub fn load_vector_field_name(&self)  -> anyhow::Result<FieldName> {
         if self.vector_field_name.len() > 1 && self.upload_non_named_vector {
             anyhow::bail!("When using --updload-non-named-vector=true, at most one value must be provided for --vector-field-name");
@@ -0,0 +1,8 @@
+use async_trait::async_trait;
+
+#[async_trait]
+pub trait EmbedderExt {
+
+    /// Returns one vector per text, in the same order
+    async fn embed(&self, texts: &[String]) -> anyhow::Result<Vec<Vec<f32>>>;
+}
@@ -0,0 +1,99 @@
+use std::collections::{HashMap, HashSet};
+
+use sha2::{Digest, Sha256};
+use tokio::sync::RwLock;
+
+use super::{Embedder, EmbedderExt};
+
+type TextHash = [u8; 32];
+
+/// Fills the vector field of rows that lack it with the embedding of their text field, before
+/// the rows are converted to points. Embeddings are requested in batches and cached by text hash.
+pub struct EmbeddingStage {
+    embedder: Embedder,
+    text_field: String,
+    vector_field: String,
+    request_size: usize,
+
+    cache: RwLock<HashMap<TextHash, Vec<f32>>>,
+    cache_capacity: usize,
+}
+
+impl EmbeddingStage {
+    pub fn new(embedder: Embedder, text_field: &str, vector_field: &str, request_size: usize, cache_capacity: usize) -> Self {
+        EmbeddingStage {
+            embedder,
+            text_field: text_field.to_owned(),
+            vector_field: vector_field.to_owned(),
+            request_size: request_size.max(1),
+            cache: RwLock::new(HashMap::new()),
+            cache_capacity,
+        }
+    }
+
+    pub async fn fill_missing_vectors(&self, batch: &mut [serde_json::Value]) -> anyhow::Result<()> {
+        let missing_rows: Vec<(usize, TextHash, String)> = batch.iter().enumerate()
+            .filter(|(_, row)| row.get(&self.vector_field).map(|vector| vector.is_null()).unwrap_or(true))
+            .filter_map(|(position, row)| {
+                row.get(&self.text_field)
+                    .and_then(|text| text.as_str())
+                    .map(|text| (position, hash_text(text), text.to_owned()))
+            })
+            .collect();
+
+        if missing_rows.is_empty() {
+            return Ok(());
+        }
+
+        let embeddings = self.load_embeddings(&missing_rows).await?;
+
+        for (position, text_hash, _) in missing_rows.iter() {
+            if let (Some(embedding), Some(row)) = (embeddings.get(text_hash), batch[*position].as_object_mut()) {
+                row.insert(self.vector_field.clone(), serde_json::Value::from(embedding.clone()));
+            }
+        }
+
+        tracing::info!("Embeddings generated for {rows} rows missing field {field}", rows=missing_rows.len(), field=self.vector_field);
+        Ok(())
+    }
+
+    async fn load_embeddings(&self, missing_rows: &[(usize, TextHash, String)]) -> anyhow::Result<HashMap<TextHash, Vec<f32>>> {
+        let mut embeddings = HashMap::new();
+        let mut uncached_texts: Vec<(TextHash, String)> = Vec::new();
+        let mut seen_hashes = HashSet::new();
+
+        {
+            let cache = self.cache.read().await;
+            for (_, text_hash, text) in missing_rows {
+                if !seen_hashes.insert(*text_hash) {
+                    continue;
+                }
+
+                match cache.get(text_hash) {
+                    Some(embedding) => { embeddings.insert(*text_hash, embedding.clone()); },
+                    None => uncached_texts.push((*text_hash, text.to_owned()))
+                }
+            }
+        }
+
+        for request in uncached_texts.chunks(self.request_size) {
+            let texts: Vec<String> = request.iter().map(|(_, text)| text.to_owned()).collect();
+            let request_embeddings = self.embedder.embed(&texts).await?;
+
+            let mut cache = self.cache.write().await;
+            for ((text_hash, _), embedding) in request.iter().zip(request_embeddings) {
+                if cache.len() < self.cache_capacity {
+                    cache.insert(*text_hash, embedding.clone());
+                }
+                embeddings.insert(*text_hash, embedding);
+            }
+        }
+
+        Ok(embeddings)
+    }
+}
+
+
+fn hash_text(text: &str) -> TextHash {
+    Sha256::digest(text.as_bytes()).into()
+}
@@ -0,0 +1,76 @@
+use async_trait::async_trait;
+use serde_json::json;
+
+use super::embedder_ext::EmbedderExt;
+
+/// Calls an OpenAI-compatible `POST {endpoint}/embeddings`
+pub struct HttpEmbedder {
+    client: reqwest::Client,
+    embeddings_url: String,
+    api_key: Option<String>,
+    model: String,
+}
+
+impl HttpEmbedder {
+    pub fn new(endpoint: &str, api_key: Option<String>, model: &str) -> anyhow::Result<Self> {
+        let client = reqwest::Client::builder().build()?;
+        let embeddings_url = format!("{endpoint}/embeddings", endpoint=endpoint.trim_end_matches('/'));
+
+        let embedder = HttpEmbedder {
+            client,
+            embeddings_url,
+            api_key,
+            model: model.to_owned(),
+        };
+
+        Ok(embedder)
+    }
+}
+
+
+#[async_trait]
+impl EmbedderExt for HttpEmbedder {
+
+    async fn embed(&self, texts: &[String]) -> anyhow::Result<Vec<Vec<f32>>> {
+        let body = json!({
+            "model": self.model,
+            "input": texts,
+        });
+
+        let mut request = self.client.post(&self.embeddings_url).json(&body);
+        if let Some(api_key) = self.api_key.as_ref() {
+            request = request.bearer_auth(api_key);
+        }
+
+        let response: serde_json::Value = request.send().await?.error_for_status()?.json().await?;
+        parse_embeddings_response(&response, texts.len())
+    }
+}
+
+
+/// The `data` entries carry an `index`, which is used to put the embeddings back in request order
+fn parse_embeddings_response(response: &serde_json::Value, expected_length: usize) -> anyhow::Result<Vec<Vec<f32>>> {
+    let Some(data) = response.get("data").and_then(|data| data.as_array()) else {
+        anyhow::bail!("Invalid embeddings response, missing data array");
+    };
+
+    let mut embeddings = vec![Vec::new(); expected_length];
+    for (position, entry) in data.iter().enumerate() {
+        let index = entry.get("index").and_then(|index| index.as_u64()).map(|index| index as usize).unwrap_or(position);
+        let Some(embedding) = entry.get("embedding").and_then(|embedding| embedding.as_array()) else {
+            anyhow::bail!("Invalid embeddings response, missing embedding at position {position}");
+        };
+
+        if index >= expected_length {
+            anyhow::bail!("Invalid embeddings response, index {index} out of {expected_length} inputs");
+        }
+
+        embeddings[index] = embedding.iter().map(|coordinate| coordinate.as_f64().unwrap_or_default() as f32).collect();
+    }
+
+    if embeddings.iter().any(|embedding| embedding.is_empty()) {
+        anyhow::bail!("Invalid embeddings response, {expected_length} embeddings were expected but {received} were received", received=data.len());
+    }
+
+    Ok(embeddings)
+}
@@ -0,0 +1,75 @@
+use self::http_embedder::HttpEmbedder;
+#[cfg(feature = "onnx")]
+use self::onnx_embedder::OnnxEmbedder;
+
+mod embedder_ext;
+mod embedding_stage;
+mod http_embedder;
+#[cfg(feature = "onnx")]
+mod onnx_embedder;
+mod provider_type;
+
+use async_trait::async_trait;
+pub use embedder_ext::EmbedderExt;
+pub use embedding_stage::EmbeddingStage;
+pub use provider_type::ProviderType;
+
+
+pub enum Embedder {
+    Http(HttpEmbedder),
+    #[cfg(feature = "onnx")]
+    Onnx(Box<OnnxEmbedder>),
+}
+
+impl Embedder {
+    pub fn load(provider_type: &ProviderType, model: &str, endpoint: &str, api_key: Option<String>,
+        tokenizer_path: Option<String>) -> anyhow::Result<Embedder> {
+        match provider_type {
+            ProviderType::Http => {
+                let embedder = HttpEmbedder::new(endpoint, api_key, model)?;
+                Ok(Embedder::Http(embedder))
+            },
+            ProviderType::Onnx => load_onnx_embedder(model, tokenizer_path)
+        }
+    }
+}
+
+
+#[async_trait]
+impl EmbedderExt for Embedder {
+
+    async fn embed(&self, texts: &[String]) -> anyhow::Result<Vec<Vec<f32>>> {
+        match self {
+            Embedder::Http(embedder) => embedder.embed(texts).await,
+            #[cfg(feature = "onnx")]
+            Embedder::Onnx(embedder) => embedder.embed(texts).await,
+        }
+    }
+}
+
+
+/// Scales a vector to unit length, the way Qdrant stores vectors of cosine collections. Zero vectors are kept as is
+pub(crate) fn normalize(vector: &[f32]) -> Vec<f32> {
+    let norm = vector.iter().map(|coordinate| coordinate * coordinate).sum::<f32>().sqrt();
+    if norm == 0.0 {
+        vector.to_vec()
+    } else {
+        vector.iter().map(|coordinate| coordinate / norm).collect()
+    }
+}
+
+
+#[cfg(feature = "onnx")]
+fn load_onnx_embedder(model_path: &str, tokenizer_path: Option<String>) -> anyhow::Result<Embedder> {
+    let Some(tokenizer_path) = tokenizer_path else {
+        anyhow::bail!("--embedding-tokenizer-path must be provided for the onnx embedding provider");
+    };
+
+    let embedder = OnnxEmbedder::new(model_path, &tokenizer_path)?;
+    Ok(Embedder::Onnx(Box::new(embedder)))
+}
+
+#[cfg(not(feature = "onnx"))]
+fn load_onnx_embedder(_model_path: &str, _tokenizer_path: Option<String>) -> anyhow::Result<Embedder> {
+    anyhow::bail!("The onnx embedding provider requires qdrant-uploader to be built with the onnx feature")
+}
@@ -0,0 +1,123 @@
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use ort::session::Session;
+use ort::value::Tensor;
+use tokenizers::{PaddingParams, Tokenizer, TruncationParams};
+
+use super::embedder_ext::EmbedderExt;
+use super::normalize;
+
+/// Maximum number of tokens of BERT-like models, used when neither the model nor the tokenizer tell theirs
+const DEFAULT_MAX_SEQUENCE_LENGTH: usize = 512;
+
+/// Runs a sentence embedding ONNX model on CPU, mean pooling the last hidden state over the attention mask.
+/// Texts longer than the maximum sequence length of the model are truncated
+pub struct OnnxEmbedder {
+    session: Arc<Mutex<Session>>,
+    tokenizer: Tokenizer,
+    uses_token_type_ids: bool,
+}
+
+impl OnnxEmbedder {
+    pub fn new(model_path: &str, tokenizer_path: &str) -> anyhow::Result<Self> {
+        let session = Session::builder()?.commit_from_file(model_path)?;
+        let uses_token_type_ids = session.inputs.iter().any(|input| input.name == "token_type_ids");
+
+        let mut tokenizer = Tokenizer::from_file(tokenizer_path).map_err(anyhow::Error::msg)?;
+        let max_sequence_length = max_sequence_length(&session, &tokenizer);
+        tokenizer.with_padding(Some(PaddingParams::default()));
+        tokenizer.with_truncation(Some(TruncationParams { max_length: max_sequence_length, ..Default::default() }))
+            .map_err(anyhow::Error::msg)?;
+
+        tracing::info!("Embedding model {model_path} loaded, texts are truncated to {max_sequence_length} tokens");
+
+        let embedder = OnnxEmbedder {
+            session: Arc::new(Mutex::new(session)),
+            tokenizer,
+            uses_token_type_ids,
+        };
+
+        Ok(embedder)
+    }
+}
+
+
+#[async_trait]
+impl EmbedderExt for OnnxEmbedder {
+
+    async fn embed(&self, texts: &[String]) -> anyhow::Result<Vec<Vec<f32>>> {
+        let encodings = self.tokenizer.encode_batch(texts.to_vec(), true).map_err(anyhow::Error::msg)?;
+        let batch_length = encodings.len();
+        let sequence_length = encodings.first().map(|encoding| encoding.len()).unwrap_or_default();
+
+        let input_ids: Vec<i64> = encodings.iter().flat_map(|encoding| encoding.get_ids().iter().map(|id| *id as i64)).collect();
+        let attention_mask: Vec<i64> = encodings.iter().flat_map(|encoding| encoding.get_attention_mask().iter().map(|mask| *mask as i64)).collect();
+        let token_type_ids: Vec<i64> = encodings.iter().flat_map(|encoding| encoding.get_type_ids().iter().map(|type_id| *type_id as i64)).collect();
+
+        let shape = [batch_length, sequence_length];
+        let mut inputs = ort::inputs![
+            "input_ids" => Tensor::from_array((shape, input_ids))?,
+            "attention_mask" => Tensor::from_array((shape, attention_mask.clone()))?,
+        ];
+        if self.uses_token_type_ids {
+            inputs.push(("token_type_ids".into(), Tensor::from_array((shape, token_type_ids))?.into()));
+        }
+
+        // Inference takes long enough to stall the other tasks of the runtime
+        let session = self.session.clone();
+        let embeddings = tokio::task::spawn_blocking(move || -> anyhow::Result<Vec<Vec<f32>>> {
+            let mut session = session.lock().map_err(|_| anyhow::anyhow!("Embedding model session lock poisoned"))?;
+            let outputs = session.run(inputs)?;
+            let (output_shape, hidden_states) = outputs[0].try_extract_tensor::<f32>()?;
+            let hidden_size = output_shape.last().copied().unwrap_or_default() as usize;
+
+            let embeddings = (0..batch_length)
+                .map(|row| mean_pooling(hidden_states, &attention_mask, row, sequence_length, hidden_size))
+                .map(|embedding| normalize(&embedding))
+                .collect();
+            Ok(embeddings)
+        }).await??;
+
+        Ok(embeddings)
+    }
+}
+
+
+/// The fixed length of the `input_ids` of the model, else the truncation of the tokenizer
+fn max_sequence_length(session: &Session, tokenizer: &Tokenizer) -> usize {
+    let model_length = session.inputs.iter()
+        .find(|input| input.name == "input_ids")
+        .and_then(|input| input.input_type.tensor_shape())
+        .and_then(|shape| shape.get(1).copied())
+        .filter(|length| *length > 0);
+
+    match model_length {
+        Some(length) => length as usize,
+        None => tokenizer.get_truncation().map(|truncation| truncation.max_length).unwrap_or(DEFAULT_MAX_SEQUENCE_LENGTH),
+    }
+}
+
+
+fn mean_pooling(hidden_states: &[f32], attention_mask: &[i64], row: usize, sequence_length: usize, hidden_size: usize) -> Vec<f32> {
+    let mut embedding = vec![0.0; hidden_size];
+    let mut tokens = 0.0;
+
+    for token in 0..sequence_length {
+        if attention_mask[row * sequence_length + token] == 0 {
+            continue;
+        }
+
+        let offset = (row * sequence_length + token) * hidden_size;
+        embedding.iter_mut()
+            .zip(hidden_states[offset..offset + hidden_size].iter())
+            .for_each(|(value, hidden_state)| *value += hidden_state);
+        tokens += 1.0;
+    }
+
+    if tokens > 0.0 {
+        embedding.iter_mut().for_each(|value| *value /= tokens);
+    }
+
+    embedding
+}
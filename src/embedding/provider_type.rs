@@ -0,0 +1,7 @@
+#[derive(clap::ValueEnum, Debug, Clone)]
+pub enum ProviderType {
+    /// OpenAI-compatible HTTP embeddings endpoint
+    Http,
+    /// Local ONNX model run on CPU
+    Onnx
+}
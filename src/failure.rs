@@ -0,0 +1,30 @@
+use std::fmt;
+
+/// What an upload error comes from, attached as context to the errors of an [`crate::UploadJob`] so that callers can
+/// react to each kind differently. It is found back with [`FailureKind::of`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureKind {
+    /// Invalid options or settings, detected before anything is uploaded
+    Config,
+    /// The source could not be opened, read or committed
+    Source,
+    /// A request to Qdrant failed
+    Qdrant,
+}
+
+impl FailureKind {
+    /// The outermost kind attached to the error, if any
+    pub fn of(error: &anyhow::Error) -> Option<FailureKind> {
+        error.downcast_ref::<FailureKind>().copied()
+    }
+}
+
+impl fmt::Display for FailureKind {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FailureKind::Config => write!(formatter, "Invalid configuration"),
+            FailureKind::Source => write!(formatter, "Could not read the source"),
+            FailureKind::Qdrant => write!(formatter, "Qdrant request failed"),
+        }
+    }
+}
@@ -0,0 +1,48 @@
+//! Upload rows of JSON, CSV, Arrow or Avro files, local or remote, and of Kafka topics to Qdrant.
+//!
+//! An [`UploadJob`] reads rows from a [`RecordSource`], converts them to points with a [`PointMapper`]
+//! and writes them to a [`Sink`]. The `qdrant-uploader` binary is a command line front end for it.
+//!
+//! ```no_run
+//! use qdrant_uploader::{PointMapper, UploadJob};
+//! use qdrant_uploader::persistence::DatabaseClient;
+//! use qdrant_uploader::persistence::files_system::{Dataset, FileType, TransportRegistry};
+//! use qdrant_uploader::persistence::vector_field_name::FieldName;
+//!
+//! # async fn upload() -> anyhow::Result<()> {
+//! let source = Dataset::load("data/items.jsonl", &FileType::JSON, &TransportRegistry::default()).await?;
+//! let point_mapper = PointMapper::builder()
+//!     .id_field("id")
+//!     .vector_field(FieldName::Single("embedding".to_owned()))
+//!     .build()?;
+//! let sink = DatabaseClient::new("http://localhost:6334", &None, "items", 256).await?;
+//!
+//! let mut upload_job = UploadJob::builder()
+//!     .source(source)
+//!     .point_mapper(point_mapper)
+//!     .sink(sink)
+//!     .batch_size(1000)
+//!     .build()?;
+//!
+//! let points_uploaded = upload_job.run().await?;
+//! # Ok(())
+//! # }
+//! ```
+
+mod collection_upload;
+pub mod embedding;
+mod failure;
+pub mod monitoring;
+pub mod persistence;
+mod record_source;
+mod shutdown;
+mod sink;
+mod upload_job;
+pub mod watch;
+
+pub use collection_upload::CollectionUpload;
+pub use failure::FailureKind;
+pub use persistence::{PointMapper, PointMapperBuilder};
+pub use record_source::{RecordSource, SourceStatistics};
+pub use sink::Sink;
+pub use upload_job::{UploadJob, UploadJobBuilder};
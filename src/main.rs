@@ -1,30 +1,149 @@
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
 use clap::Parser;
-use persistence::files_system::Dataset;
-use processors::run_transference;
-use crate::{command_line::CommandLine, persistence::DatabaseClient};
+use tracing::Instrument;
+use qdrant_uploader::{CollectionUpload, FailureKind, PointMapper, UploadJob};
+use qdrant_uploader::embedding::EmbeddingStage;
+use qdrant_uploader::monitoring::{serve_metrics, Metrics};
+use qdrant_uploader::persistence::files_system::{Dataset, TransportRegistry};
+use qdrant_uploader::persistence::{DatabaseClient, UploadVerifier};
+use crate::command_line::CommandLine;
+use crate::run_summary::{RunSummary, SourceReport};
+use crate::telemetry::init_telemetry;
 
-mod persistence;
 mod command_line;
-mod processors;
-
+mod run_summary;
+mod telemetry;
 
 #[tokio::main(flavor="current_thread")]
-async fn main() -> anyhow::Result<()> {
-    env_logger::init();
+async fn main() -> ExitCode {
+    let started = Instant::now();
     let arguments = CommandLine::parse();
-    let payload_fields = arguments.load_payload_field()?;
-    let vector_fields = arguments.load_vector_field_name()?;
+    let metrics = Arc::new(Metrics::default());
+    let (_telemetry_guard, result) = match init_telemetry(arguments.log_format, arguments.otlp_endpoint.as_deref()) {
+        Ok(telemetry_guard) => {
+            let job_span = tracing::info_span!("upload", job=%arguments.job_name, collection=%arguments.database_collection);
+            (Some(telemetry_guard), run_transference(&arguments, metrics.clone()).instrument(job_span).await)
+        },
+        Err(error) => (None, Err(error.context(FailureKind::Config))),
+    };
+    if let Err(error) = result.as_ref() {
+        eprintln!("Error: {error:?}");
+    }
+
+    let summary = RunSummary::new(result, metrics.snapshot(), started.elapsed());
+    if let Err(error) = summary.write(arguments.summary_path.as_deref()).await {
+        eprintln!("Could not write the run summary: {error:?}");
+    }
+    summary.exit_code()
+}
+
+
+/// Errors are tagged with a [`FailureKind`], which sets the exit code
+async fn run_transference(arguments: &CommandLine, metrics: Arc<Metrics>) -> anyhow::Result<SourceReport> {
+    arguments.validate_source_options().context(FailureKind::Config)?;
+    let point_mapper = arguments.load_point_mapper().context(FailureKind::Config)?;
+    let collection_settings = arguments.load_collection_settings().context(FailureKind::Config)?;
+    let embedding_stage = arguments.load_embedding_stage().context(FailureKind::Config)?.map(Arc::new);
+
+    if let Some(metrics_addr) = arguments.metrics_addr {
+        serve_metrics(metrics_addr, metrics.clone()).context(FailureKind::Config)?;
+    }
     
-    let database_client =
-        DatabaseClient::new(&arguments.connection_string, &arguments.api_key, &arguments.database_collection,
-            arguments.id_field_name.clone(), vector_fields,
-            payload_fields, arguments.chunk_size,
-        ).await?;
+    arguments.validate_upsert_options().context(FailureKind::Config)?;
+    let connection_options = arguments.load_connection_options().await.context(FailureKind::Config)?;
+    let mut database_client =
+        DatabaseClient::connect(&arguments.connection_string, &connection_options, &arguments.database_collection, arguments.chunk_size).await
+            .context(FailureKind::Qdrant)?
+            .with_write_ordering(arguments.write_ordering)
+            .with_wait(!arguments.no_wait)
+            .with_max_chunk_bytes(arguments.max_batch_bytes.map(|max_batch_bytes| max_batch_bytes as usize))
+            .with_rate_limit(arguments.max_points_per_second, arguments.max_bytes_per_second)
+            .with_metrics(metrics.clone());
+    if arguments.adaptive_chunk_size {
+        database_client = database_client
+            .with_adaptive_chunk_size(arguments.min_chunk_size, Duration::from_millis(arguments.target_upsert_latency_ms));
+    }
+
+    let transports = arguments.load_transport_registry().await.context(FailureKind::Config)?;
+
+    if arguments.watch {
+        let mut watcher = arguments.load_watcher(metrics.clone()).await.context(FailureKind::Config)?;
+        if arguments.recreate_collection {
+            database_client.recreate_collection(&collection_settings, point_mapper.vector_field()).await.context(FailureKind::Qdrant)?;
+        }
+        metrics.set_ready();
+        watcher.run(|path| upload_watched_object(path, arguments, &point_mapper, embedding_stage.as_ref(), &database_client, &transports, &metrics)).await?;
+        return Ok(SourceReport::default());
+    }
+
+    let record_source = arguments.load_record_source(&transports).await.context(FailureKind::Source)?;
+    metrics.set_ready();
+
+    let mut upload_job_builder = UploadJob::builder()
+        .source(record_source)
+        .point_mapper(point_mapper.clone())
+        .batch_size(arguments.batch_size)
+        .metrics(metrics);
+
+    if let Some(embedding_stage) = embedding_stage {
+        upload_job_builder = upload_job_builder.embedding_stage(embedding_stage);
+    }
+
+    if arguments.verify {
+        upload_job_builder = upload_job_builder.verifier(UploadVerifier::new(arguments.verify_sample_size, arguments.verify_tolerance));
+    }
+
+    if let Some(tenant_index_field) = arguments.tenant_index_field.as_ref() {
+        if !arguments.recreate_collection {
+            database_client.validate_payload_index(tenant_index_field).await.context(FailureKind::Qdrant)?;
+        }
+    }
+
+    if arguments.recreate_collection {
+        database_client.recreate_collection(&collection_settings, point_mapper.vector_field()).await.context(FailureKind::Qdrant)?;
+        if let Some(tenant_index_field) = arguments.tenant_index_field.as_ref() {
+            database_client.create_tenant_index(tenant_index_field).await.context(FailureKind::Qdrant)?;
+        }
+    }
+
+    let upload_job = CollectionUpload::new(&database_client, &collection_settings)
+        .bulk_load(arguments.bulk_load)
+        .blue_green(arguments.blue_green)
+        .wait_for_green(arguments.wait_for_green.then(|| Duration::from_secs(arguments.wait_for_green_timeout)))
+        .delete_previous_collection_after(arguments.delete_previous_collection_after.map(Duration::from_secs))
+        .run(upload_job_builder)
+        .await?;
+
+    Ok(source_report(&upload_job))
+}
+
+
+fn source_report(upload_job: &UploadJob) -> SourceReport {
+    SourceReport {
+        checksum: upload_job.source_checksum(),
+        duplicate_ids: upload_job.verifier().map(UploadVerifier::duplicate_ids),
+    }
+}
+
 
-    let dataset = Dataset::load(&arguments.source_path, &arguments.source_file_type, arguments.s3_access_key, arguments.s3_secret_access_key,
-                                        arguments.s3_region, arguments.s3_endpoint).await?;
+/// Uploads one file found by --watch into the collection, returning the number of points upserted
+async fn upload_watched_object(path: String, arguments: &CommandLine, point_mapper: &PointMapper, embedding_stage: Option<&Arc<EmbeddingStage>>,
+                               database_client: &DatabaseClient, transports: &TransportRegistry, metrics: &Arc<Metrics>) -> anyhow::Result<u64> {
+    let dataset = Dataset::load(&path, &arguments.source_file_type, transports).await.context(FailureKind::Source)?;
+    let mut upload_job_builder = UploadJob::builder()
+        .source(dataset)
+        .point_mapper(point_mapper.clone())
+        .sink(database_client.clone())
+        .batch_size(arguments.batch_size)
+        .metrics(metrics.clone());
 
-    run_transference(&database_client, &dataset, arguments.batch_size).await?;
+    if let Some(embedding_stage) = embedding_stage {
+        upload_job_builder = upload_job_builder.embedding_stage(embedding_stage.clone());
+    }
 
-    Ok(())
+    upload_job_builder.build()?.run().await
 }
@@ -0,0 +1,154 @@
+use std::fmt::Write;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
+
+use crate::record_source::SourceStatistics;
+
+/// Upper bounds in seconds of the batch latency histogram buckets
+const BATCH_LATENCY_BUCKETS: [f64; 11] = [0.01, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0];
+
+/// Counters of a running uploader, rendered in the Prometheus text format
+#[derive(Default)]
+pub struct Metrics {
+    watch_polls: AtomicU64,
+    watch_failed_polls: AtomicU64,
+    objects_uploaded: AtomicU64,
+    objects_failed: AtomicU64,
+    rows_read: AtomicU64,
+    rows_rejected: AtomicU64,
+    points_upserted: AtomicU64,
+    retries: AtomicU64,
+    bytes_read: AtomicU64,
+    batch_latency: Histogram,
+    last_poll_failed: AtomicBool,
+    ready: AtomicBool,
+}
+
+impl Metrics {
+    pub fn record_poll(&self, succeeded: bool) {
+        self.watch_polls.fetch_add(1, Ordering::Relaxed);
+        if !succeeded {
+            self.watch_failed_polls.fetch_add(1, Ordering::Relaxed);
+        }
+        self.last_poll_failed.store(!succeeded, Ordering::Relaxed);
+    }
+
+    pub fn record_object_uploaded(&self) {
+        self.objects_uploaded.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_object_failed(&self) {
+        self.objects_failed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// A batch written to the sink, `latency` running from the moment it was read to its commit
+    pub fn record_batch(&self, rows_read: u64, points_upserted: u64, latency: Duration) {
+        self.rows_read.fetch_add(rows_read, Ordering::Relaxed);
+        self.points_upserted.fetch_add(points_upserted, Ordering::Relaxed);
+        self.batch_latency.observe(latency.as_secs_f64());
+    }
+
+    /// Adds what a source read since its previous statistics
+    pub fn record_source_progress(&self, progress: SourceStatistics) {
+        self.bytes_read.fetch_add(progress.bytes_read, Ordering::Relaxed);
+        self.rows_rejected.fetch_add(progress.rows_rejected, Ordering::Relaxed);
+        self.retries.fetch_add(progress.retries, Ordering::Relaxed);
+    }
+
+    /// An upsert that failed with a transient error and was sent again
+    pub fn record_retry(&self) {
+        self.retries.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Set once Qdrant is reachable and the source is open
+    pub fn set_ready(&self) {
+        self.ready.store(true, Ordering::Relaxed);
+    }
+
+    /// False while the last poll of a watched location failed
+    pub fn is_healthy(&self) -> bool {
+        !self.last_poll_failed.load(Ordering::Relaxed)
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.ready.load(Ordering::Relaxed) && self.is_healthy()
+    }
+
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot {
+            rows_read: self.rows_read.load(Ordering::Relaxed),
+            rows_rejected: self.rows_rejected.load(Ordering::Relaxed),
+            points_upserted: self.points_upserted.load(Ordering::Relaxed),
+            retries: self.retries.load(Ordering::Relaxed),
+            bytes_read: self.bytes_read.load(Ordering::Relaxed),
+            objects_uploaded: self.objects_uploaded.load(Ordering::Relaxed),
+            objects_failed: self.objects_failed.load(Ordering::Relaxed),
+        }
+    }
+
+    pub fn render(&self) -> String {
+        let mut text = String::new();
+        let counters = [
+            ("qdrant_uploader_watch_polls_total", "Polls of the watched location", &self.watch_polls),
+            ("qdrant_uploader_watch_failed_polls_total", "Polls of the watched location that could not list it", &self.watch_failed_polls),
+            ("qdrant_uploader_objects_uploaded_total", "Watched objects uploaded", &self.objects_uploaded),
+            ("qdrant_uploader_objects_failed_total", "Uploads of watched objects that failed", &self.objects_failed),
+            ("qdrant_uploader_rows_read_total", "Rows read from the source and written to the sink", &self.rows_read),
+            ("qdrant_uploader_rows_rejected_total", "Rows of the source skipped because they could not be parsed", &self.rows_rejected),
+            ("qdrant_uploader_points_upserted_total", "Points upserted into the collection", &self.points_upserted),
+            ("qdrant_uploader_retries_total", "Interrupted source downloads that were resumed and failed upserts that were sent again", &self.retries),
+            ("qdrant_uploader_source_bytes_read_total", "Bytes read from the source, before decompression", &self.bytes_read),
+        ];
+
+        for (name, help, counter) in counters {
+            let _ = writeln!(text, "# HELP {name} {help}\n# TYPE {name} counter\n{name} {value}", value=counter.load(Ordering::Relaxed));
+        }
+        self.batch_latency.render(&mut text, "qdrant_uploader_batch_latency_seconds", "Time from reading a batch to committing it");
+        text
+    }
+}
+
+
+/// Counter values at a point in time, such as the end of a run
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MetricsSnapshot {
+    pub rows_read: u64,
+    pub rows_rejected: u64,
+    pub points_upserted: u64,
+    pub retries: u64,
+    pub bytes_read: u64,
+    pub objects_uploaded: u64,
+    pub objects_failed: u64,
+}
+
+
+/// Cumulative histogram over [`BATCH_LATENCY_BUCKETS`]
+#[derive(Default)]
+struct Histogram {
+    buckets: [AtomicU64; BATCH_LATENCY_BUCKETS.len()],
+    count: AtomicU64,
+    sum_micros: AtomicU64,
+}
+
+impl Histogram {
+    fn observe(&self, seconds: f64) {
+        for (bucket, upper_bound) in self.buckets.iter().zip(BATCH_LATENCY_BUCKETS) {
+            if seconds <= upper_bound {
+                bucket.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_micros.fetch_add((seconds * 1_000_000.0) as u64, Ordering::Relaxed);
+    }
+
+    fn render(&self, text: &mut String, name: &str, help: &str) {
+        let _ = writeln!(text, "# HELP {name} {help}\n# TYPE {name} histogram");
+        for (bucket, upper_bound) in self.buckets.iter().zip(BATCH_LATENCY_BUCKETS) {
+            let _ = writeln!(text, "{name}_bucket{{le=\"{upper_bound}\"}} {value}", value=bucket.load(Ordering::Relaxed));
+        }
+        let count = self.count.load(Ordering::Relaxed);
+        let _ = writeln!(text, "{name}_bucket{{le=\"+Inf\"}} {count}");
+        let _ = writeln!(text, "{name}_sum {sum}", sum=self.sum_micros.load(Ordering::Relaxed) as f64 / 1_000_000.0);
+        let _ = writeln!(text, "{name}_count {count}");
+    }
+}
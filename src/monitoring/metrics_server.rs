@@ -0,0 +1,50 @@
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use hyper::header::{HeaderValue, CONTENT_TYPE};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+
+use super::Metrics;
+
+/// Serves `/health` and `/ready`, answering 503 while unhealthy or not ready yet, and `/metrics` in the Prometheus text format.
+/// Returns the bound address, the port being picked by the system if 0 was given
+pub fn serve_metrics(address: SocketAddr, metrics: Arc<Metrics>) -> anyhow::Result<SocketAddr> {
+    let make_service = make_service_fn(move |_| {
+        let metrics = metrics.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                let response = respond(&request, &metrics);
+                async move { Ok::<_, Infallible>(response) }
+            }))
+        }
+    });
+
+    let server = Server::try_bind(&address)?.serve(make_service);
+    let local_address = server.local_addr();
+    tokio::spawn(async move {
+        if let Err(error) = server.await {
+            tracing::error!("Metrics server stopped: {error}");
+        }
+    });
+
+    tracing::info!("Serving /health, /ready and /metrics on {local_address}");
+    Ok(local_address)
+}
+
+fn respond(request: &Request<Body>, metrics: &Metrics) -> Response<Body> {
+    let (status, body) = match (request.method(), request.uri().path()) {
+        (&Method::GET, "/health") if metrics.is_healthy() => (StatusCode::OK, "ok".to_owned()),
+        (&Method::GET, "/health") => (StatusCode::SERVICE_UNAVAILABLE, "unhealthy".to_owned()),
+        (&Method::GET, "/ready") if metrics.is_ready() => (StatusCode::OK, "ready".to_owned()),
+        (&Method::GET, "/ready") => (StatusCode::SERVICE_UNAVAILABLE, "not ready".to_owned()),
+        (&Method::GET, "/metrics") => (StatusCode::OK, metrics.render()),
+        _ => (StatusCode::NOT_FOUND, "not found".to_owned()),
+    };
+
+    let mut response = Response::new(Body::from(body));
+    *response.status_mut() = status;
+    response.headers_mut().insert(CONTENT_TYPE, HeaderValue::from_static("text/plain; version=0.0.4"));
+    response
+}
@@ -0,0 +1,5 @@
+mod metrics;
+mod metrics_server;
+
+pub use metrics::{Metrics, MetricsSnapshot};
+pub use metrics_server::serve_metrics;
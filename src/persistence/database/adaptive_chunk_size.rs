@@ -0,0 +1,53 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Number of points per upsert, halved when an upsert is slower than the target latency or fails, and grown back
+/// by a quarter while upserts take less than half of it
+pub(super) struct AdaptiveChunkSize {
+    min_chunk_size: usize,
+    max_chunk_size: usize,
+    target_latency: Duration,
+    chunk_size: Mutex<usize>,
+}
+
+impl AdaptiveChunkSize {
+    /// Starts at `max_chunk_size`. Chunks hold at least one point
+    pub(super) fn new(min_chunk_size: usize, max_chunk_size: usize, target_latency: Duration) -> Self {
+        let max_chunk_size = max_chunk_size.max(1);
+        let min_chunk_size = min_chunk_size.clamp(1, max_chunk_size);
+        AdaptiveChunkSize {
+            min_chunk_size,
+            max_chunk_size,
+            target_latency,
+            chunk_size: Mutex::new(max_chunk_size),
+        }
+    }
+
+    pub(super) fn chunk_size(&self) -> usize {
+        *self.chunk_size.lock().unwrap()
+    }
+
+    pub(super) fn record_success(&self, latency: Duration) {
+        if latency > self.target_latency {
+            self.shrink("slow upsert");
+        } else if latency < self.target_latency / 2 {
+            let mut chunk_size = self.chunk_size.lock().unwrap();
+            if *chunk_size < self.max_chunk_size {
+                *chunk_size = (*chunk_size + (*chunk_size / 4).max(1)).min(self.max_chunk_size);
+                tracing::debug!(chunk_size=*chunk_size, "Chunk size increased");
+            }
+        }
+    }
+
+    pub(super) fn record_failure(&self) {
+        self.shrink("failed upsert");
+    }
+
+    fn shrink(&self, reason: &str) {
+        let mut chunk_size = self.chunk_size.lock().unwrap();
+        if *chunk_size > self.min_chunk_size {
+            *chunk_size = (*chunk_size / 2).max(self.min_chunk_size);
+            tracing::info!(chunk_size=*chunk_size, "Chunk size reduced after a {reason}");
+        }
+    }
+}
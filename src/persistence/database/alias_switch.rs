@@ -0,0 +1,243 @@
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use qdrant_client::qdrant::{AliasOperations, ChangeAliases, CollectionConfig, CreateAlias, CreateCollection, CreateFieldIndexCollection,
+                            CreateShardKey, CreateShardKeyRequest, DeleteAlias, DeleteCollection, FieldType, ListAliasesRequest,
+                            ListShardKeysRequest, PayloadSchemaInfo, PayloadSchemaType, ShardingMethod};
+use qdrant_client::qdrant::alias_operations::Action;
+
+use crate::collection_upload::with_cleanup_error;
+
+use super::DatabaseClient;
+use super::collection_settings::CollectionSettings;
+use super::qdrant_grpc::QdrantGrpc;
+
+/// Blue/green load: a fresh collection is created with the same configuration as the one
+/// currently behind the alias, and the alias is only moved once the upload is verified.
+pub(crate) struct AliasSwitch {
+    client: QdrantGrpc,
+    alias_name: String,
+    previous_collection: String,
+    target_collection: String,
+}
+
+impl AliasSwitch {
+
+    pub async fn prepare(database_client: &DatabaseClient, settings: &CollectionSettings) -> anyhow::Result<AliasSwitch> {
+        let client = database_client.qdrant_client().clone();
+        let alias_name = database_client.collection_name().to_owned();
+
+        let previous_collection = resolve_alias(&client, &alias_name).await?;
+        let (config, payload_schema) = load_collection_config(database_client, &previous_collection).await?;
+
+        let target_collection = make_collection_name(&alias_name)?;
+        if database_client.for_collection(&target_collection).collection_exists().await? {
+            anyhow::bail!("Collection {target_collection} already exists, another blue/green load of alias {alias_name} may be running");
+        }
+
+        let custom_sharding = config.params.as_ref().and_then(|params| params.sharding_method) == Some(ShardingMethod::Custom as i32);
+        let mut create_collection = make_create_collection(&target_collection, config);
+        settings.apply(&mut create_collection);
+        client.collections().create(create_collection).await?;
+
+        let alias_switch = AliasSwitch {
+            client,
+            alias_name,
+            previous_collection,
+            target_collection,
+        };
+
+        // From here on the new collection is dropped again when anything fails
+        if let Err(error) = alias_switch.copy_collection_setup(custom_sharding, &payload_schema).await {
+            return Err(alias_switch.abort_after(error).await);
+        }
+
+        tracing::info!("Collection {target} created from the configuration of {previous} (alias {alias})",
+            target=alias_switch.target_collection, previous=alias_switch.previous_collection, alias=alias_switch.alias_name);
+
+        Ok(alias_switch)
+    }
+
+    async fn copy_collection_setup(&self, custom_sharding: bool, payload_schema: &HashMap<String, PayloadSchemaInfo>) -> anyhow::Result<()> {
+        if custom_sharding {
+            copy_shard_keys(&self.client, &self.previous_collection, &self.target_collection).await?;
+        }
+        copy_payload_indexes(&self.client, &self.target_collection, payload_schema).await
+    }
+
+    pub fn target_collection(&self) -> &str {
+        &self.target_collection
+    }
+
+    /// Checks that the new collection holds one point per distinct uploaded id, as counted by the
+    /// [`super::UploadVerifier`], and atomically moves the alias to it
+    pub async fn commit(&self, target_client: &DatabaseClient, distinct_ids: u64) -> anyhow::Result<()> {
+        let points_count = target_client.count_points().await?;
+        if points_count != distinct_ids {
+            anyhow::bail!("Collection {collection} has {points_count} points, but {distinct_ids} distinct ids were uploaded. Alias {alias} was not switched",
+                collection=self.target_collection, alias=self.alias_name);
+        }
+        tracing::info!("Collection {collection} has {points_count} points", collection=self.target_collection);
+
+        let delete_alias = Action::DeleteAlias(DeleteAlias {
+            alias_name: self.alias_name.clone()
+        });
+
+        let create_alias = Action::CreateAlias(CreateAlias {
+            collection_name: self.target_collection.clone(),
+            alias_name: self.alias_name.clone()
+        });
+
+        let change_aliases = ChangeAliases {
+            actions: vec![
+                AliasOperations { action: Some(delete_alias) },
+                AliasOperations { action: Some(create_alias) },
+            ],
+            timeout: None
+        };
+
+        self.client.collections().update_aliases(change_aliases).await?;
+        tracing::info!("Alias {alias} moved from {previous} to {target}", alias=self.alias_name,
+            previous=self.previous_collection, target=self.target_collection);
+
+        Ok(())
+    }
+
+    /// Drops the collection created by `prepare`, used when the upload fails before the switch
+    pub async fn abort(&self) -> anyhow::Result<()> {
+        delete_collection(&self.client, &self.target_collection).await?;
+        tracing::warn!("Collection {target} deleted, alias {alias} still points to {previous}", target=self.target_collection,
+            alias=self.alias_name, previous=self.previous_collection);
+        Ok(())
+    }
+
+    /// Aborts after `error`, which is returned with the error of the abort, if any
+    pub async fn abort_after(&self, error: anyhow::Error) -> anyhow::Error {
+        match self.abort().await {
+            Ok(()) => error,
+            Err(abort_error) => with_cleanup_error(error, abort_error),
+        }
+    }
+
+    pub async fn delete_previous_collection(&self, grace_period: Duration) -> anyhow::Result<()> {
+        tracing::info!("Waiting {seconds}s before deleting collection {previous}", seconds=grace_period.as_secs(),
+            previous=self.previous_collection);
+        tokio::time::sleep(grace_period).await;
+
+        delete_collection(&self.client, &self.previous_collection).await?;
+        tracing::info!("Collection {previous} deleted", previous=self.previous_collection);
+        Ok(())
+    }
+}
+
+
+async fn resolve_alias(client: &QdrantGrpc, alias_name: &str) -> anyhow::Result<String> {
+    let aliases = client.collections().list_aliases(ListAliasesRequest {}).await?.into_inner().aliases;
+    let maybe_alias = aliases.into_iter().find(|alias| alias.alias_name == alias_name);
+
+    match maybe_alias {
+        Some(alias) => Ok(alias.collection_name),
+        None => anyhow::bail!("{alias_name} is not an alias, blue/green load requires --database-collection to be an alias to an existing collection")
+    }
+}
+
+async fn load_collection_config(database_client: &DatabaseClient, collection_name: &str) -> anyhow::Result<(CollectionConfig, HashMap<String, PayloadSchemaInfo>)> {
+    let collection_info = database_client.for_collection(collection_name).collection_info().await?;
+
+    match collection_info {
+        Some(info) if info.config.is_some() => Ok((info.config.unwrap(), info.payload_schema)),
+        _ => anyhow::bail!("Could not load the configuration of collection {collection_name}")
+    }
+}
+
+/// Shard keys are not part of the collection configuration either, each one is created with the shard number and
+/// replication factor of the collection
+async fn copy_shard_keys(client: &QdrantGrpc, previous_collection: &str, target_collection: &str) -> anyhow::Result<()> {
+    let list_shard_keys = ListShardKeysRequest { collection_name: previous_collection.to_owned() };
+    let shard_keys = client.collections().list_shard_keys(list_shard_keys).await?.into_inner().shard_keys;
+
+    for shard_key in shard_keys.into_iter().filter_map(|description| description.key) {
+        let create_shard_key = CreateShardKeyRequest {
+            collection_name: target_collection.to_owned(),
+            request: Some(CreateShardKey { shard_key: Some(shard_key), ..Default::default() }),
+            timeout: None,
+        };
+        client.collections().create_shard_key(create_shard_key).await?;
+    }
+
+    tracing::info!("Shard keys of collection {previous_collection} created in collection {target_collection}");
+    Ok(())
+}
+
+/// Payload indexes are not part of the collection configuration, so they are created one by one
+async fn copy_payload_indexes(client: &QdrantGrpc, collection_name: &str, payload_schema: &HashMap<String, PayloadSchemaInfo>) -> anyhow::Result<()> {
+    for (field_name, schema_info) in payload_schema.iter() {
+        match payload_schema_to_field_type(schema_info.data_type) {
+            Some(field_type) => {
+                let create_field_index = CreateFieldIndexCollection {
+                    collection_name: collection_name.to_owned(),
+                    wait: Some(true),
+                    field_name: field_name.clone(),
+                    field_type: Some(field_type as i32),
+                    field_index_params: schema_info.params.clone(),
+                    ..Default::default()
+                };
+                client.points().create_field_index(create_field_index).await?;
+                tracing::info!("Payload index for field {field_name} created in collection {collection_name}");
+            },
+            None => {
+                tracing::warn!("Payload index for field {field_name} has an unsupported type and was not copied to collection {collection_name}");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn payload_schema_to_field_type(data_type: i32) -> Option<FieldType> {
+    match PayloadSchemaType::try_from(data_type).ok()? {
+        PayloadSchemaType::Keyword => Some(FieldType::Keyword),
+        PayloadSchemaType::Integer => Some(FieldType::Integer),
+        PayloadSchemaType::Float => Some(FieldType::Float),
+        PayloadSchemaType::Geo => Some(FieldType::Geo),
+        PayloadSchemaType::Text => Some(FieldType::Text),
+        PayloadSchemaType::Bool => Some(FieldType::Bool),
+        _ => None
+    }
+}
+
+async fn delete_collection(client: &QdrantGrpc, collection_name: &str) -> anyhow::Result<()> {
+    let delete_collection = DeleteCollection { collection_name: collection_name.to_owned(), ..Default::default() };
+    client.collections().delete(delete_collection).await?;
+    Ok(())
+}
+
+fn make_collection_name(alias_name: &str) -> anyhow::Result<String> {
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis();
+    Ok(format!("{alias_name}_{timestamp}"))
+}
+
+/// `on_disk_payload` is copied along with its replacement `payload`, which older servers do not report
+#[allow(deprecated)]
+fn make_create_collection(collection_name: &str, config: CollectionConfig) -> CreateCollection {
+    let params = config.params.unwrap_or_default();
+
+    CreateCollection {
+        collection_name: collection_name.to_owned(),
+        hnsw_config: config.hnsw_config,
+        wal_config: config.wal_config,
+        optimizers_config: config.optimizer_config,
+        shard_number: Some(params.shard_number),
+        on_disk_payload: Some(params.on_disk_payload),
+        payload: params.payload,
+        vectors_config: params.vectors_config,
+        sparse_vectors_config: params.sparse_vectors_config,
+        sharding_method: params.sharding_method,
+        replication_factor: params.replication_factor,
+        write_consistency_factor: params.write_consistency_factor,
+        quantization_config: config.quantization_config,
+        strict_mode_config: config.strict_mode_config,
+        metadata: config.metadata,
+        ..Default::default()
+    }
+}
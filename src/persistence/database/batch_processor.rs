@@ -1,6 +1,8 @@
 use std::collections::HashMap;
 use std::str::FromStr;
 
+use anyhow::Context;
+
 use qdrant_client::qdrant::NamedVectors;
 use qdrant_client::qdrant::PointId;
 use qdrant_client::qdrant::PointStruct;
@@ -8,6 +10,7 @@ use qdrant_client::qdrant::Value as QdrantValue;
 use qdrant_client::qdrant::Vector;
 use qdrant_client::qdrant::Vectors;
 use qdrant_client::qdrant::point_id::PointIdOptions;
+use qdrant_client::qdrant::shard_key::Key as ShardKey;
 use qdrant_client::qdrant::vectors::VectorsOptions;
 
 use crate::persistence::vector_field_name::FieldName;
@@ -23,7 +26,27 @@ pub fn batch_to_points(batch: Vec<serde_json::Value>, id_field_name: Option<Stri
 }
 
 
-fn value_to_point(value: &serde_json::Value, maybe_id_field_name: &Option<String>, vector_field_names: &FieldName, maybe_payload_field: &Option<FieldName>) -> PointStruct {
+/// Groups the points of a batch by the shard key read from each row, rows without a shard key are kept in a `None` group
+pub fn batch_to_sharded_points(batch: Vec<serde_json::Value>, id_field_name: Option<String>, vector_field: &FieldName, payload_field: &Option<FieldName>,
+                               shard_key_field_name: &str) -> anyhow::Result<Vec<(Option<ShardKey>, Vec<PointStruct>)>> {
+    let mut sharded_points: Vec<(Option<ShardKey>, Vec<PointStruct>)> = Vec::new();
+
+    for (row, value) in batch.iter().enumerate() {
+        let shard_key = extract_shard_key(shard_key_field_name, value)
+            .with_context(|| format!("Row {row} of the batch can not be routed to a shard", row=row + 1))?;
+        let point = value_to_point(value, &id_field_name, vector_field, payload_field);
+
+        match sharded_points.iter_mut().find(|(group_key, _)| group_key.as_ref() == Some(&shard_key)) {
+            Some((_, points)) => points.push(point),
+            None => sharded_points.push((Some(shard_key), vec![point]))
+        }
+    }
+
+    Ok(sharded_points)
+}
+
+
+pub fn value_to_point(value: &serde_json::Value, maybe_id_field_name: &Option<String>, vector_field_names: &FieldName, maybe_payload_field: &Option<FieldName>) -> PointStruct {
     let id = extract_point_id(maybe_id_field_name, value);
     let payload = extract_payload(maybe_payload_field, value);
     let vectors = extract_vectors(vector_field_names, value);
@@ -40,7 +63,7 @@ fn extract_point_id(maybe_id_field_name: &Option<String>, source_value: &serde_j
                 if let Some(id_value) = value.as_u64() {
                     Some(PointIdOptions::Num(id_value))
                 } else if let Some(id_value) = value.as_str() {
-                    if let Ok(_) = uuid::Uuid::from_str(id_value) {
+                    if uuid::Uuid::from_str(id_value).is_ok() {
                         Some(PointIdOptions::Uuid(id_value.to_owned()))
                     } else {
                         None
@@ -55,13 +78,26 @@ fn extract_point_id(maybe_id_field_name: &Option<String>, source_value: &serde_j
             None
         };
     
-    let point_id = id_options.map(|child|
+    id_options.map(|child|
         PointId {
             point_id_options: Some(child)
         }
-    );
+    )
+}
 
-    point_id
+/// Every row needs a shard key, Qdrant would otherwise spread the point over the shards of all keys
+fn extract_shard_key(shard_key_field_name: &str, source_value: &serde_json::Value) -> anyhow::Result<ShardKey> {
+    match source_value.get(shard_key_field_name) {
+        None | Some(serde_json::Value::Null) => anyhow::bail!("Missing shard key in field {shard_key_field_name}"),
+        Some(serde_json::Value::String(keyword)) => Ok(ShardKey::Keyword(keyword.to_owned())),
+        Some(value) => {
+            if let Some(number) = value.as_u64() {
+                Ok(ShardKey::Number(number))
+            } else {
+                anyhow::bail!("Invalid shard key {value} in field {shard_key_field_name}, it must be a string or a non negative integer")
+            }
+        }
+    }
 }
 
 fn extract_payload(maybe_payload_fields: &Option<FieldName>, value: &serde_json::Value) -> HashMap<String, QdrantValue>{
@@ -69,7 +105,7 @@ fn extract_payload(maybe_payload_fields: &Option<FieldName>, value: &serde_json:
         Some(FieldName::Single(field_name)) => {
             let maybe_field_content = value.get(field_name);
             if maybe_field_content.is_none() {
-                return HashMap::new()
+                HashMap::new()
             } else {
                 extract_payload_from_single_field(maybe_field_content)
             }
@@ -83,7 +119,7 @@ fn extract_payload(maybe_payload_fields: &Option<FieldName>, value: &serde_json:
     }
 }
 
-fn extract_payload_from_multiple_fields(field_names: &Vec<String>, value: &serde_json::Value) -> HashMap<String, QdrantValue> {
+fn extract_payload_from_multiple_fields(field_names: &[String], value: &serde_json::Value) -> HashMap<String, QdrantValue> {
     field_names.iter().map(|field_name|{
         let field_value = value.get(field_name).map(|value| QdrantValue::from(value.to_owned())).unwrap_or_default();
         (field_name.to_owned(), field_value)
@@ -115,7 +151,7 @@ fn extract_vectors(vector_field_names: &FieldName, value: &serde_json::Value) ->
     }
 }
 
-fn extract_named_vectors(field_names: &Vec<String>, value: &serde_json::Value) -> Option<Vectors> {
+fn extract_named_vectors(field_names: &[String], value: &serde_json::Value) -> Option<Vectors> {
     let vectors_with_names = 
         extract_vectors_with_names(field_names, value);
             
@@ -125,7 +161,7 @@ fn extract_named_vectors(field_names: &Vec<String>, value: &serde_json::Value) -
     Some(vectors)
 }
 
-fn extract_vectors_with_names(field_names: &Vec<String>, value: &serde_json::Value) -> HashMap<String, Vector> {
+fn extract_vectors_with_names(field_names: &[String], value: &serde_json::Value) -> HashMap<String, Vector> {
     field_names.iter().filter_map(|field_name|{
         extract_qdrant_vector(value, field_name)
             .map(|qdrant_vector|{
@@ -136,28 +172,23 @@ fn extract_vectors_with_names(field_names: &Vec<String>, value: &serde_json::Val
 
 fn extract_single_vector(value: &serde_json::Value, field_name: &String) -> Option<Vectors> {
     let maybe_qdrant_vector = extract_qdrant_vector(value, field_name);
-    let maybe_qdrant_vector_option = maybe_qdrant_vector.map(|qdrant_vector| VectorsOptions::Vector(qdrant_vector));
+    let maybe_qdrant_vector_option = maybe_qdrant_vector.map(VectorsOptions::Vector);
     let vectors = Vectors {vectors_options: maybe_qdrant_vector_option};
     Some(vectors)
 }
 
 fn extract_qdrant_vector(value: &serde_json::Value, field_name: &String) -> Option<Vector> {
     let maybe_field_value = value.get(field_name);
-    if maybe_field_value.is_none() {
-        None
-    } else {
-        let field_value = maybe_field_value.unwrap();
-    
+    if let Some(field_value) = maybe_field_value {
         let maybe_vector = field_value.as_array();
-        if maybe_vector.is_none() {
-            None
-        } else {
-            let vector = maybe_vector.unwrap();
-            let vector_data = vector.iter().map(|coordinate| coordinate.as_f64().unwrap_or_default() as f32).collect();
-            let qdrant_vector = Vector {
-                data: vector_data
-            };
+        if let Some(vector) = maybe_vector {
+            let vector_data: Vec<f32> = vector.iter().map(|coordinate| coordinate.as_f64().unwrap_or_default() as f32).collect();
+            let qdrant_vector = Vector::new_dense(vector_data);
             Some(qdrant_vector)
+        } else {
+            None
         }
+    } else {
+        None
     }
 }
\ No newline at end of file
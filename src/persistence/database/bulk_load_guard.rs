@@ -0,0 +1,65 @@
+use qdrant_client::qdrant::OptimizersConfigDiff;
+
+use super::DatabaseClient;
+
+/// Qdrant default indexing threshold, in kilobytes, restored when the collection does not report its own
+const DEFAULT_INDEXING_THRESHOLD: u64 = 10000;
+
+/// Disables indexing of a collection while points are uploaded and keeps its previous optimizers
+/// configuration so it can be restored afterwards, whether the upload succeeded or not.
+pub(crate) struct BulkLoadGuard {
+    database_client: DatabaseClient,
+    previous_optimizers_config: OptimizersConfigDiff,
+    restored: bool,
+}
+
+impl BulkLoadGuard {
+
+    /// When given, `indexing_threshold` replaces the threshold of the collection once restored
+    pub async fn start(database_client: &DatabaseClient, indexing_threshold: Option<u64>) -> anyhow::Result<BulkLoadGuard> {
+        let mut previous_optimizers_config = database_client.optimizers_config().await?;
+        previous_optimizers_config.indexing_threshold = indexing_threshold
+            .or(previous_optimizers_config.indexing_threshold)
+            .or(Some(DEFAULT_INDEXING_THRESHOLD));
+
+        let bulk_load_config = OptimizersConfigDiff {
+            indexing_threshold: Some(0),
+            ..Default::default()
+        };
+        database_client.update_optimizers_config(&bulk_load_config).await?;
+
+        tracing::info!("Indexing disabled for collection {collection} during bulk load (previous indexing threshold was {threshold:?})",
+            collection=database_client.collection_name(), threshold=previous_optimizers_config.indexing_threshold);
+
+        let guard = BulkLoadGuard {
+            database_client: database_client.clone(),
+            previous_optimizers_config,
+            restored: false,
+        };
+
+        Ok(guard)
+    }
+
+    pub async fn restore(mut self) -> anyhow::Result<()> {
+        self.database_client.update_optimizers_config(&self.previous_optimizers_config).await?;
+        self.restored = true;
+
+        tracing::info!("Optimizers configuration of collection {collection} restored", collection=self.database_client.collection_name());
+        Ok(())
+    }
+
+    /// Gives up restoring, for a collection deleted after a failed upload
+    pub fn dismiss(mut self) {
+        self.restored = true;
+    }
+}
+
+/// Restoring is asynchronous and can not happen on drop, so at least tell what must be restored by hand
+impl Drop for BulkLoadGuard {
+    fn drop(&mut self) {
+        if !self.restored {
+            tracing::error!("Optimizers configuration of collection {collection} was not restored after bulk load, it must be set back to {config:?}",
+                collection=self.database_client.collection_name(), config=self.previous_optimizers_config);
+        }
+    }
+}
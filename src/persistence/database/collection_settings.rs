@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+
+use qdrant_client::qdrant::{BinaryQuantization, CompressionRatio, CreateCollection, Distance, ProductQuantization,
+                            QuantizationConfig, QuantizationType, ScalarQuantization, VectorParams, VectorParamsMap, VectorsConfig};
+use qdrant_client::qdrant::quantization_config::Quantization;
+use qdrant_client::qdrant::vectors_config::Config as VectorsConfigOptions;
+
+use crate::persistence::vector_field_name::FieldName;
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy)]
+pub enum VectorDistance {
+    Cosine,
+    Euclid,
+    Dot
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy)]
+pub enum QuantizationKind {
+    Scalar,
+    Product,
+    Binary
+}
+
+/// Settings applied to collections created by the uploader, either from scratch (`--recreate-collection`)
+/// or copied from the aliased collection on a blue/green load
+#[derive(Clone, Debug, Default)]
+pub struct CollectionSettings {
+    pub vector_sizes: Vec<u64>,
+    pub distance: Option<VectorDistance>,
+
+    pub hnsw_m: Option<u64>,
+    pub hnsw_ef_construct: Option<u64>,
+    pub on_disk_vectors: bool,
+
+    pub quantization: Option<QuantizationKind>,
+    pub quantization_always_ram: bool,
+
+    pub defer_indexing: bool,
+    pub indexing_threshold: Option<u64>,
+}
+
+impl CollectionSettings {
+
+    /// Builds the request to create a collection from scratch, with one vector per vector field
+    pub fn make_create_collection(&self, collection_name: &str, vector_field: &FieldName) -> anyhow::Result<CreateCollection> {
+        let vectors_config = self.make_vectors_config(vector_field)?;
+
+        let mut create_collection = CreateCollection {
+            collection_name: collection_name.to_owned(),
+            vectors_config: Some(vectors_config),
+            ..Default::default()
+        };
+
+        self.apply(&mut create_collection);
+        Ok(create_collection)
+    }
+
+    /// Overrides the parts of a create collection request controlled by the command line
+    pub fn apply(&self, create_collection: &mut CreateCollection) {
+        if self.hnsw_m.is_some() || self.hnsw_ef_construct.is_some() {
+            let mut hnsw_config = create_collection.hnsw_config.unwrap_or_default();
+            hnsw_config.m = self.hnsw_m.or(hnsw_config.m);
+            hnsw_config.ef_construct = self.hnsw_ef_construct.or(hnsw_config.ef_construct);
+            create_collection.hnsw_config = Some(hnsw_config);
+        }
+
+        if self.on_disk_vectors {
+            if let Some(vectors_config) = create_collection.vectors_config.as_mut() {
+                set_vectors_on_disk(vectors_config);
+            }
+        }
+
+        if let Some(quantization) = self.quantization {
+            create_collection.quantization_config = Some(make_quantization_config(quantization, self.quantization_always_ram));
+        }
+    }
+
+
+    fn make_vectors_config(&self, vector_field: &FieldName) -> anyhow::Result<VectorsConfig> {
+        let distance = self.distance.unwrap_or(VectorDistance::Cosine);
+
+        let config = match vector_field {
+            FieldName::Single(_) => {
+                let size = self.vector_size_at(0)?;
+                VectorsConfigOptions::Params(make_vector_params(size, distance))
+            },
+            FieldName::Named(field_names) => {
+                let map: anyhow::Result<HashMap<String, VectorParams>> = field_names.iter().enumerate()
+                    .map(|(position, field_name)| {
+                        let size = self.vector_size_at(position)?;
+                        Ok((field_name.to_owned(), make_vector_params(size, distance)))
+                    })
+                    .collect();
+                VectorsConfigOptions::ParamsMap(VectorParamsMap { map: map? })
+            }
+        };
+
+        Ok(VectorsConfig { config: Some(config) })
+    }
+
+    /// A single size applies to every vector field, otherwise sizes are given in the same order as the fields
+    fn vector_size_at(&self, position: usize) -> anyhow::Result<u64> {
+        let maybe_size =
+            if self.vector_sizes.len() == 1 {
+                self.vector_sizes.first()
+            } else {
+                self.vector_sizes.get(position)
+            };
+
+        match maybe_size {
+            Some(size) => Ok(*size),
+            None => anyhow::bail!("A --vector-size must be provided for each --vector-field-name (or a single one for all of them)")
+        }
+    }
+}
+
+
+impl From<VectorDistance> for Distance {
+    fn from(distance: VectorDistance) -> Self {
+        match distance {
+            VectorDistance::Cosine => Distance::Cosine,
+            VectorDistance::Euclid => Distance::Euclid,
+            VectorDistance::Dot => Distance::Dot,
+        }
+    }
+}
+
+fn make_vector_params(size: u64, distance: VectorDistance) -> VectorParams {
+    VectorParams {
+        size,
+        distance: Distance::from(distance).into(),
+        ..Default::default()
+    }
+}
+
+/// `on_disk` and `always_ram` are deprecated in favour of `memory`, which older servers ignore
+#[allow(deprecated)]
+fn set_vectors_on_disk(vectors_config: &mut VectorsConfig) {
+    match vectors_config.config.as_mut() {
+        Some(VectorsConfigOptions::Params(params)) => {
+            params.on_disk = Some(true);
+        },
+        Some(VectorsConfigOptions::ParamsMap(params_map)) => {
+            params_map.map.values_mut().for_each(|params| params.on_disk = Some(true));
+        },
+        None => {}
+    }
+}
+
+#[allow(deprecated)]
+fn make_quantization_config(quantization: QuantizationKind, always_ram: bool) -> QuantizationConfig {
+    let quantization = match quantization {
+        QuantizationKind::Scalar => Quantization::Scalar(ScalarQuantization {
+            r#type: QuantizationType::Int8.into(),
+            quantile: Some(0.99),
+            always_ram: Some(always_ram),
+            ..Default::default()
+        }),
+        QuantizationKind::Product => Quantization::Product(ProductQuantization {
+            compression: CompressionRatio::X16.into(),
+            always_ram: Some(always_ram),
+            ..Default::default()
+        }),
+        QuantizationKind::Binary => Quantization::Binary(BinaryQuantization {
+            always_ram: Some(always_ram),
+            ..Default::default()
+        }),
+    };
+
+    QuantizationConfig { quantization: Some(quantization) }
+}
@@ -0,0 +1,76 @@
+use std::time::Duration;
+
+use anyhow::Context;
+use tonic::transport::{Certificate, ClientTlsConfig, Identity};
+
+/// Connection settings of a [`super::DatabaseClient`]. Timeouts not set are the defaults of the Qdrant client
+#[derive(Clone, Default)]
+pub struct ConnectionOptions {
+    pub api_key: Option<String>,
+    pub request_timeout: Option<Duration>,
+    pub connect_timeout: Option<Duration>,
+    /// Interval of HTTP/2 keep-alive pings, none are sent if not set
+    pub keep_alive_interval: Option<Duration>,
+    /// How long to wait for the answer to a keep-alive ping before closing the connection
+    pub keep_alive_timeout: Option<Duration>,
+    /// Send keep-alive pings while no request is running too, every 30 seconds if the interval is not set
+    pub keep_alive_while_idle: bool,
+    pub tls: Option<TlsSettings>,
+}
+
+
+/// TLS settings of an `https://` connection, which otherwise trusts the system CA certificates and sends no client
+/// certificate
+#[derive(Clone)]
+pub struct TlsSettings {
+    pub(super) client_config: ClientTlsConfig,
+}
+
+impl TlsSettings {
+    /// Loads the PEM files. The CA bundle replaces the system roots, and a client certificate requires its key.
+    /// `server_name` is checked against the server certificate instead of the host of the connection string
+    pub fn load(ca_cert_path: Option<&str>, client_cert_path: Option<&str>, client_key_path: Option<&str>,
+                server_name: Option<String>) -> anyhow::Result<TlsSettings> {
+        let mut client_config = match ca_cert_path {
+            Some(ca_cert_path) => ClientTlsConfig::new().ca_certificate(Certificate::from_pem(read_certificates(ca_cert_path)?)),
+            None => ClientTlsConfig::new().with_native_roots(),
+        };
+
+        client_config = match (client_cert_path, client_key_path) {
+            (Some(client_cert_path), Some(client_key_path)) =>
+                client_config.identity(Identity::from_pem(read_certificates(client_cert_path)?, read_private_key(client_key_path)?)),
+            (None, None) => client_config,
+            _ => anyhow::bail!("A client certificate and its key must be given together"),
+        };
+
+        if let Some(server_name) = server_name {
+            client_config = client_config.domain_name(server_name);
+        }
+
+        Ok(TlsSettings { client_config })
+    }
+}
+
+/// The PEM file, once checked to hold at least one certificate
+fn read_certificates(path: &str) -> anyhow::Result<Vec<u8>> {
+    let pem = std::fs::read(path).with_context(|| format!("Could not open {path}"))?;
+    let certificates = rustls_pemfile::certs(&mut pem.as_slice()).with_context(|| format!("Invalid PEM file {path}"))?;
+    if certificates.is_empty() {
+        anyhow::bail!("No certificate found in {path}");
+    }
+    Ok(pem)
+}
+
+/// The PEM file, once checked to hold a PKCS#8, RSA or EC private key
+fn read_private_key(path: &str) -> anyhow::Result<Vec<u8>> {
+    let pem = std::fs::read(path).with_context(|| format!("Could not open {path}"))?;
+    let mut reader = pem.as_slice();
+    loop {
+        match rustls_pemfile::read_one(&mut reader).with_context(|| format!("Invalid PEM file {path}"))? {
+            Some(rustls_pemfile::Item::PKCS8Key(_) | rustls_pemfile::Item::RSAKey(_) | rustls_pemfile::Item::ECKey(_)) =>
+                return Ok(pem),
+            Some(_) => continue,
+            None => anyhow::bail!("No private key found in {path}"),
+        }
+    }
+}
@@ -1,48 +1,376 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::{Duration, Instant}};
 
-use qdrant_client::{prelude::QdrantClient, qdrant::WriteOrdering};
+use async_trait::async_trait;
+use tonic::Code;
+use tracing::Instrument;
+use qdrant_client::qdrant::{WriteOrdering, WriteOrderingType, CountPoints, PointStruct, OptimizersConfigDiff, CollectionStatus, CollectionExistsRequest,
+                            CollectionInfo, DeleteCollection, GetCollectionInfoRequest, ShardKeySelector, UpdateCollection, UpsertPoints,
+                            CreateFieldIndexCollection, FieldType, KeywordIndexParams, PayloadIndexParams, PayloadSchemaType};
+use qdrant_client::qdrant::payload_index_params::IndexParams;
+use qdrant_client::qdrant::shard_key::Key as ShardKey;
 
+use crate::monitoring::Metrics;
 use crate::persistence::vector_field_name::FieldName;
+use crate::sink::Sink;
 
-use super::batch_processor::batch_to_points;
+use super::adaptive_chunk_size::AdaptiveChunkSize;
+use super::collection_settings::CollectionSettings;
+use super::connection_options::ConnectionOptions;
+use super::point_chunks::take_chunk;
+use super::qdrant_grpc::QdrantGrpc;
+use super::rate_limiter::RateLimiter;
 
+/// Failed upserts retried with a smaller chunk size before giving up, when the chunk size is adaptive
+const MAX_CONSECUTIVE_FAILED_UPSERTS: u32 = 5;
+const FAILED_UPSERT_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Status codes of the upserts that may succeed when retried: an overloaded or unreachable server, or a timeout.
+/// Anything else, such as an invalid point or a wrong vector dimension, fails the same way again
+const TRANSIENT_UPSERT_ERRORS: [Code; 3] = [Code::Unavailable, Code::DeadlineExceeded, Code::ResourceExhausted];
+
+/// Interval between two polls of the update queue, once the upserts were sent without waiting
+const UPDATE_QUEUE_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Ordering guarantee of the writes across the replicas of a shard
+#[derive(clap::ValueEnum, Debug, Clone, Copy)]
+pub enum WriteOrderingKind {
+    /// Writes are applied by any replica, without ordering guarantees
+    Weak,
+    /// Writes go through a dynamically elected leader replica
+    Medium,
+    /// Writes go through the permanent leader of the shard
+    Strong
+}
+
+impl WriteOrderingKind {
+    fn write_ordering(self) -> WriteOrdering {
+        let ordering_type = match self {
+            WriteOrderingKind::Weak => WriteOrderingType::Weak,
+            WriteOrderingKind::Medium => WriteOrderingType::Medium,
+            WriteOrderingKind::Strong => WriteOrderingType::Strong,
+        };
+        WriteOrdering { r#type: ordering_type as i32 }
+    }
+}
+
+
+/// Qdrant [`Sink`] writing into a single collection, also used to manage that collection
+#[derive(Clone)]
 pub struct DatabaseClient {
-    client: Arc<QdrantClient>,
+    client: QdrantGrpc,
     collection_name: String,
-
-    id_field: Option<String>, 
-    vector_field: FieldName,
-    payload_field: Option<FieldName>,
     
     write_ordering: Option<WriteOrdering>,
+    wait: bool,
     chunk_size: usize,
+    max_chunk_bytes: Option<usize>,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    adaptive_chunk_size: Option<Arc<AdaptiveChunkSize>>,
+    metrics: Option<Arc<Metrics>>,
 }
 
 impl DatabaseClient {
 
-    pub async fn new(connection_string: &str, api_key: &Option<String>, collection_name: &str, id_field_name: Option<String>, vector_field: FieldName,
-            payload_field: Option<FieldName>, chunk_size: usize) -> anyhow::Result<DatabaseClient> {
-                
-        let client = QdrantClient::from_url(connection_string).with_api_key(api_key.to_owned()).build()?;
-        let arc_client = Arc::new(client);
-        
+    /// Points are upserted in requests of at most `chunk_size` points
+    pub async fn new(connection_string: &str, api_key: &Option<String>, collection_name: &str, chunk_size: usize) -> anyhow::Result<DatabaseClient> {
+        let options = ConnectionOptions { api_key: api_key.to_owned(), ..Default::default() };
+        DatabaseClient::connect(connection_string, &options, collection_name, chunk_size).await
+    }
+
+    /// Same as [`DatabaseClient::new`], with the TLS, timeout and keep-alive settings of the connection
+    pub async fn connect(connection_string: &str, options: &ConnectionOptions, collection_name: &str, chunk_size: usize) -> anyhow::Result<DatabaseClient> {
+        let client = QdrantGrpc::connect(connection_string, options).await?;
+
         let database_client = DatabaseClient{
-            client: arc_client,
+            client,
             collection_name: collection_name.to_owned(),
-            vector_field,
-            id_field: id_field_name.to_owned(),
-            payload_field,
             write_ordering: None,
-            chunk_size
+            wait: true,
+            chunk_size,
+            max_chunk_bytes: None,
+            rate_limiter: None,
+            adaptive_chunk_size: None,
+            metrics: None,
         };
 
         Ok(database_client)
     }
 
-    pub async fn insert_batch(&self, batch: Vec<serde_json::Value>) -> anyhow::Result<()> {
-        
-        let points = batch_to_points(batch, self.id_field.clone(), &self.vector_field, &self.payload_field)?;
-        self.client.upsert_points_batch_blocking(&self.collection_name, points, self.write_ordering.clone(), self.chunk_size).await?;
+    /// Ordering of the upserts, Qdrant's default (weak) if not set
+    pub fn with_write_ordering(mut self, write_ordering: Option<WriteOrderingKind>) -> Self {
+        self.write_ordering = write_ordering.map(WriteOrderingKind::write_ordering);
+        self
+    }
+
+    /// Whether each upsert waits for its points to be applied. Without waiting, upserts return once Qdrant has
+    /// accepted them and [`Sink::flush`] waits for the update queue of the collection to be empty
+    pub fn with_wait(mut self, wait: bool) -> Self {
+        self.wait = wait;
+        self
+    }
+
+    /// Splits the upserts so that none carries more than `max_chunk_bytes` of encoded points, such as to stay under
+    /// the gRPC message size limit of Qdrant whatever the size of the vectors and payloads
+    pub fn with_max_chunk_bytes(mut self, max_chunk_bytes: Option<usize>) -> Self {
+        self.max_chunk_bytes = max_chunk_bytes;
+        self
+    }
+
+    /// Limits the points and the bytes of encoded points upserted per second. The limits are shared with the clients
+    /// returned by [`DatabaseClient::for_collection`]
+    pub fn with_rate_limit(mut self, max_points_per_second: Option<u64>, max_bytes_per_second: Option<u64>) -> Self {
+        self.rate_limiter =
+            if max_points_per_second.is_some() || max_bytes_per_second.is_some() {
+                Some(Arc::new(RateLimiter::new(max_points_per_second, max_bytes_per_second)))
+            } else {
+                None
+            };
+        self
+    }
+
+    /// Adapts the chunk size, between `min_chunk_size` and the chunk size given to [`DatabaseClient::new`], to keep
+    /// upserts under `target_latency`. Upserts failing with a transient error are then retried with smaller chunks
+    pub fn with_adaptive_chunk_size(mut self, min_chunk_size: usize, target_latency: Duration) -> Self {
+        self.adaptive_chunk_size = Some(Arc::new(AdaptiveChunkSize::new(min_chunk_size, self.chunk_size, target_latency)));
+        self
+    }
+
+    /// Metrics counting the retried upserts
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Returns a client sharing the same connection, but writing into another collection
+    pub fn for_collection(&self, collection_name: &str) -> DatabaseClient {
+        DatabaseClient {
+            collection_name: collection_name.to_owned(),
+            ..self.clone()
+        }
+    }
+
+    pub fn collection_name(&self) -> &str {
+        &self.collection_name
+    }
+
+    pub(super) fn qdrant_client(&self) -> &QdrantGrpc {
+        &self.client
+    }
+
+    pub(super) async fn collection_info(&self) -> anyhow::Result<Option<CollectionInfo>> {
+        let request = GetCollectionInfoRequest { collection_name: self.collection_name.clone() };
+        let response = self.client.collections().get(request).await?;
+        Ok(response.into_inner().result)
+    }
+
+    /// Fails if the collection has no keyword or uuid payload index on the given field, required to filter tenants
+    /// efficiently
+    pub async fn validate_payload_index(&self, field_name: &str) -> anyhow::Result<()> {
+        let collection_info = self.collection_info().await?;
+        let data_type = collection_info
+            .and_then(|info| info.payload_schema.get(field_name).map(|schema_info| schema_info.data_type));
+
+        match data_type.map(PayloadSchemaType::try_from) {
+            Some(Ok(PayloadSchemaType::Keyword | PayloadSchemaType::Uuid)) => Ok(()),
+            Some(data_type) => {
+                let data_type = data_type.map(|data_type| data_type.as_str_name()).unwrap_or("unknown");
+                anyhow::bail!("Collection {collection} has a {data_type} payload index for the tenant field {field_name}, a keyword or uuid index is required",
+                    collection=self.collection_name)
+            },
+            None => anyhow::bail!("Collection {collection} has no payload index for the tenant field {field_name}", collection=self.collection_name),
+        }
+    }
+
+    /// Creates a keyword payload index marked as holding tenant ids, for collections created by the uploader
+    pub async fn create_tenant_index(&self, field_name: &str) -> anyhow::Result<()> {
+        let keyword_index_params = KeywordIndexParams { is_tenant: Some(true), ..Default::default() };
+        let create_field_index = CreateFieldIndexCollection {
+            collection_name: self.collection_name.clone(),
+            wait: Some(true),
+            field_name: field_name.to_owned(),
+            field_type: Some(FieldType::Keyword as i32),
+            field_index_params: Some(PayloadIndexParams { index_params: Some(IndexParams::KeywordIndexParams(keyword_index_params)) }),
+            ..Default::default()
+        };
+
+        self.client.points().create_field_index(create_field_index).await?;
+        tracing::info!("Tenant index for field {field_name} created in collection {collection}", collection=self.collection_name);
         Ok(())
     }
-}
\ No newline at end of file
+
+    /// Deletes the collection, if it exists, and creates it again using the given settings, with one vector per vector field
+    pub async fn recreate_collection(&self, settings: &CollectionSettings, vector_field: &FieldName) -> anyhow::Result<()> {
+        if self.collection_exists().await? {
+            let delete_collection = DeleteCollection { collection_name: self.collection_name.clone(), ..Default::default() };
+            self.client.collections().delete(delete_collection).await?;
+            tracing::info!("Collection {collection} deleted", collection=self.collection_name);
+        }
+
+        let create_collection = settings.make_create_collection(&self.collection_name, vector_field)?;
+        self.client.collections().create(create_collection).await?;
+        tracing::info!("Collection {collection} created", collection=self.collection_name);
+
+        Ok(())
+    }
+
+    pub async fn collection_exists(&self) -> anyhow::Result<bool> {
+        let exists_request = CollectionExistsRequest { collection_name: self.collection_name.clone() };
+        let exists = self.client.collections().collection_exists(exists_request).await?
+            .into_inner().result
+            .map(|result| result.exists)
+            .unwrap_or(false);
+        Ok(exists)
+    }
+
+    pub async fn optimizers_config(&self) -> anyhow::Result<OptimizersConfigDiff> {
+        let collection_info = self.collection_info().await?;
+        let maybe_optimizers_config = collection_info
+            .and_then(|info| info.config)
+            .and_then(|config| config.optimizer_config);
+
+        match maybe_optimizers_config {
+            Some(optimizers_config) => Ok(optimizers_config),
+            None => anyhow::bail!("Could not load the optimizers configuration of collection {collection}", collection=self.collection_name)
+        }
+    }
+
+    pub async fn update_optimizers_config(&self, optimizers_config: &OptimizersConfigDiff) -> anyhow::Result<()> {
+        let update_collection = UpdateCollection {
+            collection_name: self.collection_name.clone(),
+            optimizers_config: Some(*optimizers_config),
+            ..Default::default()
+        };
+        self.client.collections().update(update_collection).await?;
+        Ok(())
+    }
+
+    /// Polls the collection until every segment is optimized and indexed, failing once `timeout` has elapsed
+    pub async fn wait_for_green(&self, poll_interval: Duration, timeout: Duration) -> anyhow::Result<()> {
+        tracing::info!("Waiting for collection {collection} to be green", collection=self.collection_name);
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            let collection_info = self.collection_info().await?;
+            let status = collection_info.map(|info| info.status).unwrap_or_default();
+
+            if status == CollectionStatus::Green as i32 {
+                tracing::info!("Collection {collection} is green", collection=self.collection_name);
+                return Ok(());
+            } else if status == CollectionStatus::Red as i32 {
+                anyhow::bail!("Collection {collection} is red", collection=self.collection_name);
+            } else if Instant::now() >= deadline {
+                let status = CollectionStatus::try_from(status).map_or("unknown", |status| status.as_str_name());
+                anyhow::bail!("Collection {collection} is still {status} after {timeout:?}", collection=self.collection_name);
+            }
+
+            tokio::time::sleep(poll_interval.min(deadline.saturating_duration_since(Instant::now()))).await;
+        }
+    }
+
+    pub async fn count_points(&self) -> anyhow::Result<u64> {
+        let request = CountPoints {
+            collection_name: self.collection_name.clone(),
+            exact: Some(true),
+            ..Default::default()
+        };
+
+        let response = self.client.points().count(request).await?.into_inner();
+        let count = response.result.map(|result| result.count).unwrap_or_default();
+        Ok(count)
+    }
+
+    async fn upsert_chunk(&self, shard_key_selector: Option<ShardKeySelector>, chunk: Vec<PointStruct>) -> anyhow::Result<()> {
+        let chunk_span = tracing::info_span!("upsert_chunk", collection=%self.collection_name, points=chunk.len());
+        let upsert_points = UpsertPoints {
+            collection_name: self.collection_name.clone(),
+            wait: Some(self.wait),
+            points: chunk,
+            ordering: self.write_ordering,
+            shard_key_selector,
+            ..Default::default()
+        };
+
+        self.client.points().upsert(upsert_points)
+            .instrument(chunk_span)
+            .await?;
+        Ok(())
+    }
+}
+
+
+#[async_trait]
+impl Sink for DatabaseClient {
+
+    /// Upserts the points in chunks of `chunk_size` points and at most `max_chunk_bytes`, one span each
+    async fn write_points(&self, shard_key: Option<ShardKey>, mut points: Vec<PointStruct>) -> anyhow::Result<()> {
+        let shard_key_selector = shard_key.map(|key| ShardKeySelector { shard_keys: vec![key.into()], fallback: None });
+        let mut failed_upserts = 0;
+        while !points.is_empty() {
+            let chunk_size = self.adaptive_chunk_size.as_ref().map_or(self.chunk_size, |adaptive| adaptive.chunk_size());
+            let mut chunk = take_chunk(&mut points, chunk_size, self.max_chunk_bytes);
+
+            if let Some(rate_limiter) = self.rate_limiter.as_ref() {
+                rate_limiter.acquire(&chunk).await;
+            }
+
+            let Some(adaptive_chunk_size) = self.adaptive_chunk_size.as_ref() else {
+                self.upsert_chunk(shard_key_selector.clone(), chunk).await?;
+                continue;
+            };
+
+            let upsert_start = Instant::now();
+            match self.upsert_chunk(shard_key_selector.clone(), chunk.clone()).await {
+                Ok(()) => {
+                    adaptive_chunk_size.record_success(upsert_start.elapsed());
+                    failed_upserts = 0;
+                },
+                Err(error) if failed_upserts < MAX_CONSECUTIVE_FAILED_UPSERTS && is_transient(&error) => {
+                    failed_upserts += 1;
+                    adaptive_chunk_size.record_failure();
+                    if let Some(metrics) = self.metrics.as_ref() {
+                        metrics.record_retry();
+                    }
+                    tracing::warn!("Upsert of {points} points failed, retrying with smaller chunks: {error}", points=chunk.len());
+                    tokio::time::sleep(FAILED_UPSERT_BACKOFF * failed_upserts).await;
+
+                    chunk.append(&mut points);
+                    points = chunk;
+                },
+                Err(error) => return Err(error),
+            }
+        }
+        Ok(())
+    }
+
+    /// Without waiting for each upsert, polls the collection until its update queue is empty. The queue is the one of
+    /// the node answering, other replicas may still be applying the upserts unless they were sent with a medium or
+    /// strong write ordering
+    async fn flush(&self) -> anyhow::Result<()> {
+        if self.wait {
+            return Ok(());
+        }
+
+        tracing::info!("Waiting for the upserts into collection {collection} to be applied", collection=self.collection_name);
+        async {
+            loop {
+                let update_queue = self.collection_info().await?.and_then(|info| info.update_queue);
+                match update_queue {
+                    Some(update_queue) if update_queue.length > 0 => tokio::time::sleep(UPDATE_QUEUE_POLL_INTERVAL).await,
+                    Some(_) => return Ok(()),
+                    None => {
+                        tracing::warn!("Qdrant does not report the update queue of collection {collection}, the last upserts may not be applied yet",
+                            collection=self.collection_name);
+                        return Ok(());
+                    },
+                }
+            }
+        }
+            .instrument(tracing::info_span!("flush", collection=%self.collection_name))
+            .await
+    }
+}
+
+
+fn is_transient(error: &anyhow::Error) -> bool {
+    error.downcast_ref::<tonic::Status>().is_some_and(|status| TRANSIENT_UPSERT_ERRORS.contains(&status.code()))
+}
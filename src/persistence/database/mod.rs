@@ -1,4 +1,19 @@
+mod adaptive_chunk_size;
+mod alias_switch;
 mod batch_processor;
+mod bulk_load_guard;
+mod collection_settings;
+mod connection_options;
 mod database_client;
-pub use database_client::DatabaseClient;
-
+mod point_chunks;
+mod point_mapper;
+mod qdrant_grpc;
+mod rate_limiter;
+mod upload_verifier;
+pub(crate) use alias_switch::AliasSwitch;
+pub(crate) use bulk_load_guard::BulkLoadGuard;
+pub use collection_settings::{CollectionSettings, QuantizationKind, VectorDistance};
+pub use connection_options::{ConnectionOptions, TlsSettings};
+pub use database_client::{DatabaseClient, WriteOrderingKind};
+pub use point_mapper::{PointMapper, PointMapperBuilder};
+pub use upload_verifier::UploadVerifier;
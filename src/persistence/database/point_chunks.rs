@@ -0,0 +1,38 @@
+use prost::Message;
+use qdrant_client::qdrant::PointStruct;
+
+/// Bytes kept in each upsert request for the fields besides the points: the collection name, of at most 255 bytes,
+/// the shard key selector, the ordering and the wait flag
+const UPSERT_REQUEST_OVERHEAD: usize = 1024;
+
+/// Bytes taken by the point in the repeated `points` field of an upsert request
+pub(super) fn encoded_size(point: &PointStruct) -> usize {
+    let length = point.encoded_len();
+    1 + prost::length_delimiter_len(length) + length
+}
+
+/// Removes and returns the first points, at most `max_points` of them and, if given, few enough that their upsert
+/// request is at most `max_bytes`. A single point too large for it is returned alone
+pub(super) fn take_chunk(points: &mut Vec<PointStruct>, max_points: usize, max_bytes: Option<usize>) -> Vec<PointStruct> {
+    let mut chunk_length = points.len().min(max_points);
+
+    if let Some(max_bytes) = max_bytes {
+        let max_points_bytes = max_bytes.saturating_sub(UPSERT_REQUEST_OVERHEAD);
+        let mut chunk_bytes = 0;
+        for (index, point) in points.iter().take(chunk_length).enumerate() {
+            chunk_bytes += encoded_size(point);
+            if chunk_bytes > max_points_bytes {
+                if index == 0 {
+                    tracing::warn!("A point of {chunk_bytes} bytes is too large for --max-batch-bytes, it is upserted alone");
+                    chunk_length = 1;
+                } else {
+                    chunk_length = index;
+                }
+                break;
+            }
+        }
+    }
+
+    let remaining_points = points.split_off(chunk_length);
+    std::mem::replace(points, remaining_points)
+}
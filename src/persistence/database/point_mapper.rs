@@ -0,0 +1,96 @@
+use qdrant_client::qdrant::PointStruct;
+use qdrant_client::qdrant::shard_key::Key as ShardKey;
+
+use crate::failure::FailureKind;
+use crate::persistence::vector_field_name::FieldName;
+
+use super::batch_processor::{batch_to_points, batch_to_sharded_points, value_to_point};
+
+/// Converts source rows into Qdrant points, reading the id, vectors, payload and shard key from the configured fields
+#[derive(Clone)]
+pub struct PointMapper {
+    id_field: Option<String>,
+    vector_field: FieldName,
+    payload_field: Option<FieldName>,
+    shard_key_field: Option<String>,
+}
+
+impl PointMapper {
+
+    pub fn builder() -> PointMapperBuilder {
+        PointMapperBuilder::default()
+    }
+
+    pub fn vector_field(&self) -> &FieldName {
+        &self.vector_field
+    }
+
+    pub fn to_point(&self, row: &serde_json::Value) -> PointStruct {
+        value_to_point(row, &self.id_field, &self.vector_field, &self.payload_field)
+    }
+
+    /// Maps a batch of rows, grouping the points by shard key when a shard key field is configured
+    pub fn to_points(&self, batch: Vec<serde_json::Value>) -> anyhow::Result<Vec<(Option<ShardKey>, Vec<PointStruct>)>> {
+        match self.shard_key_field.as_ref() {
+            Some(shard_key_field) => {
+                batch_to_sharded_points(batch, self.id_field.clone(), &self.vector_field, &self.payload_field, shard_key_field)
+            },
+            None => {
+                let points = batch_to_points(batch, self.id_field.clone(), &self.vector_field, &self.payload_field)?;
+                Ok(vec![(None, points)])
+            }
+        }
+    }
+}
+
+
+#[derive(Default)]
+pub struct PointMapperBuilder {
+    id_field: Option<String>,
+    vector_field: Option<FieldName>,
+    payload_field: Option<FieldName>,
+    shard_key_field: Option<String>,
+}
+
+impl PointMapperBuilder {
+
+    /// Field holding the point id, an unsigned integer or an UUID string. Points of rows without a valid id,
+    /// or of every row if not set, are mapped without id
+    pub fn id_field(mut self, id_field: &str) -> Self {
+        self.id_field = Some(id_field.to_owned());
+        self
+    }
+
+    pub fn vector_field(mut self, vector_field: FieldName) -> Self {
+        self.vector_field = Some(vector_field);
+        self
+    }
+
+    /// Field whose object is the payload, or fields copied to the payload. Points have an empty payload if not set
+    pub fn payload_field(mut self, payload_field: FieldName) -> Self {
+        self.payload_field = Some(payload_field);
+        self
+    }
+
+    /// Field holding the shard key, a string or a non negative integer. Rows without it are mapped without shard key
+    pub fn shard_key_field(mut self, shard_key_field: &str) -> Self {
+        self.shard_key_field = Some(shard_key_field.to_owned());
+        self
+    }
+
+    /// Fails with [`FailureKind::Config`] if the vector field was not set
+    pub fn build(self) -> anyhow::Result<PointMapper> {
+        let Some(vector_field) = self.vector_field else {
+            return Err(anyhow::anyhow!("A point mapper requires a vector field").context(FailureKind::Config));
+        };
+
+        let point_mapper = PointMapper {
+            id_field: self.id_field,
+            vector_field,
+            payload_field: self.payload_field,
+            shard_key_field: self.shard_key_field,
+        };
+
+        Ok(point_mapper)
+    }
+}
@@ -0,0 +1,101 @@
+use std::time::Duration;
+
+use anyhow::Context;
+use qdrant_client::qdrant::collections_client::CollectionsClient;
+use qdrant_client::qdrant::points_client::PointsClient;
+use tonic::metadata::AsciiMetadataValue;
+use tonic::service::Interceptor;
+use tonic::service::interceptor::InterceptedService;
+use tonic::transport::{Channel, ClientTlsConfig, Endpoint};
+
+use super::connection_options::ConnectionOptions;
+
+/// Defaults of the Qdrant client
+const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+/// Interval of the keep-alive pings sent while idle, when no interval is given
+const DEFAULT_KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(30);
+
+const API_KEY_HEADER: &str = "api-key";
+
+type QdrantService = InterceptedService<Channel, ApiKeyInterceptor>;
+
+/// Generated clients of the Qdrant points and collections gRPC services, sharing one channel
+#[derive(Clone)]
+pub(super) struct QdrantGrpc {
+    points: PointsClient<QdrantService>,
+    collections: CollectionsClient<QdrantService>,
+}
+
+impl QdrantGrpc {
+
+    /// Connects right away, so that an unreachable server or a failed TLS handshake is reported before anything is
+    /// read from the source
+    pub(super) async fn connect(url: &str, options: &ConnectionOptions) -> anyhow::Result<QdrantGrpc> {
+        let connect_timeout = options.connect_timeout.unwrap_or(DEFAULT_CONNECT_TIMEOUT);
+        let mut endpoint = Endpoint::from_shared(url.to_owned())
+            .with_context(|| format!("Invalid connection string {url}"))?
+            .timeout(options.request_timeout.unwrap_or(DEFAULT_REQUEST_TIMEOUT))
+            .connect_timeout(connect_timeout);
+
+        // Without an interval no ping is sent at all, idle or not
+        let keep_alive_interval = options.keep_alive_interval
+            .or(options.keep_alive_while_idle.then_some(DEFAULT_KEEP_ALIVE_INTERVAL));
+        if let Some(keep_alive_interval) = keep_alive_interval {
+            endpoint = endpoint
+                .http2_keep_alive_interval(keep_alive_interval)
+                .keep_alive_while_idle(options.keep_alive_while_idle);
+        }
+        if let Some(keep_alive_timeout) = options.keep_alive_timeout {
+            endpoint = endpoint.keep_alive_timeout(keep_alive_timeout);
+        }
+
+        match (endpoint.uri().scheme_str(), options.tls.as_ref()) {
+            (Some("https"), Some(tls)) => {
+                endpoint = endpoint.tls_config(tls.client_config.clone().timeout(connect_timeout)).context("Invalid TLS settings")?;
+            },
+            (Some("https"), None) => {
+                endpoint = endpoint.tls_config(ClientTlsConfig::new().with_native_roots().timeout(connect_timeout))?;
+            },
+            (_, Some(_)) => anyhow::bail!("TLS options require an https:// connection string"),
+            (_, None) => {},
+        }
+
+        let channel = endpoint.connect().await.with_context(|| format!("Could not connect to Qdrant at {url}"))?;
+
+        let api_key = options.api_key.as_deref()
+            .map(|api_key| api_key.parse::<AsciiMetadataValue>().context("The API key is not a valid header value"))
+            .transpose()?;
+        let interceptor = ApiKeyInterceptor { api_key };
+
+        Ok(QdrantGrpc {
+            points: PointsClient::with_interceptor(channel.clone(), interceptor.clone()),
+            collections: CollectionsClient::with_interceptor(channel, interceptor),
+        })
+    }
+
+    /// The generated methods take `&mut self`, each call works on its own cheap clone
+    pub(super) fn points(&self) -> PointsClient<QdrantService> {
+        self.points.clone()
+    }
+
+    pub(super) fn collections(&self) -> CollectionsClient<QdrantService> {
+        self.collections.clone()
+    }
+}
+
+
+/// Sends the API key, if any, with every request
+#[derive(Clone)]
+pub(super) struct ApiKeyInterceptor {
+    api_key: Option<AsciiMetadataValue>,
+}
+
+impl Interceptor for ApiKeyInterceptor {
+    fn call(&mut self, mut request: tonic::Request<()>) -> Result<tonic::Request<()>, tonic::Status> {
+        if let Some(api_key) = self.api_key.as_ref() {
+            request.metadata_mut().insert(API_KEY_HEADER, api_key.clone());
+        }
+        Ok(request)
+    }
+}
@@ -0,0 +1,74 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use qdrant_client::qdrant::PointStruct;
+
+use super::point_chunks::encoded_size;
+
+/// Delays upserts so that at most the given number of points and of encoded bytes are sent per second
+pub(super) struct RateLimiter {
+    points: Option<TokenBucket>,
+    bytes: Option<TokenBucket>,
+}
+
+impl RateLimiter {
+    pub(super) fn new(max_points_per_second: Option<u64>, max_bytes_per_second: Option<u64>) -> Self {
+        RateLimiter {
+            points: max_points_per_second.map(TokenBucket::new),
+            bytes: max_bytes_per_second.map(TokenBucket::new),
+        }
+    }
+
+    /// Waits until the points can be sent
+    pub(super) async fn acquire(&self, points: &[PointStruct]) {
+        let points_delay = self.points.as_ref()
+            .map(|bucket| bucket.take(points.len() as u64))
+            .unwrap_or_default();
+        let bytes_delay = self.bytes.as_ref()
+            .map(|bucket| bucket.take(points.iter().map(|point| encoded_size(point) as u64).sum()))
+            .unwrap_or_default();
+
+        let delay = points_delay.max(bytes_delay);
+        if !delay.is_zero() {
+            tracing::debug!(delay_ms=delay.as_millis() as u64, "Upsert throttled");
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+
+/// Refilled with `rate` tokens per second, up to one second worth of tokens. Taking more tokens than available
+/// leaves the bucket in debt, which the caller pays by waiting for the returned delay
+struct TokenBucket {
+    rate: f64,
+    state: Mutex<BucketState>,
+}
+
+struct BucketState {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: u64) -> Self {
+        let rate = rate as f64;
+        TokenBucket {
+            rate,
+            state: Mutex::new(BucketState { tokens: rate, refilled_at: Instant::now() }),
+        }
+    }
+
+    fn take(&self, tokens: u64) -> Duration {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        state.tokens = (state.tokens + now.duration_since(state.refilled_at).as_secs_f64() * self.rate).min(self.rate);
+        state.refilled_at = now;
+        state.tokens -= tokens as f64;
+
+        if state.tokens < 0.0 {
+            Duration::from_secs_f64(-state.tokens / self.rate)
+        } else {
+            Duration::ZERO
+        }
+    }
+}
@@ -0,0 +1,241 @@
+use std::collections::{HashMap, HashSet};
+
+use qdrant_client::qdrant::{Distance, GetPoints, PointId, PointStruct, RetrievedPoint, Value as QdrantValue, Vector, VectorOutput, Vectors,
+                            VectorsOutput};
+use qdrant_client::qdrant::point_id::PointIdOptions;
+use qdrant_client::qdrant::value::Kind;
+use qdrant_client::qdrant::{vector, vector_output};
+use qdrant_client::qdrant::vectors::VectorsOptions;
+use qdrant_client::qdrant::vectors_output::VectorsOptions as VectorsOutputOptions;
+use qdrant_client::qdrant::vectors_config::Config as VectorsConfigOptions;
+use rand::Rng;
+
+use crate::embedding::normalize;
+
+use super::{DatabaseClient, PointMapper};
+
+/// Name used to look up the distance of a non named vector
+const DEFAULT_VECTOR_NAME: &str = "";
+
+/// Keeps track of what was uploaded during a run so it can be checked against the collection afterwards:
+/// every distinct point id, and a uniformly sampled (reservoir) set of the source rows converted to points.
+pub struct UploadVerifier {
+    sample_size: usize,
+    tolerance: f32,
+
+    point_ids: HashSet<String>,
+    duplicate_ids: u64,
+    sample: Vec<PointStruct>,
+    rows_seen: u64,
+}
+
+impl UploadVerifier {
+
+    pub fn new(sample_size: usize, tolerance: f32) -> UploadVerifier {
+        UploadVerifier {
+            sample_size,
+            tolerance,
+            point_ids: HashSet::new(),
+            duplicate_ids: 0,
+            sample: Vec::with_capacity(sample_size),
+            rows_seen: 0,
+        }
+    }
+
+    pub fn observe_batch(&mut self, point_mapper: &PointMapper, batch: &[serde_json::Value]) {
+        let mut random = rand::thread_rng();
+
+        for row in batch {
+            let point = point_mapper.to_point(row);
+            if let Some(point_key) = point.id.as_ref().and_then(point_id_key) {
+                if !self.point_ids.insert(point_key) {
+                    self.duplicate_ids += 1;
+                    // The row overwrites the point of the previous one, which must not be expected anymore
+                    for sampled_point in self.sample.iter_mut().filter(|sampled_point| sampled_point.id == point.id) {
+                        *sampled_point = point.clone();
+                    }
+                }
+            }
+
+            self.rows_seen += 1;
+            if self.sample.len() < self.sample_size {
+                self.sample.push(point);
+            } else {
+                let position = random.gen_range(0..self.rows_seen) as usize;
+                if position < self.sample_size {
+                    self.sample[position] = point;
+                }
+            }
+        }
+    }
+
+    /// Rows whose point id was already uploaded during the run, each overwriting the previous point
+    pub fn duplicate_ids(&self) -> u64 {
+        self.duplicate_ids
+    }
+
+    pub fn distinct_ids(&self) -> u64 {
+        self.point_ids.len() as u64
+    }
+
+    /// Fails if the collection point count differs from the distinct uploaded ids or if any sampled point
+    /// does not match its source row
+    pub async fn verify(&self, database_client: &DatabaseClient) -> anyhow::Result<()> {
+        let collection_name = database_client.collection_name();
+        let points_count = database_client.count_points().await?;
+        let distinct_ids = self.distinct_ids();
+
+        if points_count != distinct_ids {
+            anyhow::bail!("Verification failed: collection {collection_name} has {points_count} points, but {distinct_ids} distinct ids were uploaded");
+        }
+
+        tracing::info!("Collection {collection_name} has the expected {points_count} points");
+
+        let distances = load_vector_distances(database_client).await?;
+        let retrieved_points = self.retrieve_sample(database_client).await?;
+
+        let mut mismatches = 0;
+        for expected_point in self.sample.iter() {
+            let Some(point_key) = expected_point.id.as_ref().and_then(point_id_key) else {
+                continue;
+            };
+
+            match retrieved_points.get(&point_key) {
+                None => {
+                    tracing::error!("Point {point_key} was not found in collection {collection_name}");
+                    mismatches += 1;
+                },
+                Some(retrieved_point) => {
+                    if !same_payload(&expected_point.payload, &retrieved_point.payload) {
+                        tracing::error!("Point {point_key} payload differs from the source row");
+                        mismatches += 1;
+                    } else if !same_vectors(&expected_point.vectors, &retrieved_point.vectors, &distances, self.tolerance) {
+                        tracing::error!("Point {point_key} vectors differ from the source row");
+                        mismatches += 1;
+                    }
+                }
+            }
+        }
+
+        if mismatches > 0 {
+            anyhow::bail!("Verification failed: {mismatches} of {sampled} sampled points differ from the source", sampled=self.sample.len());
+        }
+
+        tracing::info!("Verification succeeded: {sampled} sampled points match the source", sampled=self.sample.len());
+        Ok(())
+    }
+
+    async fn retrieve_sample(&self, database_client: &DatabaseClient) -> anyhow::Result<HashMap<String, RetrievedPoint>> {
+        let point_ids: Vec<PointId> = self.sample.iter().filter_map(|point| point.id.clone()).collect();
+        if point_ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let get_points = GetPoints {
+            collection_name: database_client.collection_name().to_owned(),
+            ids: point_ids,
+            with_payload: Some(true.into()),
+            with_vectors: Some(true.into()),
+            ..Default::default()
+        };
+        let response = database_client.qdrant_client().points().get(get_points).await?.into_inner();
+
+        let retrieved_points = response.result.into_iter()
+            .filter_map(|point| {
+                point.id.as_ref().and_then(point_id_key).map(|point_key| (point_key, point))
+            })
+            .collect();
+
+        Ok(retrieved_points)
+    }
+}
+
+
+fn point_id_key(point_id: &PointId) -> Option<String> {
+    match point_id.point_id_options.as_ref() {
+        Some(PointIdOptions::Num(number)) => Some(number.to_string()),
+        Some(PointIdOptions::Uuid(uuid)) => Some(uuid.to_lowercase()),
+        None => None
+    }
+}
+
+async fn load_vector_distances(database_client: &DatabaseClient) -> anyhow::Result<HashMap<String, Distance>> {
+    let collection_info = database_client.collection_info().await?;
+
+    let maybe_vectors_config = collection_info
+        .and_then(|info| info.config)
+        .and_then(|config| config.params)
+        .and_then(|params| params.vectors_config)
+        .and_then(|vectors_config| vectors_config.config);
+
+    let distances = match maybe_vectors_config {
+        Some(VectorsConfigOptions::Params(params)) => {
+            let distance = Distance::try_from(params.distance).unwrap_or(Distance::UnknownDistance);
+            HashMap::from([(DEFAULT_VECTOR_NAME.to_owned(), distance)])
+        },
+        Some(VectorsConfigOptions::ParamsMap(params_map)) => {
+            params_map.map.into_iter().map(|(name, params)| {
+                let distance = Distance::try_from(params.distance).unwrap_or(Distance::UnknownDistance);
+                (name, distance)
+            }).collect()
+        },
+        None => HashMap::new()
+    };
+
+    Ok(distances)
+}
+
+/// Missing fields are uploaded as empty values, which Qdrant returns as null
+fn same_payload(expected: &HashMap<String, QdrantValue>, actual: &HashMap<String, QdrantValue>) -> bool {
+    let keys: HashSet<&String> = expected.keys().chain(actual.keys()).collect();
+
+    keys.into_iter().all(|key| {
+        let expected_kind = expected.get(key).and_then(|value| value.kind.as_ref()).filter(|kind| !is_null(kind));
+        let actual_kind = actual.get(key).and_then(|value| value.kind.as_ref()).filter(|kind| !is_null(kind));
+        expected_kind == actual_kind
+    })
+}
+
+fn is_null(kind: &Kind) -> bool {
+    matches!(kind, Kind::NullValue(_))
+}
+
+fn same_vectors(expected: &Option<Vectors>, actual: &Option<VectorsOutput>, distances: &HashMap<String, Distance>, tolerance: f32) -> bool {
+    let expected_options = expected.as_ref().and_then(|vectors| vectors.vectors_options.as_ref());
+    let actual_options = actual.as_ref().and_then(|vectors| vectors.vectors_options.as_ref());
+
+    match (expected_options, actual_options) {
+        (None, None) => true,
+        (Some(VectorsOptions::Vector(expected_vector)), Some(VectorsOutputOptions::Vector(actual_vector))) => {
+            same_vector(expected_vector, actual_vector, distances.get(DEFAULT_VECTOR_NAME), tolerance)
+        },
+        (Some(VectorsOptions::Vectors(expected_vectors)), Some(VectorsOutputOptions::Vectors(actual_vectors))) => {
+            expected_vectors.vectors.len() == actual_vectors.vectors.len() &&
+            expected_vectors.vectors.iter().all(|(name, expected_vector)| {
+                actual_vectors.vectors.get(name)
+                    .map(|actual_vector| same_vector(expected_vector, actual_vector, distances.get(name), tolerance))
+                    .unwrap_or(false)
+            })
+        },
+        _ => false
+    }
+}
+
+/// Qdrant stores cosine vectors normalized, so the source vector is normalized before comparing
+fn same_vector(expected: &Vector, actual: &VectorOutput, distance: Option<&Distance>, tolerance: f32) -> bool {
+    let (Some(vector::Vector::Dense(expected)), vector_output::Vector::Dense(actual)) = (expected.vector.as_ref(), actual.clone().into_vector()) else {
+        return false;
+    };
+    if expected.data.len() != actual.data.len() {
+        return false;
+    }
+
+    let expected_data = match distance {
+        Some(Distance::Cosine) => normalize(&expected.data),
+        _ => expected.data.clone()
+    };
+
+    expected_data.iter()
+        .zip(actual.data.iter())
+        .all(|(expected_coordinate, actual_coordinate)| (expected_coordinate - actual_coordinate).abs() <= tolerance)
+}
@@ -0,0 +1,249 @@
+use arrow_array::cast::AsArray;
+use arrow_array::types::*;
+use arrow_array::{Array, RecordBatch};
+use arrow_buffer::Buffer;
+use arrow_ipc::reader::StreamDecoder;
+use arrow_schema::{DataType, TimeUnit};
+use serde_json::{Map, Value};
+use tokio::io::{AsyncBufRead, AsyncReadExt};
+
+use super::typed_values::{hex_value, optional_string};
+
+const FILE_MAGIC: &[u8] = b"ARROW1";
+const CONTINUATION_MARKER: [u8; 4] = [0xff; 4];
+/// Largest metadata announced by an Arrow IPC message, beyond which the input is taken for corrupt
+const MAX_METADATA_LENGTH: i32 = 16 * 1024 * 1024;
+/// Largest body announced by an Arrow IPC message, which holds one record batch
+const MAX_BODY_LENGTH: i64 = 1024 * 1024 * 1024;
+
+/// Rows of an Arrow IPC stream, or of an Arrow IPC file (Feather v2) read front to back without its footer.
+/// Messages are read one at a time, so only the current record batch is kept in memory
+pub struct ArrowRows<R> {
+    reader: R,
+    decoder: StreamDecoder,
+    batch: Option<RecordBatch>,
+    row: usize,
+    finished: bool,
+    /// Set until the first message of a file, whose magic may be padded up to 64 bytes
+    skip_padding: bool,
+}
+
+impl<R: AsyncBufRead + Unpin + Send> ArrowRows<R> {
+    pub fn stream(reader: R) -> Self {
+        ArrowRows { reader, decoder: StreamDecoder::new(), batch: None, row: 0, finished: false, skip_padding: false }
+    }
+
+    /// Fails if the input does not start with the magic bytes of an Arrow IPC file
+    pub async fn file(mut reader: R) -> anyhow::Result<Self> {
+        // The magic is padded to at least 8 bytes, the same messages as in a stream follow
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic).await?;
+        if &magic[..6] != FILE_MAGIC {
+            anyhow::bail!("Not an Arrow IPC file");
+        }
+
+        Ok(ArrowRows { skip_padding: true, ..ArrowRows::stream(reader) })
+    }
+
+    pub async fn next_row(&mut self) -> anyhow::Result<Option<Value>> {
+        loop {
+            if let Some(batch) = self.batch.as_ref() {
+                if self.row < batch.num_rows() {
+                    let row = batch_row(batch, self.row)?;
+                    self.row += 1;
+                    return Ok(Some(row));
+                }
+            }
+
+            match self.next_batch().await? {
+                Some(batch) => {
+                    self.batch = Some(batch);
+                    self.row = 0;
+                },
+                None => return Ok(None)
+            }
+        }
+    }
+
+    async fn next_batch(&mut self) -> anyhow::Result<Option<RecordBatch>> {
+        while !self.finished {
+            let Some(message) = self.next_message().await? else {
+                self.finished = true;
+                break;
+            };
+
+            if let Some(batch) = self.decoder.decode(&mut Buffer::from_vec(message))? {
+                return Ok(Some(batch));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Bytes of the next encapsulated message, its body included, or `None` at the end-of-stream marker.
+    /// Messages are framed here rather than by the decoder so that the footer of a file is never read
+    async fn next_message(&mut self) -> anyhow::Result<Option<Vec<u8>>> {
+        let mut prefix = [0u8; 4];
+        loop {
+            if !read_exact_or_eof(&mut self.reader, &mut prefix).await? {
+                return Ok(None);
+            }
+            if !self.skip_padding || prefix != [0u8; 4] {
+                break;
+            }
+        }
+        self.skip_padding = false;
+
+        let mut message = Vec::new();
+        if prefix == CONTINUATION_MARKER {
+            message.extend_from_slice(&prefix);
+            if !read_exact_or_eof(&mut self.reader, &mut prefix).await? {
+                return Ok(None);
+            }
+        }
+        message.extend_from_slice(&prefix);
+
+        let metadata_length = i32::from_le_bytes(prefix);
+        if metadata_length == 0 {
+            return Ok(None);
+        }
+        if !(0..=MAX_METADATA_LENGTH).contains(&metadata_length) {
+            anyhow::bail!("Invalid Arrow IPC message metadata of {metadata_length} bytes, at most {MAX_METADATA_LENGTH} are accepted");
+        }
+
+        let metadata_start = message.len();
+        message.resize(metadata_start + metadata_length as usize, 0);
+        self.reader.read_exact(&mut message[metadata_start..]).await?;
+
+        let body_length = arrow_ipc::root_as_message(&message[metadata_start..])
+            .map_err(|error| anyhow::anyhow!("Invalid Arrow IPC message: {error}"))?
+            .bodyLength();
+        if !(0..=MAX_BODY_LENGTH).contains(&body_length) {
+            anyhow::bail!("Invalid Arrow IPC message body of {body_length} bytes, at most {MAX_BODY_LENGTH} are accepted");
+        }
+
+        let body_start = message.len();
+        message.resize(body_start + body_length as usize, 0);
+        self.reader.read_exact(&mut message[body_start..]).await?;
+
+        Ok(Some(message))
+    }
+}
+
+
+/// Fills the buffer, or returns false if the input ends before its first byte
+async fn read_exact_or_eof<R: AsyncBufRead + Unpin>(reader: &mut R, buffer: &mut [u8]) -> anyhow::Result<bool> {
+    let mut read = 0;
+    while read < buffer.len() {
+        match reader.read(&mut buffer[read..]).await? {
+            0 if read == 0 => return Ok(false),
+            0 => anyhow::bail!("Unexpected end of Arrow IPC input"),
+            length => read += length,
+        }
+    }
+    Ok(true)
+}
+
+
+fn batch_row(batch: &RecordBatch, row: usize) -> anyhow::Result<Value> {
+    let mut object = Map::new();
+    for (field, column) in batch.schema().fields().iter().zip(batch.columns()) {
+        object.insert(field.name().to_owned(), array_value(column.as_ref(), row)?);
+    }
+    Ok(Value::Object(object))
+}
+
+/// JSON value of an element, keeping its type: lists of floats become arrays of numbers that can be read as
+/// vectors, dates and timestamps become RFC 3339 strings and binary values become hex strings
+fn array_value(array: &dyn Array, index: usize) -> anyhow::Result<Value> {
+    if array.is_null(index) {
+        return Ok(Value::Null);
+    }
+
+    let value = match array.data_type() {
+        DataType::Null => Value::Null,
+        DataType::Boolean => Value::from(array.as_boolean().value(index)),
+        DataType::Int8 => Value::from(array.as_primitive::<Int8Type>().value(index)),
+        DataType::Int16 => Value::from(array.as_primitive::<Int16Type>().value(index)),
+        DataType::Int32 => Value::from(array.as_primitive::<Int32Type>().value(index)),
+        DataType::Int64 => Value::from(array.as_primitive::<Int64Type>().value(index)),
+        DataType::UInt8 => Value::from(array.as_primitive::<UInt8Type>().value(index)),
+        DataType::UInt16 => Value::from(array.as_primitive::<UInt16Type>().value(index)),
+        DataType::UInt32 => Value::from(array.as_primitive::<UInt32Type>().value(index)),
+        DataType::UInt64 => Value::from(array.as_primitive::<UInt64Type>().value(index)),
+        DataType::Float16 => Value::from(array.as_primitive::<Float16Type>().value(index).to_f64()),
+        DataType::Float32 => Value::from(array.as_primitive::<Float32Type>().value(index) as f64),
+        DataType::Float64 => Value::from(array.as_primitive::<Float64Type>().value(index)),
+        DataType::Decimal128(_, _) => decimal_value(&array.as_primitive::<Decimal128Type>().value_as_string(index)),
+        DataType::Decimal256(_, _) => decimal_value(&array.as_primitive::<Decimal256Type>().value_as_string(index)),
+        DataType::Utf8 => Value::from(array.as_string::<i32>().value(index)),
+        DataType::LargeUtf8 => Value::from(array.as_string::<i64>().value(index)),
+        DataType::Utf8View => Value::from(array.as_string_view().value(index)),
+        DataType::Binary => hex_value(array.as_binary::<i32>().value(index)),
+        DataType::LargeBinary => hex_value(array.as_binary::<i64>().value(index)),
+        DataType::BinaryView => hex_value(array.as_binary_view().value(index)),
+        DataType::FixedSizeBinary(_) => hex_value(array.as_fixed_size_binary().value(index)),
+        DataType::Date32 => optional_string(array.as_primitive::<Date32Type>().value_as_date(index)),
+        DataType::Date64 => optional_string(array.as_primitive::<Date64Type>().value_as_date(index)),
+        DataType::Time32(TimeUnit::Second) => optional_string(array.as_primitive::<Time32SecondType>().value_as_time(index)),
+        DataType::Time32(_) => optional_string(array.as_primitive::<Time32MillisecondType>().value_as_time(index)),
+        DataType::Time64(TimeUnit::Microsecond) => optional_string(array.as_primitive::<Time64MicrosecondType>().value_as_time(index)),
+        DataType::Time64(_) => optional_string(array.as_primitive::<Time64NanosecondType>().value_as_time(index)),
+        DataType::Timestamp(unit, time_zone) => {
+            let date_time = match unit {
+                TimeUnit::Second => array.as_primitive::<TimestampSecondType>().value_as_datetime(index),
+                TimeUnit::Millisecond => array.as_primitive::<TimestampMillisecondType>().value_as_datetime(index),
+                TimeUnit::Microsecond => array.as_primitive::<TimestampMicrosecondType>().value_as_datetime(index),
+                TimeUnit::Nanosecond => array.as_primitive::<TimestampNanosecondType>().value_as_datetime(index),
+            };
+            // Values with a time zone are stored as UTC
+            let format = if time_zone.is_some() { "%Y-%m-%dT%H:%M:%S%.fZ" } else { "%Y-%m-%dT%H:%M:%S%.f" };
+            optional_string(date_time.map(|date_time| date_time.format(format)))
+        },
+        DataType::List(_) => list_value(array.as_list::<i32>().value(index).as_ref())?,
+        DataType::LargeList(_) => list_value(array.as_list::<i64>().value(index).as_ref())?,
+        DataType::FixedSizeList(_, _) => list_value(array.as_fixed_size_list().value(index).as_ref())?,
+        DataType::Struct(fields) => {
+            let structure = array.as_struct();
+            let mut object = Map::new();
+            for (field, column) in fields.iter().zip(structure.columns()) {
+                object.insert(field.name().to_owned(), array_value(column.as_ref(), index)?);
+            }
+            Value::Object(object)
+        },
+        DataType::Map(_, _) => {
+            let entries = array.as_map().value(index);
+            let mut object = Map::new();
+            for entry in 0..entries.len() {
+                let key = match array_value(entries.column(0).as_ref(), entry)? {
+                    Value::String(key) => key,
+                    key => key.to_string(),
+                };
+                object.insert(key, array_value(entries.column(1).as_ref(), entry)?);
+            }
+            Value::Object(object)
+        },
+        DataType::Dictionary(_, _) => {
+            let dictionary = array.as_any_dictionary();
+            let key = array_value(dictionary.keys(), index)?;
+            match key.as_u64() {
+                Some(key) => array_value(dictionary.values().as_ref(), key as usize)?,
+                None => anyhow::bail!("Invalid dictionary key {key}"),
+            }
+        },
+        data_type => anyhow::bail!("Arrow columns of type {data_type} are not supported"),
+    };
+
+    Ok(value)
+}
+
+fn list_value(elements: &dyn Array) -> anyhow::Result<Value> {
+    let values = (0..elements.len())
+        .map(|index| array_value(elements, index))
+        .collect::<anyhow::Result<_>>()?;
+    Ok(Value::Array(values))
+}
+
+fn decimal_value(decimal: &str) -> Value {
+    decimal.parse::<f64>().map(Value::from).unwrap_or_else(|_| Value::from(decimal))
+}
@@ -0,0 +1,266 @@
+use avro_schema::file::{Block, CompressedBlock, Compression};
+use avro_schema::schema::{BytesLogical, Fixed, FixedLogical, IntLogical, LongLogical, Record, Schema};
+use chrono::{DateTime, NaiveDate, NaiveTime};
+use serde_json::{Map, Value};
+use tokio::io::{AsyncBufRead, AsyncReadExt};
+use tokio_util::compat::TokioAsyncReadCompatExt;
+
+use super::typed_values::{hex_value, optional_string};
+
+/// Largest compressed Avro block read, writers flush blocks of a few megabytes at most
+const MAX_BLOCK_LENGTH: i64 = 256 * 1024 * 1024;
+/// Largest number of rows announced by an Avro block
+const MAX_BLOCK_ROWS: i64 = 64 * 1024 * 1024;
+
+/// Records of an Avro object container file, decoded with the schema of its header.
+/// Blocks are read and decompressed one at a time, so only the current block is kept in memory
+pub struct AvroRows<R> {
+    reader: R,
+    record: Record,
+    compression: Option<Compression>,
+    marker: [u8; 16],
+    block: Block,
+    position: usize,
+    rows_left: usize,
+}
+
+impl<R: AsyncBufRead + Unpin + Send> AvroRows<R> {
+    /// Reads the header, failing if it is not the one of an Avro file with a record schema
+    pub async fn open(reader: R) -> anyhow::Result<Self> {
+        let mut compat_reader = reader.compat();
+        let metadata = avro_schema::read_async::read_metadata(&mut compat_reader).await
+            .map_err(|error| anyhow::anyhow!("Invalid Avro file header: {error}"))?;
+
+        Ok(AvroRows {
+            reader: compat_reader.into_inner(),
+            record: metadata.record,
+            compression: metadata.compression,
+            marker: metadata.marker,
+            block: Block::new(0, Vec::new()),
+            position: 0,
+            rows_left: 0,
+        })
+    }
+
+    pub async fn next_row(&mut self) -> anyhow::Result<Option<Value>> {
+        while self.rows_left == 0 {
+            if !self.next_block().await? {
+                return Ok(None);
+            }
+        }
+
+        let mut data = &self.block.data[self.position..];
+        let row = record_value(&self.record, &mut data)?;
+        self.position = self.block.data.len() - data.len();
+        self.rows_left -= 1;
+
+        Ok(Some(row))
+    }
+
+    /// Returns false at the end of the file
+    async fn next_block(&mut self) -> anyhow::Result<bool> {
+        let Some(rows) = read_long(&mut self.reader).await? else {
+            return Ok(false);
+        };
+        let Some(length) = read_long(&mut self.reader).await? else {
+            anyhow::bail!("Unexpected end of Avro block");
+        };
+
+        if !(0..=MAX_BLOCK_ROWS).contains(&rows) {
+            anyhow::bail!("Invalid Avro block of {rows} rows, at most {MAX_BLOCK_ROWS} are accepted");
+        }
+        if !(0..=MAX_BLOCK_LENGTH).contains(&length) {
+            anyhow::bail!("Invalid Avro block of {length} bytes, at most {MAX_BLOCK_LENGTH} are accepted");
+        }
+
+        let mut compressed = CompressedBlock::new(rows as usize, vec![0u8; length as usize]);
+        self.reader.read_exact(&mut compressed.data).await?;
+
+        let mut marker = [0u8; 16];
+        self.reader.read_exact(&mut marker).await?;
+        if marker != self.marker {
+            anyhow::bail!("Invalid sync marker after an Avro block");
+        }
+
+        avro_schema::read_async::decompress_block(&mut compressed, &mut self.block, self.compression)
+            .map_err(|error| anyhow::anyhow!("Could not decompress Avro block: {error}"))?;
+        self.position = 0;
+        self.rows_left = rows as usize;
+
+        Ok(true)
+    }
+}
+
+
+/// Zigzag encoded variable length long, or `None` if the input ends before its first byte
+async fn read_long<R: AsyncBufRead + Unpin>(reader: &mut R) -> anyhow::Result<Option<i64>> {
+    let mut encoded = 0u64;
+    for shift in (0..64).step_by(7) {
+        let mut byte = [0u8; 1];
+        if reader.read(&mut byte).await? == 0 {
+            if shift == 0 {
+                return Ok(None);
+            }
+            anyhow::bail!("Unexpected end of Avro input");
+        }
+
+        encoded |= ((byte[0] & 0x7f) as u64) << shift;
+        if byte[0] & 0x80 == 0 {
+            return Ok(Some(zigzag(encoded)));
+        }
+    }
+    anyhow::bail!("Invalid Avro variable length integer")
+}
+
+fn zigzag(encoded: u64) -> i64 {
+    (encoded >> 1) as i64 ^ -((encoded & 1) as i64)
+}
+
+
+fn record_value(record: &Record, data: &mut &[u8]) -> anyhow::Result<Value> {
+    let mut object = Map::new();
+    for field in record.fields.iter() {
+        object.insert(field.name.to_owned(), datum_value(&field.schema, data)?);
+    }
+    Ok(Value::Object(object))
+}
+
+/// JSON value of a datum, keeping its type: arrays of floats become arrays of numbers that can be read as vectors,
+/// unions become the value of their branch, dates and timestamps become RFC 3339 strings and bytes become hex strings
+fn datum_value(schema: &Schema, data: &mut &[u8]) -> anyhow::Result<Value> {
+    let value = match schema {
+        Schema::Null => Value::Null,
+        Schema::Boolean => Value::from(take(data, 1)?[0] != 0),
+        Schema::Int(Some(IntLogical::Date)) => {
+            let days = decode_long(data)?;
+            optional_string(NaiveDate::from_num_days_from_ce_opt(days as i32 + UNIX_EPOCH_DAYS_FROM_CE))
+        },
+        Schema::Int(Some(IntLogical::Time)) => {
+            let milliseconds = decode_long(data)?;
+            optional_string(NaiveTime::from_num_seconds_from_midnight_opt((milliseconds / 1_000) as u32,
+                (milliseconds % 1_000) as u32 * 1_000_000))
+        },
+        Schema::Int(None) => Value::from(decode_long(data)?),
+        Schema::Long(Some(LongLogical::Time)) => {
+            let microseconds = decode_long(data)?;
+            optional_string(NaiveTime::from_num_seconds_from_midnight_opt((microseconds / 1_000_000) as u32,
+                (microseconds % 1_000_000) as u32 * 1_000))
+        },
+        Schema::Long(Some(logical @ (LongLogical::TimestampMillis | LongLogical::TimestampMicros
+                                     | LongLogical::LocalTimestampMillis | LongLogical::LocalTimestampMicros))) => {
+            let timestamp = decode_long(data)?;
+            let date_time = match logical {
+                LongLogical::TimestampMillis | LongLogical::LocalTimestampMillis => DateTime::from_timestamp_millis(timestamp),
+                _ => DateTime::from_timestamp_micros(timestamp),
+            };
+            // Local timestamps do not designate an instant, so they are written without a time zone
+            let format = match logical {
+                LongLogical::TimestampMillis | LongLogical::TimestampMicros => "%Y-%m-%dT%H:%M:%S%.fZ",
+                _ => "%Y-%m-%dT%H:%M:%S%.f",
+            };
+            optional_string(date_time.map(|date_time| date_time.naive_utc().format(format)))
+        },
+        Schema::Long(None) => Value::from(decode_long(data)?),
+        Schema::Float => Value::from(f32::from_le_bytes(take(data, 4)?.try_into()?) as f64),
+        Schema::Double => Value::from(f64::from_le_bytes(take(data, 8)?.try_into()?)),
+        Schema::Bytes(Some(BytesLogical::Decimal(_, scale))) => {
+            let length = decode_length(data)?;
+            decimal_value(take(data, length)?, *scale)
+        },
+        Schema::Bytes(None) => {
+            let length = decode_length(data)?;
+            hex_value(take(data, length)?)
+        },
+        Schema::String(_) => {
+            let length = decode_length(data)?;
+            Value::from(std::str::from_utf8(take(data, length)?)?)
+        },
+        Schema::Fixed(Fixed { size, logical: Some(FixedLogical::Decimal(_, scale)), .. }) => decimal_value(take(data, *size)?, *scale),
+        Schema::Fixed(Fixed { size, .. }) => hex_value(take(data, *size)?),
+        Schema::Record(record) => record_value(record, data)?,
+        Schema::Enum(enumeration) => {
+            let index = decode_length(data)?;
+            match enumeration.symbols.get(index) {
+                Some(symbol) => Value::from(symbol.as_str()),
+                None => anyhow::bail!("Invalid symbol index {index} of Avro enum {}", enumeration.name),
+            }
+        },
+        Schema::Array(items) => {
+            let mut elements = Vec::new();
+            while let Some(count) = decode_block_count(data)? {
+                for _ in 0..count {
+                    elements.push(datum_value(items, data)?);
+                }
+            }
+            Value::Array(elements)
+        },
+        Schema::Map(values) => {
+            let mut object = Map::new();
+            while let Some(count) = decode_block_count(data)? {
+                for _ in 0..count {
+                    let length = decode_length(data)?;
+                    let key = std::str::from_utf8(take(data, length)?)?.to_owned();
+                    object.insert(key, datum_value(values, data)?);
+                }
+            }
+            Value::Object(object)
+        },
+        Schema::Union(branches) => {
+            let index = decode_length(data)?;
+            match branches.get(index) {
+                Some(branch) => datum_value(branch, data)?,
+                None => anyhow::bail!("Invalid Avro union branch {index}"),
+            }
+        },
+    };
+
+    Ok(value)
+}
+
+const UNIX_EPOCH_DAYS_FROM_CE: i32 = 719_163;
+
+fn take<'a>(data: &mut &'a [u8], length: usize) -> anyhow::Result<&'a [u8]> {
+    if data.len() < length {
+        anyhow::bail!("Unexpected end of Avro block");
+    }
+    let (taken, rest) = data.split_at(length);
+    *data = rest;
+    Ok(taken)
+}
+
+fn decode_long(data: &mut &[u8]) -> anyhow::Result<i64> {
+    let mut encoded = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = take(data, 1)?[0];
+        encoded |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(zigzag(encoded));
+        }
+    }
+    anyhow::bail!("Invalid Avro variable length integer")
+}
+
+fn decode_length(data: &mut &[u8]) -> anyhow::Result<usize> {
+    let length = decode_long(data)?;
+    usize::try_from(length).map_err(|_| anyhow::anyhow!("Invalid Avro length {length}"))
+}
+
+/// Number of items of the next block of an array or map, `None` after the last block.
+/// A negative count is followed by the size in bytes of the block, which is not needed here
+fn decode_block_count(data: &mut &[u8]) -> anyhow::Result<Option<usize>> {
+    match decode_long(data)? {
+        0 => Ok(None),
+        count if count < 0 => {
+            decode_long(data)?;
+            Ok(Some(count.unsigned_abs() as usize))
+        },
+        count => Ok(Some(count as usize)),
+    }
+}
+
+/// Big-endian two's complement unscaled value
+fn decimal_value(bytes: &[u8], scale: usize) -> Value {
+    let unscaled = bytes.iter().fold(if bytes.first().is_some_and(|byte| byte & 0x80 != 0) { -1i128 } else { 0 },
+        |unscaled, byte| (unscaled << 8) | *byte as i128);
+    Value::from(unscaled as f64 / 10f64.powi(scale as i32))
+}
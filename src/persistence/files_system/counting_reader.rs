@@ -0,0 +1,57 @@
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncRead, ReadBuf};
+
+use super::transport::ByteReader;
+
+/// Adds the bytes read through it to a shared [`ReadProgress`]
+pub(super) struct CountingReader {
+    reader: ByteReader,
+    progress: Arc<ReadProgress>,
+}
+
+impl CountingReader {
+    pub(super) fn new(reader: ByteReader, progress: Arc<ReadProgress>) -> Self {
+        CountingReader { reader, progress }
+    }
+}
+
+impl AsyncRead for CountingReader {
+    fn poll_read(mut self: Pin<&mut Self>, context: &mut Context<'_>, buffer: &mut ReadBuf<'_>) -> Poll<std::io::Result<()>> {
+        let filled_before = buffer.filled().len();
+        let result = Pin::new(&mut self.reader).poll_read(context, buffer);
+        if let Poll::Ready(Ok(())) = result {
+            self.progress.add(&buffer.filled()[filled_before..]);
+        }
+        result
+    }
+}
+
+
+/// Number and SHA-256 digest of the bytes read so far
+#[derive(Default)]
+pub(super) struct ReadProgress {
+    bytes_read: AtomicU64,
+    digest: Mutex<Sha256>,
+}
+
+impl ReadProgress {
+    fn add(&self, bytes: &[u8]) {
+        self.bytes_read.fetch_add(bytes.len() as u64, Ordering::Relaxed);
+        self.digest.lock().unwrap().update(bytes);
+    }
+
+    pub(super) fn bytes_read(&self) -> u64 {
+        self.bytes_read.load(Ordering::Relaxed)
+    }
+
+    /// Hex encoded
+    pub(super) fn checksum(&self) -> String {
+        let digest = self.digest.lock().unwrap().clone().finalize();
+        digest.iter().map(|byte| format!("{byte:02x}")).collect()
+    }
+}
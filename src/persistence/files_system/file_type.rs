@@ -1,5 +1,23 @@
 #[derive(clap::ValueEnum, Debug, Clone)]
 pub enum FileType {
+    /// A top-level array if the input starts with `[`, concatenated JSON values otherwise
     JSON,
-    CSV
-}
\ No newline at end of file
+    /// One JSON value per line
+    #[value(name = "jsonl")]
+    JSONL,
+    /// Elements of a single top-level array, streamed without loading the whole array
+    #[value(name = "json-array")]
+    JSONArray,
+    /// Concatenated JSON values, pretty-printed or not
+    #[value(name = "json-stream")]
+    JSONStream,
+    CSV,
+    /// Arrow IPC file, also known as Feather v2
+    #[value(name = "arrow")]
+    ArrowFile,
+    /// Arrow IPC stream
+    #[value(name = "arrow-stream")]
+    ArrowStream,
+    /// Avro object container file
+    Avro
+}
@@ -0,0 +1,165 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use futures::StreamExt;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, ETAG, IF_RANGE, LAST_MODIFIED, LOCATION, RANGE};
+use reqwest::{redirect, StatusCode, Url};
+
+use super::resumable_reader::{resumable_reader, ChunkStream, RangeRequest};
+use super::transport::{ByteReader, Transport};
+
+const DEFAULT_MAX_RETRIES: u32 = 5;
+const DEFAULT_MAX_REDIRECTS: usize = 10;
+
+/// Streams the body of a GET request, for `http://` and `https://` source paths. When the connection drops
+/// the download is resumed with a `Range` request from the last byte read, as long as the file did not change
+pub struct HttpTransport {
+    http_client: reqwest::Client,
+    headers: HeaderMap,
+    max_redirects: usize,
+    max_retries: u32,
+    retries: Arc<AtomicU64>,
+}
+
+impl HttpTransport {
+    /// `headers` are sent with every request to the host of the source path, e.g. `Authorization: Bearer <token>`,
+    /// but not to the hosts it redirects to
+    pub fn new(headers: HeaderMap, max_redirects: usize, max_retries: u32) -> anyhow::Result<Self> {
+        // Redirects are followed by the transport, which decides per request whether the headers are sent
+        let http_client = reqwest::Client::builder()
+            .redirect(redirect::Policy::none())
+            .build()?;
+
+        Ok(HttpTransport { http_client, headers, max_redirects, max_retries, retries: Arc::default() })
+    }
+
+    /// Follows redirects up to the limit and returns the response of the last URL
+    async fn get(&self, source_url: &Url) -> anyhow::Result<reqwest::Response> {
+        let mut url = source_url.clone();
+        let mut redirects = 0;
+        loop {
+            let response = self.http_client.get(url.clone()).headers(origin_headers(&self.headers, source_url, &url)).send().await?;
+            if !is_redirect(response.status()) {
+                return Ok(response.error_for_status()?);
+            }
+            if redirects == self.max_redirects {
+                anyhow::bail!("Too many redirects from {source_url}, at most {max} are followed", max=self.max_redirects);
+            }
+
+            let Some(location) = response.headers().get(LOCATION) else {
+                anyhow::bail!("Redirect from {url} without a Location header");
+            };
+            url = url.join(location.to_str()?)?;
+            redirects += 1;
+        }
+    }
+}
+
+impl Default for HttpTransport {
+    fn default() -> Self {
+        HttpTransport {
+            http_client: reqwest::Client::builder().redirect(redirect::Policy::none()).build().unwrap(),
+            headers: HeaderMap::new(),
+            max_redirects: DEFAULT_MAX_REDIRECTS,
+            max_retries: DEFAULT_MAX_RETRIES,
+            retries: Arc::default(),
+        }
+    }
+}
+
+
+#[async_trait]
+impl Transport for HttpTransport {
+
+    async fn open(&self, source_path: &str) -> anyhow::Result<ByteReader> {
+        let source_url = Url::parse(source_path)?;
+        let response = self.get(&source_url).await?;
+        // If-Range only accepts strong entity tags
+        let validator = response.headers().get(ETAG)
+            .filter(|etag| !etag.as_bytes().starts_with(b"W/"))
+            .or(response.headers().get(LAST_MODIFIED))
+            .cloned();
+
+        tracing::info!("Opening file {filename}", filename=source_path);
+
+        let range_request = HttpRangeRequest {
+            http_client: self.http_client.clone(),
+            url: response.url().to_string(),
+            headers: origin_headers(&self.headers, &source_url, response.url()),
+            validator,
+        };
+
+        Ok(resumable_reader(range_request, body_stream(response), self.max_retries, self.retries.clone()))
+    }
+
+    fn retries(&self) -> u64 {
+        self.retries.load(Ordering::Relaxed)
+    }
+}
+
+
+/// Parses a `Name: value` header given on the command line
+pub fn parse_header(header: &str) -> anyhow::Result<(HeaderName, HeaderValue)> {
+    let Some((name, value)) = header.split_once(':') else {
+        anyhow::bail!("Invalid header {header}, expected Name: value");
+    };
+
+    Ok((HeaderName::from_bytes(name.trim().as_bytes())?, HeaderValue::from_str(value.trim())?))
+}
+
+
+/// Only requests to the origin of the source path get the headers, so that credentials do not leak to another host
+fn origin_headers(headers: &HeaderMap, source_url: &Url, url: &Url) -> HeaderMap {
+    if url.origin() == source_url.origin() {
+        headers.clone()
+    } else {
+        HeaderMap::new()
+    }
+}
+
+fn is_redirect(status: StatusCode) -> bool {
+    matches!(status,
+        StatusCode::MOVED_PERMANENTLY | StatusCode::FOUND | StatusCode::SEE_OTHER |
+        StatusCode::TEMPORARY_REDIRECT | StatusCode::PERMANENT_REDIRECT)
+}
+
+
+struct HttpRangeRequest {
+    http_client: reqwest::Client,
+    url: String,
+    /// The headers of the transport, or none when the file was redirected to another origin
+    headers: HeaderMap,
+    validator: Option<HeaderValue>,
+}
+
+#[async_trait]
+impl RangeRequest for HttpRangeRequest {
+
+    async fn open_from(&self, offset: u64) -> anyhow::Result<ChunkStream> {
+        let mut request = self.http_client.get(&self.url)
+            .headers(self.headers.clone())
+            .header(RANGE, format!("bytes={offset}-"));
+        if let Some(validator) = self.validator.as_ref() {
+            request = request.header(IF_RANGE, validator.clone());
+        }
+
+        let response = request.send().await?.error_for_status()?;
+        if response.status() != StatusCode::PARTIAL_CONTENT {
+            anyhow::bail!("the server did not honour the range request (status {status}), the file may have changed", status=response.status());
+        }
+
+        Ok(body_stream(response))
+    }
+
+    fn location(&self) -> &str {
+        &self.url
+    }
+}
+
+
+fn body_stream(response: reqwest::Response) -> ChunkStream {
+    response.bytes_stream()
+        .map(|chunk| chunk.map_err(std::io::Error::other))
+        .boxed()
+}
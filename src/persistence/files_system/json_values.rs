@@ -0,0 +1,168 @@
+use tokio::io::{AsyncBufRead, AsyncBufReadExt};
+
+/// Reads top-level JSON values one at a time, either concatenated (pretty-printed objects, JSON lines) or as the
+/// elements of a single top-level array. Only the bytes of the current value are kept in memory
+pub struct JsonValues<R> {
+    reader: R,
+    in_array: bool,
+    /// Within an array, whether a comma must come before the next element
+    after_first_element: bool,
+    finished: bool,
+}
+
+impl<R: AsyncBufRead + Unpin + Send> JsonValues<R> {
+    pub fn concatenated(reader: R) -> Self {
+        JsonValues { reader, in_array: false, after_first_element: false, finished: false }
+    }
+
+    /// Fails if the first non-whitespace byte does not open an array
+    pub async fn array(mut reader: R) -> anyhow::Result<Self> {
+        match peek_significant_byte(&mut reader).await? {
+            Some(b'[') => reader.consume(1),
+            _ => anyhow::bail!("The JSON source does not start with an array"),
+        }
+
+        Ok(JsonValues { reader, in_array: true, after_first_element: false, finished: false })
+    }
+
+    pub async fn next_value(&mut self) -> anyhow::Result<Option<serde_json::Value>> {
+        if self.finished || !self.skip_separators().await? {
+            self.finished = true;
+            return Ok(None);
+        }
+
+        let mut value = Vec::new();
+        let mut scanner = ValueScanner::default();
+        loop {
+            let buffer = self.reader.fill_buf().await?;
+            if buffer.is_empty() {
+                if !scanner.is_complete_at_end() {
+                    anyhow::bail!("Unexpected end of JSON input");
+                }
+                break;
+            }
+
+            match scanner.scan(buffer) {
+                Some(end) => {
+                    value.extend_from_slice(&buffer[..end]);
+                    self.reader.consume(end);
+                    break;
+                },
+                None => {
+                    let length = buffer.len();
+                    value.extend_from_slice(buffer);
+                    self.reader.consume(length);
+                }
+            }
+        }
+
+        Ok(Some(serde_json::from_slice(&value)?))
+    }
+
+    /// Skips whitespace, and the single comma between array elements. Returns false when there are no more values
+    async fn skip_separators(&mut self) -> anyhow::Result<bool> {
+        if !self.in_array {
+            return Ok(peek_significant_byte(&mut self.reader).await?.is_some());
+        }
+
+        match peek_significant_byte(&mut self.reader).await? {
+            Some(b']') => {
+                self.reader.consume(1);
+                return Ok(false);
+            },
+            Some(b',') if self.after_first_element => {
+                self.reader.consume(1);
+                match peek_significant_byte(&mut self.reader).await? {
+                    Some(byte @ (b',' | b']')) => anyhow::bail!("Expected a JSON array element after a comma, found '{}'", byte as char),
+                    None => anyhow::bail!("Unexpected end of JSON array"),
+                    Some(_) => {},
+                }
+            },
+            Some(byte) if self.after_first_element => {
+                anyhow::bail!("Expected a comma between JSON array elements, found '{}'", byte as char)
+            },
+            Some(b',') => anyhow::bail!("Unexpected comma before the first JSON array element"),
+            Some(_) => {},
+            None => anyhow::bail!("Unexpected end of JSON array"),
+        }
+
+        self.after_first_element = true;
+        Ok(true)
+    }
+}
+
+
+/// Consumes whitespace and returns the next byte without consuming it, `None` at the end of the input
+pub async fn peek_significant_byte<R: AsyncBufRead + Unpin>(reader: &mut R) -> anyhow::Result<Option<u8>> {
+    loop {
+        let buffer = reader.fill_buf().await?;
+        if buffer.is_empty() {
+            return Ok(None);
+        }
+
+        match buffer.iter().position(|byte| !byte.is_ascii_whitespace()) {
+            Some(position) => {
+                let byte = buffer[position];
+                reader.consume(position);
+                return Ok(Some(byte));
+            },
+            None => {
+                let length = buffer.len();
+                reader.consume(length);
+            }
+        }
+    }
+}
+
+
+/// Finds where a JSON value ends, tracking nesting and strings across buffers
+#[derive(Default)]
+struct ValueScanner {
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+}
+
+impl ValueScanner {
+    /// Position right after the value, if it ends in this buffer
+    fn scan(&mut self, buffer: &[u8]) -> Option<usize> {
+        for (position, byte) in buffer.iter().enumerate() {
+            if self.in_string {
+                if self.escaped {
+                    self.escaped = false;
+                } else if *byte == b'\\' {
+                    self.escaped = true;
+                } else if *byte == b'"' {
+                    self.in_string = false;
+                    if self.depth == 0 {
+                        return Some(position + 1);
+                    }
+                }
+                continue;
+            }
+
+            match byte {
+                b'"' => self.in_string = true,
+                b'{' | b'[' => self.depth += 1,
+                // A closing bracket at depth 0 ends a scalar array element
+                b'}' | b']' if self.depth == 0 => return Some(position),
+                b'}' | b']' => {
+                    self.depth -= 1;
+                    if self.depth == 0 {
+                        return Some(position + 1);
+                    }
+                },
+                b',' if self.depth == 0 => return Some(position),
+                byte if self.depth == 0 && byte.is_ascii_whitespace() => return Some(position),
+                _ => {}
+            }
+        }
+
+        None
+    }
+
+    /// Only a top-level number or literal may end with the input
+    fn is_complete_at_end(&self) -> bool {
+        self.depth == 0 && !self.in_string
+    }
+}
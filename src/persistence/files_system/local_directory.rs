@@ -0,0 +1,64 @@
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use async_trait::async_trait;
+
+use super::object_listing::{ListedObject, ObjectListing};
+
+/// Regular files of a local directory, given as a plain path or a `file://` URL. The ETag of a file is made
+/// of its modification time and size
+pub struct LocalDirectory {
+    directory: PathBuf,
+}
+
+impl LocalDirectory {
+    pub fn new(directory: &str) -> Self {
+        let directory = directory.strip_prefix("file://").unwrap_or(directory);
+        LocalDirectory { directory: PathBuf::from(directory) }
+    }
+}
+
+#[async_trait]
+impl ObjectListing for LocalDirectory {
+
+    async fn list(&self) -> anyhow::Result<Vec<ListedObject>> {
+        let mut objects = Vec::new();
+        let mut entries = tokio::fs::read_dir(&self.directory).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let metadata = entry.metadata().await?;
+            if !metadata.is_file() {
+                continue;
+            }
+
+            let modified = metadata.modified()?.duration_since(UNIX_EPOCH)?.as_nanos();
+            objects.push(ListedObject {
+                path: entry.path().to_string_lossy().into_owned(),
+                etag: format!("{modified}-{length}", length=metadata.len()),
+            });
+        }
+
+        objects.sort_by(|first, second| first.path.cmp(&second.path));
+        Ok(objects)
+    }
+
+    async fn move_object(&self, object: &ListedObject, destination: &str) -> anyhow::Result<()> {
+        let destination = Path::new(destination.strip_prefix("file://").unwrap_or(destination));
+        let source = Path::new(&object.path);
+        let Some(file_name) = source.file_name() else {
+            anyhow::bail!("Invalid file path {path}", path=object.path);
+        };
+
+        tokio::fs::create_dir_all(destination).await?;
+        tokio::fs::rename(source, destination.join(file_name)).await?;
+        Ok(())
+    }
+
+    async fn tag_object(&self, _object: &ListedObject, _tags: &[(String, String)]) -> anyhow::Result<()> {
+        anyhow::bail!("Local files can not be tagged")
+    }
+
+    /// Files are written in place, unlike objects that only appear once fully uploaded
+    fn lists_partial_objects(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,22 @@
+use std::path::Path;
+
+use async_trait::async_trait;
+use tokio::fs::File;
+
+use super::transport::{ByteReader, Transport};
+
+/// Reads `file://` URLs and plain paths from the local file system
+pub struct LocalTransport;
+
+#[async_trait]
+impl Transport for LocalTransport {
+
+    async fn open(&self, source_path: &str) -> anyhow::Result<ByteReader> {
+        let file_path = source_path.strip_prefix("file://").unwrap_or(source_path);
+        let file = File::open(Path::new(file_path)).await?;
+
+        tracing::info!("Opening file {filename}", filename=file_path);
+
+        Ok(Box::new(file))
+    }
+}
@@ -1,45 +1,150 @@
-use self::{local_dataset::LocalDataset, s3_dataset::S3Dataset};
-
-mod dataset_ext;
+mod arrow_rows;
+mod avro_rows;
+mod counting_reader;
 mod file_type;
-mod local_dataset;
-mod s3_dataset;
+mod http_transport;
+mod json_values;
+mod local_directory;
+mod local_transport;
+mod object_listing;
+mod paired_dataset;
+mod resumable_reader;
+mod s3_prefix;
+mod s3_transport;
+mod stdin_transport;
+mod transport;
+mod transport_registry;
+mod typed_values;
+mod vector_matrix;
+
+use std::sync::Arc;
 
+use arrow_rows::ArrowRows;
 use async_trait::async_trait;
+use avro_rows::AvroRows;
+use counting_reader::{CountingReader, ReadProgress};
+use tokio::io::{AsyncBufReadExt, BufReader, Lines};
+use json_values::{peek_significant_byte, JsonValues};
+use tokio::sync::Mutex;
+
 pub use file_type::FileType;
-pub use dataset_ext::DatasetExt;
+pub use http_transport::{parse_header, HttpTransport};
+pub use local_directory::LocalDirectory;
+pub use local_transport::LocalTransport;
+pub use object_listing::{ListedObject, ObjectListing};
+pub use paired_dataset::PairedDataset;
+pub use s3_prefix::S3Prefix;
+pub use s3_transport::{S3Options, S3Transport};
+pub use stdin_transport::StdinTransport;
+pub use transport::{ByteReader, Transport};
+pub use transport_registry::TransportRegistry;
+pub use vector_matrix::VectorFileType;
 
+use crate::record_source::{RecordSource, SourceStatistics};
+
+/// Rows parsed from the bytes of any [`Transport`], as JSON, CSV with a header line, Arrow IPC or Avro
+pub struct Dataset {
+    records: Mutex<Records>,
+    read_progress: Arc<ReadProgress>,
+    /// Transport the dataset was loaded with and its retry count at that time
+    transport: Option<(Arc<dyn Transport>, u64)>,
+}
 
-pub enum Dataset {
-    S3(S3Dataset),
-    Local(LocalDataset),
+enum Records {
+    JsonLines(Lines<BufReader<ByteReader>>),
+    JsonValues(JsonValues<BufReader<ByteReader>>),
+    Csv {
+        lines: Lines<BufReader<ByteReader>>,
+        header: Option<String>,
+    },
+    Arrow(ArrowRows<BufReader<ByteReader>>),
+    Avro(AvroRows<BufReader<ByteReader>>),
 }
 
 impl Dataset {
-    pub async fn load(source_path: &str, file_type: &FileType, access_key: Option<String>,
-        secret_key: Option<String>, region: Option<String>, endpoint: Option<String>) -> anyhow::Result<Dataset> {
-        let dataset = if source_path.starts_with("s3://") {
-            let dataset = S3Dataset::new(source_path, file_type, access_key,
-                secret_key, region, endpoint).await?;
-            Dataset::S3(dataset)
-        } else {
-            let dataset = LocalDataset::new(source_path, file_type).await?;
-            Dataset::Local(dataset)
-        };
+    /// Opens the source path with the transport registered for its scheme
+    pub async fn load(source_path: &str, file_type: &FileType, transports: &TransportRegistry) -> anyhow::Result<Dataset> {
+        let transport = transports.transport(source_path)?;
+        let retries = transport.retries();
+        let reader = transport.open(source_path).await?;
 
+        let mut dataset = Dataset::from_reader(reader, file_type).await?;
+        dataset.transport = Some((transport, retries));
         Ok(dataset)
     }
+
+    pub async fn from_reader(reader: ByteReader, file_type: &FileType) -> anyhow::Result<Dataset> {
+        let read_progress = Arc::new(ReadProgress::default());
+        let mut reader = BufReader::new(Box::new(CountingReader::new(reader, read_progress.clone())) as ByteReader);
+        let records = match file_type {
+            FileType::JSON => {
+                if peek_significant_byte(&mut reader).await? == Some(b'[') {
+                    Records::JsonValues(JsonValues::array(reader).await?)
+                } else {
+                    Records::JsonValues(JsonValues::concatenated(reader))
+                }
+            },
+            FileType::JSONL => Records::JsonLines(reader.lines()),
+            FileType::JSONArray => Records::JsonValues(JsonValues::array(reader).await?),
+            FileType::JSONStream => Records::JsonValues(JsonValues::concatenated(reader)),
+            FileType::CSV => {
+                let mut lines = reader.lines();
+                let header = lines.next_line().await?;
+                Records::Csv { lines, header }
+            },
+            FileType::ArrowFile => Records::Arrow(ArrowRows::file(reader).await?),
+            FileType::ArrowStream => Records::Arrow(ArrowRows::stream(reader)),
+            FileType::Avro => Records::Avro(AvroRows::open(reader).await?),
+        };
+
+        Ok(Dataset { records: Mutex::new(records), read_progress, transport: None })
+    }
 }
 
 
 #[async_trait]
-impl DatasetExt for Dataset {
-    type DatasetType = Dataset;
+impl RecordSource for Dataset {
+
+    async fn next_record(&self) -> anyhow::Result<Option<serde_json::Value>> {
+        let mut records = self.records.lock().await;
 
-    async fn next_line(&self) -> anyhow::Result<Option<serde_json::Value>> {
-        match self {
-            Dataset::S3(dataset) => dataset.next_line().await,
-            Dataset::Local(dataset) => dataset.next_line().await
+        match &mut *records {
+            Records::JsonLines(lines) => {
+                match lines.next_line().await? {
+                    Some(current_line) => Ok(Some(serde_json::from_str(&current_line)?)),
+                    None => Ok(None)
+                }
+            },
+            Records::JsonValues(values) => values.next_value().await,
+            Records::Csv { lines, header } => {
+                match lines.next_line().await? {
+                    Some(current_line) => {
+                        let csv_line_with_header = header.clone().unwrap_or_default() + "\n" + &current_line;
+                        let mut csv_reader = csv::Reader::from_reader(csv_line_with_header.as_bytes());
+                        let mut csv_iter = csv_reader.deserialize();
+
+                        // Deserialized as a map, so that every column is keyed by its header
+                        let row: serde_json::Map<String, serde_json::Value> = csv_iter.next().unwrap()?;
+                        Ok(Some(serde_json::Value::Object(row)))
+                    },
+                    None => Ok(None)
+                }
+            },
+            Records::Arrow(rows) => rows.next_row().await,
+            Records::Avro(rows) => rows.next_row().await,
         }
     }
+
+    /// A row that can not be parsed fails the run instead of being skipped, so no row is ever rejected
+    fn statistics(&self) -> SourceStatistics {
+        let retries = self.transport.as_ref()
+            .map(|(transport, retries_when_opened)| transport.retries() - retries_when_opened)
+            .unwrap_or_default();
+
+        SourceStatistics { bytes_read: self.read_progress.bytes_read(), rows_rejected: 0, retries }
+    }
+
+    fn checksum(&self) -> Option<String> {
+        Some(self.read_progress.checksum())
+    }
 }
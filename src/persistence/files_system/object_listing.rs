@@ -0,0 +1,30 @@
+use async_trait::async_trait;
+
+/// An object found in a watched location. Its ETag changes whenever its content does, so that a new version
+/// of the same path is told apart from the one already processed
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ListedObject {
+    /// Source path of the object, to be opened with a [`super::TransportRegistry`]
+    pub path: String,
+    pub etag: String,
+}
+
+/// A directory or prefix whose objects are listed and, once processed, moved or tagged
+#[async_trait]
+pub trait ObjectListing: Send + Sync {
+
+    /// Objects directly under the watched location, sorted by path
+    async fn list(&self) -> anyhow::Result<Vec<ListedObject>>;
+
+    /// Moves an object into the destination, a directory or prefix of the same storage
+    async fn move_object(&self, object: &ListedObject, destination: &str) -> anyhow::Result<()>;
+
+    /// Sets tags on an object, for storages that support it
+    async fn tag_object(&self, object: &ListedObject, tags: &[(String, String)]) -> anyhow::Result<()>;
+
+    /// Whether a listed object may still be being written, in which case it is only processed once two
+    /// consecutive listings give it the same ETag
+    fn lists_partial_objects(&self) -> bool {
+        false
+    }
+}
@@ -0,0 +1,87 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use tokio::sync::Mutex;
+
+use crate::record_source::{RecordSource, SourceStatistics};
+
+use super::counting_reader::{CountingReader, ReadProgress};
+use super::vector_matrix::{VectorFileType, VectorRows};
+use super::{Dataset, FileType, TransportRegistry};
+
+/// Rows of a metadata file, with ids and payload, each completed with the vector at the same position in a binary matrix file
+pub struct PairedDataset {
+    metadata: Dataset,
+    vectors: Mutex<PairedVectors>,
+    vector_field: String,
+    vector_progress: Arc<ReadProgress>,
+}
+
+struct PairedVectors {
+    rows: VectorRows,
+    rows_read: u64,
+}
+
+impl PairedDataset {
+    pub async fn load(metadata_path: &str, file_type: &FileType, vectors_path: &str, vector_file_type: Option<VectorFileType>,
+                      npz_array: &str, vector_field: &str, transports: &TransportRegistry) -> anyhow::Result<PairedDataset> {
+        let vector_file_type = match vector_file_type {
+            Some(vector_file_type) => vector_file_type,
+            None => VectorFileType::detect(vectors_path)?,
+        };
+
+        let metadata = Dataset::load(metadata_path, file_type, transports).await?;
+        let vector_progress = Arc::new(ReadProgress::default());
+        let vector_reader = CountingReader::new(transports.open(vectors_path).await?, vector_progress.clone());
+        let rows = VectorRows::open(Box::new(vector_reader), vector_file_type, npz_array).await?;
+
+        let dataset = PairedDataset {
+            metadata,
+            vectors: Mutex::new(PairedVectors { rows, rows_read: 0 }),
+            vector_field: vector_field.to_owned(),
+            vector_progress,
+        };
+
+        Ok(dataset)
+    }
+}
+
+
+#[async_trait]
+impl RecordSource for PairedDataset {
+
+    async fn next_record(&self) -> anyhow::Result<Option<serde_json::Value>> {
+        let mut vectors = self.vectors.lock().await;
+        let row = self.metadata.next_record().await?;
+        let vector = vectors.rows.next_vector().await?;
+
+        match (row, vector) {
+            (Some(mut row), Some(vector)) => {
+                vectors.rows_read += 1;
+                let Some(fields) = row.as_object_mut() else {
+                    anyhow::bail!("Metadata row {position} is not an object", position=vectors.rows_read);
+                };
+                fields.insert(self.vector_field.clone(), serde_json::Value::from(vector));
+                Ok(Some(row))
+            },
+            (None, None) => Ok(None),
+            (Some(_), None) => anyhow::bail!("The vector file has fewer rows than the metadata file ({rows})", rows=vectors.rows_read),
+            (None, Some(_)) => anyhow::bail!("The metadata file has fewer rows than the vector file ({rows})", rows=vectors.rows_read),
+        }
+    }
+
+    /// Retries are the ones of the metadata file only
+    fn statistics(&self) -> SourceStatistics {
+        let metadata_statistics = self.metadata.statistics();
+        SourceStatistics {
+            bytes_read: metadata_statistics.bytes_read + self.vector_progress.bytes_read(),
+            ..metadata_statistics
+        }
+    }
+
+    /// Checksum of the metadata file followed by the one of the vector file
+    fn checksum(&self) -> Option<String> {
+        let metadata_checksum = self.metadata.checksum()?;
+        Some(format!("{metadata_checksum}+{vector_checksum}", vector_checksum=self.vector_progress.checksum()))
+    }
+}
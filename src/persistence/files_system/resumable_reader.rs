@@ -0,0 +1,104 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::stream::{self, BoxStream};
+use futures::StreamExt;
+use tokio_util::io::StreamReader;
+
+use super::transport::ByteReader;
+
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+
+pub(super) type ChunkStream = BoxStream<'static, std::io::Result<Bytes>>;
+
+/// Reopens a remote file from a byte offset
+#[async_trait]
+pub(super) trait RangeRequest: Send + Sync + 'static {
+
+    /// Fails if the file changed since it was first opened
+    async fn open_from(&self, offset: u64) -> anyhow::Result<ChunkStream>;
+
+    fn location(&self) -> &str;
+}
+
+/// Reads `body` and, when it fails, continues with a range request from the last byte read.
+/// Gives up after `max_retries` consecutive failures, waiting twice as long before each new attempt.
+/// Every attempt is added to `total_retries`
+pub(super) fn resumable_reader(range_request: impl RangeRequest, body: ChunkStream, max_retries: u32, total_retries: Arc<AtomicU64>) -> ByteReader {
+    let download = Download {
+        range_request,
+        body: Some(body),
+        bytes_read: 0,
+        retries: 0,
+        max_retries,
+        total_retries,
+        finished: false,
+    };
+
+    let stream = stream::unfold(download, |mut download| async move {
+        download.next_chunk().await.map(|chunk| (chunk, download))
+    });
+
+    Box::new(StreamReader::new(Box::pin(stream)))
+}
+
+
+struct Download<R: RangeRequest> {
+    range_request: R,
+    body: Option<ChunkStream>,
+    bytes_read: u64,
+    retries: u32,
+    max_retries: u32,
+    total_retries: Arc<AtomicU64>,
+    finished: bool,
+}
+
+impl<R: RangeRequest> Download<R> {
+    async fn next_chunk(&mut self) -> Option<std::io::Result<Bytes>> {
+        if self.finished {
+            return None;
+        }
+
+        loop {
+            let error = match self.body.as_mut() {
+                Some(body) => match body.next().await {
+                    Some(Ok(chunk)) => {
+                        self.bytes_read += chunk.len() as u64;
+                        self.retries = 0;
+                        return Some(Ok(chunk));
+                    },
+                    Some(Err(error)) => anyhow::Error::from(error),
+                    None => {
+                        self.finished = true;
+                        return None;
+                    }
+                },
+                None => match self.range_request.open_from(self.bytes_read).await {
+                    Ok(body) => {
+                        self.body = Some(body);
+                        continue;
+                    },
+                    Err(error) => error
+                }
+            };
+
+            self.body = None;
+            let location = self.range_request.location();
+            if self.retries >= self.max_retries {
+                self.finished = true;
+                return Some(Err(std::io::Error::other(format!("Download of {location} failed after {retries} retries: {error}",
+                    retries=self.retries))));
+            }
+
+            self.retries += 1;
+            self.total_retries.fetch_add(1, Ordering::Relaxed);
+            let delay = RETRY_BASE_DELAY * 2u32.pow(self.retries - 1);
+            tracing::warn!("Download of {location} interrupted at byte {bytes_read}: {error}. Resuming in {delay:?} (retry {retry}/{max_retries})",
+                bytes_read=self.bytes_read, retry=self.retries, max_retries=self.max_retries);
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
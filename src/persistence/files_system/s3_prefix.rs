@@ -0,0 +1,127 @@
+use async_trait::async_trait;
+use aws_sdk_s3::model::{Tag, Tagging};
+
+use super::object_listing::{ListedObject, ObjectListing};
+use super::s3_transport::{make_s3_config, split_bucket_and_key, S3Options};
+
+/// Objects directly under an `s3://bucket/prefix/` location, or a `gs://` one through the Cloud Storage XML API.
+/// Processed objects are moved with a copy followed by a delete
+pub struct S3Prefix {
+    s3_client: aws_sdk_s3::Client,
+    scheme: String,
+    bucket: String,
+    prefix: String,
+}
+
+impl S3Prefix {
+    pub async fn new(location: &str, options: S3Options) -> anyhow::Result<Self> {
+        let Some((scheme, _)) = location.split_once("://") else {
+            anyhow::bail!("Invalid S3 location {location}");
+        };
+        let (bucket, mut prefix) = split_bucket_and_key(location)?;
+        if !prefix.is_empty() && !prefix.ends_with('/') {
+            prefix.push('/');
+        }
+        let s3_client = aws_sdk_s3::Client::from_conf(make_s3_config(options).await?);
+
+        Ok(S3Prefix { s3_client, scheme: scheme.to_owned(), bucket, prefix })
+    }
+
+    fn key_of<'a>(&self, object: &'a ListedObject) -> anyhow::Result<&'a str> {
+        let bucket_prefix = format!("{scheme}://{bucket}/", scheme=self.scheme, bucket=self.bucket);
+        match object.path.strip_prefix(&bucket_prefix) {
+            Some(key) => Ok(key),
+            None => anyhow::bail!("{path} is not an object of bucket {bucket}", path=object.path, bucket=self.bucket),
+        }
+    }
+}
+
+#[async_trait]
+impl ObjectListing for S3Prefix {
+
+    async fn list(&self) -> anyhow::Result<Vec<ListedObject>> {
+        let mut objects = Vec::new();
+        let mut continuation_token = None;
+        loop {
+            let page = self.s3_client
+                .list_objects_v2()
+                .bucket(&self.bucket)
+                .prefix(&self.prefix)
+                .delimiter("/")
+                .set_continuation_token(continuation_token)
+                .send()
+                .await?;
+
+            for object in page.contents().unwrap_or_default() {
+                let (Some(key), Some(etag)) = (object.key(), object.e_tag()) else {
+                    continue;
+                };
+                objects.push(ListedObject {
+                    path: format!("{scheme}://{bucket}/{key}", scheme=self.scheme, bucket=self.bucket),
+                    etag: etag.to_owned(),
+                });
+            }
+
+            continuation_token = page.next_continuation_token().map(str::to_owned);
+            if continuation_token.is_none() {
+                break;
+            }
+        }
+
+        objects.sort_by(|first, second| first.path.cmp(&second.path));
+        Ok(objects)
+    }
+
+    async fn move_object(&self, object: &ListedObject, destination: &str) -> anyhow::Result<()> {
+        let key = self.key_of(object)?;
+        let (destination_bucket, destination_prefix) = split_bucket_and_key(destination)?;
+        let file_name = key.rsplit('/').next().unwrap_or(key);
+        let destination_key = format!("{prefix}{separator}{file_name}", prefix=destination_prefix,
+            separator=if destination_prefix.is_empty() || destination_prefix.ends_with('/') { "" } else { "/" });
+
+        self.s3_client
+            .copy_object()
+            .copy_source(format!("{bucket}/{key}", bucket=self.bucket, key=encode_key(key)))
+            .copy_source_if_match(&object.etag)
+            .bucket(destination_bucket)
+            .key(destination_key)
+            .send()
+            .await?;
+
+        self.s3_client
+            .delete_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await?;
+
+        Ok(())
+    }
+
+    async fn tag_object(&self, object: &ListedObject, tags: &[(String, String)]) -> anyhow::Result<()> {
+        let tag_set = tags.iter()
+            .map(|(key, value)| Tag::builder().key(key).value(value).build())
+            .collect();
+
+        self.s3_client
+            .put_object_tagging()
+            .bucket(&self.bucket)
+            .key(self.key_of(object)?)
+            .tagging(Tagging::builder().set_tag_set(Some(tag_set)).build())
+            .send()
+            .await?;
+
+        Ok(())
+    }
+}
+
+
+/// Percent-encodes a key for the `x-amz-copy-source` header, keeping its slashes
+fn encode_key(key: &str) -> String {
+    key.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => (byte as char).to_string(),
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
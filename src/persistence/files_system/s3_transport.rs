@@ -0,0 +1,214 @@
+use std::borrow::Cow;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use aws_config::default_provider::credentials::DefaultCredentialsChain;
+use aws_config::default_provider::region::DefaultRegionChain;
+use aws_config::sts::AssumeRoleProvider;
+use aws_credential_types::provider::SharedCredentialsProvider;
+use aws_sdk_s3::types::ByteStream;
+use aws_sdk_s3::{Credentials, Region};
+use futures::StreamExt;
+use url::Url;
+
+use super::resumable_reader::{resumable_reader, ChunkStream, RangeRequest};
+use super::transport::{ByteReader, Transport};
+
+/// Region signing S3 requests when none is given nor configured, accepted by MinIO and most S3-compatible storages
+const FALLBACK_REGION: &str = "us-east-1";
+
+/// Reads objects from S3 or any S3-compatible storage, the bucket being the host of the source path URL.
+/// Registered for `s3://` and, pointed at the Cloud Storage XML API with HMAC keys, for `gs://`.
+/// If the stream fails, reading continues with a ranged GET pinned to the version or ETag of the object first read
+pub struct S3Transport {
+    s3_client: aws_sdk_s3::Client,
+    max_retries: u32,
+    retries: Arc<AtomicU64>,
+}
+
+impl S3Transport {
+    pub async fn new(options: S3Options) -> anyhow::Result<Self> {
+        let max_retries = options.max_retries;
+        let s3_config = make_s3_config(options).await?;
+        let s3_client = aws_sdk_s3::Client::from_conf(s3_config);
+        Ok(S3Transport { s3_client, max_retries, retries: Arc::default() })
+    }
+}
+
+
+/// Connection settings of an [`S3Transport`]. Without an access key and a secret key, credentials are
+/// resolved with the default AWS provider chain (environment, profile, web identity, SSO, IAM role)
+#[derive(Debug, Clone, Default)]
+pub struct S3Options {
+    pub access_key: Option<String>,
+    pub secret_key: Option<String>,
+    pub session_token: Option<String>,
+    /// Profile of the shared AWS config and credentials files used by the default provider chain
+    pub profile: Option<String>,
+    /// Role assumed with STS on top of the resolved credentials
+    pub role_arn: Option<String>,
+    pub role_session_name: Option<String>,
+    pub external_id: Option<String>,
+    /// Overrides the region of the environment or profile, used by STS and S3
+    pub region: Option<String>,
+    pub endpoint: Option<String>,
+    /// Address buckets as `bucket.endpoint` rather than `endpoint/bucket`
+    pub virtual_hosted_style: bool,
+    pub max_retries: u32,
+}
+
+
+#[async_trait]
+impl Transport for S3Transport {
+
+    async fn open(&self, source_path: &str) -> anyhow::Result<ByteReader> {
+        let (bucket, key) = split_bucket_and_key(source_path)?;
+        let object = self.s3_client
+            .get_object()
+            .bucket(&bucket)
+            .key(&key)
+            .send()
+            .await?;
+
+        tracing::info!("Opening file {source_path}");
+
+        let range_request = S3RangeRequest {
+            s3_client: self.s3_client.clone(),
+            source_path: source_path.to_owned(),
+            bucket,
+            key,
+            version_id: object.version_id().map(str::to_owned),
+            e_tag: object.e_tag().map(str::to_owned),
+        };
+
+        Ok(resumable_reader(range_request, body_stream(object.body), self.max_retries, self.retries.clone()))
+    }
+
+    fn retries(&self) -> u64 {
+        self.retries.load(Ordering::Relaxed)
+    }
+}
+
+
+struct S3RangeRequest {
+    s3_client: aws_sdk_s3::Client,
+    source_path: String,
+    bucket: String,
+    key: String,
+    version_id: Option<String>,
+    e_tag: Option<String>,
+}
+
+#[async_trait]
+impl RangeRequest for S3RangeRequest {
+
+    async fn open_from(&self, offset: u64) -> anyhow::Result<ChunkStream> {
+        let mut request = self.s3_client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(&self.key)
+            .range(format!("bytes={offset}-"));
+
+        // A version id always designates the same content, on unversioned buckets the ETag must still match
+        if let Some(version_id) = self.version_id.as_ref() {
+            request = request.version_id(version_id);
+        } else if let Some(e_tag) = self.e_tag.as_ref() {
+            request = request.if_match(e_tag);
+        }
+
+        let object = request.send().await?;
+        Ok(body_stream(object.body))
+    }
+
+    fn location(&self) -> &str {
+        &self.source_path
+    }
+}
+
+
+fn body_stream(body: ByteStream) -> ChunkStream {
+    StreamExt::map(body, |chunk| chunk.map_err(std::io::Error::from)).boxed()
+}
+
+
+pub(super) async fn make_s3_config(options: S3Options) -> anyhow::Result<aws_sdk_s3::Config> {
+    let given_region = options.region.map(|region_name| Region::new(Cow::Owned(region_name)));
+    let credential_provider = make_credentials_provider(&options.access_key, &options.secret_key, &options.session_token,
+        &options.profile, &given_region).await?;
+
+    let region = match given_region {
+        Some(region) => Some(region),
+        None => resolve_region(&options.profile).await
+    };
+
+    let credential_provider = match options.role_arn {
+        Some(role_arn) => {
+            let mut assume_role_builder = AssumeRoleProvider::builder(role_arn)
+                .session_name(options.role_session_name.unwrap_or("qdrant-uploader".to_owned()));
+            if let Some(external_id) = options.external_id {
+                assume_role_builder = assume_role_builder.external_id(external_id);
+            }
+            if let Some(region) = region.clone() {
+                assume_role_builder = assume_role_builder.region(region);
+            }
+            SharedCredentialsProvider::new(assume_role_builder.build(credential_provider))
+        },
+        None => credential_provider
+    };
+    
+    let region = region.unwrap_or(Region::from_static(FALLBACK_REGION));
+    let mut s3_config_builder = aws_sdk_s3::Config::builder().region(region);
+    
+    s3_config_builder.set_force_path_style(Some(!options.virtual_hosted_style));
+    s3_config_builder.set_endpoint_url(options.endpoint);
+    s3_config_builder.set_credentials_provider(Some(credential_provider));
+
+    let s3_config = s3_config_builder.build();
+
+    Ok(s3_config)
+}
+
+async fn make_credentials_provider(access_key: &Option<String>, secret_key: &Option<String>, session_token: &Option<String>,
+                                   profile: &Option<String>, region: &Option<Region>) -> anyhow::Result<SharedCredentialsProvider> {
+    match (access_key, secret_key) {
+        (Some(_), Some(_)) if profile.is_some() => anyhow::bail!("An S3 profile can not be used together with an access key and a secret key"),
+        (Some(access_key), Some(secret_key)) => {
+            let credentials = Credentials::new(access_key, secret_key, session_token.clone(), None, "InternalProvider");
+            Ok(SharedCredentialsProvider::new(credentials))
+        },
+        (None, None) => {
+            let mut chain_builder = DefaultCredentialsChain::builder();
+            if let Some(profile) = profile {
+                chain_builder = chain_builder.profile_name(profile);
+            }
+            if let Some(region) = region.clone() {
+                chain_builder = chain_builder.region(region);
+            }
+            Ok(SharedCredentialsProvider::new(chain_builder.build().await))
+        },
+        _ => anyhow::bail!("The S3 access key and secret key must be provided together")
+    }
+}
+
+
+/// Region of the environment or of the profile, looked up the way the default credentials chain does
+async fn resolve_region(profile: &Option<String>) -> Option<Region> {
+    let mut region_builder = DefaultRegionChain::builder();
+    if let Some(profile) = profile {
+        region_builder = region_builder.profile_name(profile);
+    }
+    region_builder.build().region().await
+}
+
+
+pub(super) fn split_bucket_and_key(source_path: &str) -> anyhow::Result<(String, String)> {
+    let url = Url::parse(source_path)?;
+    if let Some(bucket) = url.host_str() {
+        let key = url.path().strip_prefix("/").unwrap_or("");
+        Ok((bucket.to_owned(), key.to_owned()))
+    } else {
+        anyhow::bail!("Invalid source-path: {source_path}")
+    }
+    
+}
@@ -0,0 +1,15 @@
+use async_trait::async_trait;
+
+use super::transport::{ByteReader, Transport};
+
+/// Reads the standard input of the process, for the `-` and `stdin://` source paths
+pub struct StdinTransport;
+
+#[async_trait]
+impl Transport for StdinTransport {
+
+    async fn open(&self, _source_path: &str) -> anyhow::Result<ByteReader> {
+        tracing::info!("Reading standard input");
+        Ok(Box::new(tokio::io::stdin()))
+    }
+}
@@ -0,0 +1,17 @@
+use async_trait::async_trait;
+use tokio::io::AsyncRead;
+
+/// Raw bytes of a source file, parsed into rows by [`super::Dataset`]
+pub type ByteReader = Box<dyn AsyncRead + Send + Unpin>;
+
+/// Opens the byte stream behind a source path, independently of its file type
+#[async_trait]
+pub trait Transport: Send + Sync {
+
+    async fn open(&self, source_path: &str) -> anyhow::Result<ByteReader>;
+
+    /// Interrupted downloads resumed by this transport since it was created
+    fn retries(&self) -> u64 {
+        0
+    }
+}
@@ -0,0 +1,67 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::transport::{ByteReader, Transport};
+use super::{HttpTransport, LocalTransport, StdinTransport};
+
+const DEFAULT_SCHEME: &str = "file";
+const STDIN_SCHEME: &str = "stdin";
+const STDIN_PATH: &str = "-";
+
+/// Transports keyed by the URL scheme of the source path. Paths without a scheme are read with the `file` transport,
+/// except `-` which is read with the `stdin` one
+#[derive(Clone)]
+pub struct TransportRegistry {
+    transports: HashMap<String, Arc<dyn Transport>>,
+}
+
+impl TransportRegistry {
+    pub fn empty() -> Self {
+        TransportRegistry { transports: HashMap::new() }
+    }
+
+    /// Registers a transport for a scheme, replacing the previous one
+    pub fn register(&mut self, scheme: &str, transport: impl Transport + 'static) -> &mut Self {
+        self.transports.insert(scheme.to_lowercase(), Arc::new(transport));
+        self
+    }
+
+    pub async fn open(&self, source_path: &str) -> anyhow::Result<ByteReader> {
+        self.transport(source_path)?.open(source_path).await
+    }
+
+    /// The transport registered for the scheme of the source path
+    pub fn transport(&self, source_path: &str) -> anyhow::Result<Arc<dyn Transport>> {
+        let scheme = source_scheme(source_path);
+        match self.transports.get(&scheme) {
+            Some(transport) => Ok(transport.clone()),
+            None => anyhow::bail!("No transport registered for scheme {scheme} of source path {source_path}"),
+        }
+    }
+}
+
+
+impl Default for TransportRegistry {
+    /// Local files, stdin and HTTP(S). Object storage transports need credentials and are registered by the caller
+    fn default() -> Self {
+        let mut registry = TransportRegistry::empty();
+        registry
+            .register("file", LocalTransport)
+            .register(STDIN_SCHEME, StdinTransport)
+            .register("http", HttpTransport::default())
+            .register("https", HttpTransport::default());
+        registry
+    }
+}
+
+
+fn source_scheme(source_path: &str) -> String {
+    if source_path == STDIN_PATH {
+        return STDIN_SCHEME.to_owned();
+    }
+
+    match source_path.split_once("://") {
+        Some((scheme, _)) => scheme.to_lowercase(),
+        None => DEFAULT_SCHEME.to_owned()
+    }
+}
@@ -0,0 +1,11 @@
+use serde_json::Value;
+
+/// Binary values have no payload type, they are kept as lowercase hex strings
+pub(super) fn hex_value(bytes: &[u8]) -> Value {
+    Value::from(bytes.iter().map(|byte| format!("{byte:02x}")).collect::<String>())
+}
+
+/// Dates, times and timestamps are formatted as strings, `None` being a value out of their range
+pub(super) fn optional_string<T: ToString>(value: Option<T>) -> Value {
+    value.map(|value| Value::from(value.to_string())).unwrap_or(Value::Null)
+}
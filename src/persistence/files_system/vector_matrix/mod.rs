@@ -0,0 +1,41 @@
+mod npy_rows;
+mod npz_entry;
+mod vecs_rows;
+mod vector_file_type;
+
+pub use vector_file_type::VectorFileType;
+
+use npy_rows::NpyRows;
+use npz_entry::open_npz_array;
+use vecs_rows::VecsRows;
+
+use super::ByteReader;
+
+/// Largest dimension of a vector in a matrix file, the one Qdrant accepts, beyond which the file is taken for corrupt
+const MAX_DIMENSION: usize = 65536;
+
+/// Vectors read row by row from a binary matrix file
+pub enum VectorRows {
+    Npy(NpyRows),
+    Vecs(VecsRows),
+}
+
+impl VectorRows {
+    /// `npz_array` names the array read from `.npz` archives
+    pub async fn open(reader: ByteReader, vector_file_type: VectorFileType, npz_array: &str) -> anyhow::Result<Self> {
+        let vector_rows = match vector_file_type {
+            VectorFileType::Npy => VectorRows::Npy(NpyRows::open(reader).await?),
+            VectorFileType::Npz => VectorRows::Npy(NpyRows::open(open_npz_array(reader, npz_array).await?).await?),
+            VectorFileType::Fvecs | VectorFileType::Bvecs | VectorFileType::Ivecs => VectorRows::Vecs(VecsRows::new(reader, vector_file_type)),
+        };
+
+        Ok(vector_rows)
+    }
+
+    pub async fn next_vector(&mut self) -> anyhow::Result<Option<Vec<f32>>> {
+        match self {
+            VectorRows::Npy(rows) => rows.next_vector().await,
+            VectorRows::Vecs(rows) => rows.next_vector().await,
+        }
+    }
+}
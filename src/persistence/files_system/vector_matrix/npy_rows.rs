@@ -0,0 +1,135 @@
+use tokio::io::{AsyncReadExt, BufReader};
+
+use crate::persistence::files_system::ByteReader;
+
+use super::MAX_DIMENSION;
+
+const MAGIC: &[u8] = b"\x93NUMPY";
+/// Largest header of an .npy file, a Python dict literal describing the array
+const MAX_HEADER_LENGTH: usize = 64 * 1024;
+
+/// Rows of a C-ordered 2-D NumPy array, read one at a time
+pub struct NpyRows {
+    reader: BufReader<ByteReader>,
+    element_type: ElementType,
+    /// Bytes of each row
+    row_length: usize,
+    rows_left: usize,
+}
+
+impl NpyRows {
+    pub async fn open(reader: ByteReader) -> anyhow::Result<Self> {
+        let mut reader = BufReader::new(reader);
+
+        let mut preamble = [0u8; 8];
+        reader.read_exact(&mut preamble).await?;
+        if &preamble[..6] != MAGIC {
+            anyhow::bail!("Not a NumPy .npy file");
+        }
+
+        let header_length = match preamble[6] {
+            1 => reader.read_u16_le().await? as usize,
+            _ => reader.read_u32_le().await? as usize,
+        };
+        if header_length > MAX_HEADER_LENGTH {
+            anyhow::bail!("Invalid NumPy header of {header_length} bytes, at most {MAX_HEADER_LENGTH} are accepted");
+        }
+        let mut header = vec![0u8; header_length];
+        reader.read_exact(&mut header).await?;
+        let header = String::from_utf8_lossy(&header);
+
+        if header_value(&header, "fortran_order").map(|order| order.starts_with("True")).unwrap_or(false) {
+            anyhow::bail!("Fortran ordered NumPy arrays are not supported, save the array in C order");
+        }
+
+        let Some(descr) = header_value(&header, "descr") else {
+            anyhow::bail!("NumPy header without descr: {header}");
+        };
+        let element_type = ElementType::parse(descr.trim_matches(|character| character == '\'' || character == '"'))?;
+
+        let shape: Vec<usize> = header_value(&header, "shape")
+            .map(|shape| shape.trim_matches(|character| character == '(' || character == ')')
+                .split(',')
+                .map(str::trim)
+                .filter(|size| !size.is_empty())
+                .map(str::parse)
+                .collect::<Result<_, _>>())
+            .transpose()?
+            .unwrap_or_default();
+        let [rows, dimension] = shape[..] else {
+            anyhow::bail!("The NumPy array must have 2 dimensions, found shape {shape:?}");
+        };
+        if !(1..=MAX_DIMENSION).contains(&dimension) {
+            anyhow::bail!("Invalid NumPy vector dimension {dimension}, it must be between 1 and {MAX_DIMENSION}");
+        }
+        let Some(row_length) = dimension.checked_mul(element_type.size) else {
+            anyhow::bail!("Invalid NumPy vector dimension {dimension}, its rows do not fit in memory");
+        };
+
+        tracing::info!("Reading {rows} vectors of dimension {dimension}");
+
+        Ok(NpyRows { reader, element_type, row_length, rows_left: rows })
+    }
+
+    pub async fn next_vector(&mut self) -> anyhow::Result<Option<Vec<f32>>> {
+        if self.rows_left == 0 {
+            return Ok(None);
+        }
+
+        let mut row = vec![0u8; self.row_length];
+        self.reader.read_exact(&mut row).await?;
+        self.rows_left -= 1;
+
+        Ok(Some(row.chunks_exact(self.element_type.size).map(|element| self.element_type.to_f32(element)).collect()))
+    }
+}
+
+
+/// Value of a key of the Python dict literal of an .npy header, up to the next key
+fn header_value<'a>(header: &'a str, key: &str) -> Option<&'a str> {
+    let start = header.find(&format!("'{key}':"))? + key.len() + 3;
+    let value = header[start..].trim_start();
+    let end = if value.starts_with('(') {
+        value.find(')').map(|end| end + 1)
+    } else {
+        value.find(',')
+    };
+
+    Some(end.map(|end| &value[..end]).unwrap_or(value).trim())
+}
+
+
+struct ElementType {
+    kind: char,
+    size: usize,
+    big_endian: bool,
+}
+
+impl ElementType {
+    fn parse(descr: &str) -> anyhow::Result<Self> {
+        let mut characters = descr.chars();
+        let big_endian = match characters.next() {
+            Some('>') => true,
+            Some('<') | Some('|') | Some('=') => false,
+            _ => anyhow::bail!("Unsupported NumPy dtype {descr}"),
+        };
+        let kind = characters.next().unwrap_or_default();
+        let size: usize = characters.as_str().parse()?;
+
+        match (kind, size) {
+            ('f', 4) | ('f', 8) | ('u', 1) | ('i', 1) => Ok(ElementType { kind, size, big_endian }),
+            _ => anyhow::bail!("Unsupported NumPy dtype {descr}, vectors must be float32, float64, uint8 or int8")
+        }
+    }
+
+    fn to_f32(&self, bytes: &[u8]) -> f32 {
+        match (self.kind, self.size, self.big_endian) {
+            ('f', 4, false) => f32::from_le_bytes(bytes.try_into().unwrap()),
+            ('f', 4, true) => f32::from_be_bytes(bytes.try_into().unwrap()),
+            ('f', 8, false) => f64::from_le_bytes(bytes.try_into().unwrap()) as f32,
+            ('f', 8, true) => f64::from_be_bytes(bytes.try_into().unwrap()) as f32,
+            ('i', 1, _) => bytes[0] as i8 as f32,
+            _ => bytes[0] as f32,
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use async_zip::base::read::stream::{Reading, ZipFileReader};
+use async_zip::base::read::WithEntry;
+use tokio::io::{AsyncRead, BufReader, ReadBuf};
+use tokio_util::compat::Compat;
+
+use crate::persistence::files_system::ByteReader;
+
+type ZipEntry = ZipFileReader<Reading<'static, Compat<BufReader<ByteReader>>, WithEntry<'static>>>;
+
+/// Streams the `.npy` member named `array_name` out of an `.npz` archive, reading its local headers in order
+pub async fn open_npz_array(reader: ByteReader, array_name: &str) -> anyhow::Result<ByteReader> {
+    let member_name = format!("{array_name}.npy");
+    let mut zip = ZipFileReader::with_tokio(BufReader::new(reader));
+
+    loop {
+        let Some(entry) = zip.next_with_entry().await? else {
+            anyhow::bail!("The .npz archive has no array named {array_name}");
+        };
+
+        if entry.reader().entry().filename().as_str()? == member_name {
+            return Ok(Box::new(NpzEntry { entry }));
+        }
+        zip = entry.skip().await?;
+    }
+}
+
+
+struct NpzEntry {
+    entry: ZipEntry,
+}
+
+impl AsyncRead for NpzEntry {
+    fn poll_read(mut self: Pin<&mut Self>, context: &mut Context<'_>, buffer: &mut ReadBuf<'_>) -> Poll<std::io::Result<()>> {
+        let entry_reader = self.entry.reader_mut();
+        match futures::AsyncRead::poll_read(Pin::new(entry_reader), context, buffer.initialize_unfilled()) {
+            Poll::Ready(Ok(read)) => {
+                buffer.advance(read);
+                Poll::Ready(Ok(()))
+            },
+            Poll::Ready(Err(error)) => Poll::Ready(Err(error)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use tokio::io::{AsyncReadExt, BufReader};
+
+use crate::persistence::files_system::ByteReader;
+
+use super::{VectorFileType, MAX_DIMENSION};
+
+/// Rows of the `.fvecs`, `.bvecs` and `.ivecs` formats of the ANN benchmark datasets,
+/// where every vector is preceded by its dimension as a little-endian int32
+pub struct VecsRows {
+    reader: BufReader<ByteReader>,
+    vector_file_type: VectorFileType,
+    dimension: Option<usize>,
+}
+
+impl VecsRows {
+    pub fn new(reader: ByteReader, vector_file_type: VectorFileType) -> Self {
+        VecsRows { reader: BufReader::new(reader), vector_file_type, dimension: None }
+    }
+
+    pub async fn next_vector(&mut self) -> anyhow::Result<Option<Vec<f32>>> {
+        let mut dimension_bytes = [0u8; 4];
+        let read = self.reader.read(&mut dimension_bytes).await?;
+        if read == 0 {
+            return Ok(None);
+        }
+        self.reader.read_exact(&mut dimension_bytes[read..]).await?;
+
+        let dimension = i32::from_le_bytes(dimension_bytes);
+        let dimension = match usize::try_from(dimension) {
+            Ok(dimension) if (1..=MAX_DIMENSION).contains(&dimension) => dimension,
+            _ => anyhow::bail!("Invalid vector dimension {dimension}, it must be between 1 and {MAX_DIMENSION}"),
+        };
+        if self.dimension.map(|expected| expected != dimension).unwrap_or(false) {
+            anyhow::bail!("Invalid vector dimension {dimension}, previous vectors had {previous:?}", previous=self.dimension);
+        }
+        self.dimension = Some(dimension);
+
+        let element_size = match self.vector_file_type {
+            VectorFileType::Bvecs => 1,
+            _ => 4,
+        };
+        let Some(row_length) = dimension.checked_mul(element_size) else {
+            anyhow::bail!("Invalid vector dimension {dimension}, its rows do not fit in memory");
+        };
+        let mut row = vec![0u8; row_length];
+        self.reader.read_exact(&mut row).await?;
+
+        let vector = match self.vector_file_type {
+            VectorFileType::Bvecs => row.iter().map(|element| *element as f32).collect(),
+            VectorFileType::Ivecs => row.chunks_exact(4).map(|element| i32::from_le_bytes(element.try_into().unwrap()) as f32).collect(),
+            _ => row.chunks_exact(4).map(|element| f32::from_le_bytes(element.try_into().unwrap())).collect(),
+        };
+
+        Ok(Some(vector))
+    }
+}
@@ -0,0 +1,28 @@
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VectorFileType {
+    /// NumPy 2-D array of floats or bytes
+    Npy,
+    /// NumPy archive, the array is picked by name
+    Npz,
+    /// Each vector prefixed with its dimension, as little-endian float32 values
+    Fvecs,
+    /// Each vector prefixed with its dimension, as unsigned bytes
+    Bvecs,
+    /// Each vector prefixed with its dimension, as little-endian int32 values
+    Ivecs,
+}
+
+impl VectorFileType {
+    /// Guesses the type from the extension of the source path
+    pub fn detect(source_path: &str) -> anyhow::Result<VectorFileType> {
+        let extension = source_path.rsplit('.').next().unwrap_or_default().to_lowercase();
+        match extension.as_str() {
+            "npy" => Ok(VectorFileType::Npy),
+            "npz" => Ok(VectorFileType::Npz),
+            "fvecs" => Ok(VectorFileType::Fvecs),
+            "bvecs" => Ok(VectorFileType::Bvecs),
+            "ivecs" => Ok(VectorFileType::Ivecs),
+            _ => anyhow::bail!("Can not detect the vector file type of {source_path}, it must be provided")
+        }
+    }
+}
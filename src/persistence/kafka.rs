@@ -0,0 +1,202 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use rdkafka::consumer::{CommitMode, Consumer, StreamConsumer};
+use rdkafka::message::BorrowedMessage;
+use rdkafka::{ClientConfig, Message, Offset, TopicPartitionList};
+use serde_json::Value;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+
+use crate::record_source::{RecordSource, SourceStatistics};
+use crate::shutdown;
+
+const SOURCE_PATH_PREFIX: &str = "kafka://";
+
+/// Consumes JSON messages of a Kafka topic as rows. The source only ends once stopped, by [`KafkaSource::stop`] or
+/// by a termination signal, after returning the batch in progress.
+///
+/// Offsets are committed by [`RecordSource::commit`], once the rows of a batch were written to the sink, so every
+/// message is delivered at least once. Messages that are not valid JSON are skipped with a warning
+pub struct KafkaSource {
+    consumer: Arc<StreamConsumer>,
+    flush_interval: Duration,
+    next_offsets: Mutex<HashMap<(String, i32), i64>>,
+    stopped: CancellationToken,
+    bytes_read: AtomicU64,
+    messages_rejected: AtomicU64,
+}
+
+/// Settings of a [`KafkaSource`]
+#[derive(Debug, Clone)]
+pub struct KafkaOptions {
+    /// Comma separated `host:port` list of bootstrap brokers
+    pub brokers: String,
+    pub topic: String,
+    pub group_id: String,
+    /// Longest time a batch waits to be filled once its first message was received
+    pub flush_interval: Duration,
+    /// Any other librdkafka consumer setting, such as `security.protocol` or `sasl.mechanism`
+    pub client_settings: Vec<(String, String)>,
+}
+
+impl KafkaOptions {
+    /// Reads the brokers and topic of a `kafka://host:port,host:port/topic` source path
+    pub fn from_source_path(source_path: &str, group_id: &str, flush_interval: Duration) -> anyhow::Result<Self> {
+        let Some((brokers, topic)) = source_path.strip_prefix(SOURCE_PATH_PREFIX).and_then(|path| path.split_once('/')) else {
+            anyhow::bail!("Invalid Kafka source path {source_path}, expected kafka://host:port/topic");
+        };
+
+        if brokers.is_empty() || topic.is_empty() {
+            anyhow::bail!("Invalid Kafka source path {source_path}, expected kafka://host:port/topic");
+        }
+
+        Ok(KafkaOptions {
+            brokers: brokers.to_owned(),
+            topic: topic.to_owned(),
+            group_id: group_id.to_owned(),
+            flush_interval,
+            client_settings: Vec::new(),
+        })
+    }
+}
+
+
+impl KafkaSource {
+    /// Subscribes to the topic. Without a committed offset for the group, the topic is read from its beginning
+    pub fn new(options: KafkaOptions) -> anyhow::Result<Self> {
+        let mut client_config = ClientConfig::new();
+        client_config
+            .set("bootstrap.servers", &options.brokers)
+            .set("group.id", &options.group_id)
+            .set("enable.auto.commit", "false")
+            .set("auto.offset.reset", "earliest");
+        for (key, value) in options.client_settings.iter() {
+            client_config.set(key, value);
+        }
+
+        let consumer: StreamConsumer = client_config.create()?;
+        consumer.subscribe(&[&options.topic])?;
+
+        tracing::info!("Consuming Kafka topic {topic} as group {group_id}", topic=options.topic, group_id=options.group_id);
+
+        let stopped = CancellationToken::new();
+        shutdown::cancel_on_termination(stopped.clone(), "Stopping the Kafka source after the batch in progress");
+
+        Ok(KafkaSource {
+            consumer: Arc::new(consumer),
+            flush_interval: options.flush_interval,
+            next_offsets: Mutex::new(HashMap::new()),
+            stopped,
+            bytes_read: AtomicU64::new(0),
+            messages_rejected: AtomicU64::new(0),
+        })
+    }
+
+    /// Ends the source after the batch in progress
+    pub fn stop(&self) {
+        self.stopped.cancel();
+    }
+
+    /// Waits for the next message until the deadline, if any. Returns `None` once stopped or past the deadline
+    async fn receive(&self, deadline: Option<Instant>) -> anyhow::Result<Option<Value>> {
+        loop {
+            let message = tokio::select! {
+                message = self.consumer.recv() => message?,
+                _ = self.stopped.cancelled() => return Ok(None),
+                _ = sleep_until(deadline) => return Ok(None),
+            };
+
+            self.next_offsets.lock().await
+                .insert((message.topic().to_owned(), message.partition()), message.offset() + 1);
+
+            self.bytes_read.fetch_add(message.payload_len() as u64, Ordering::Relaxed);
+
+            match parse_message(&message) {
+                Some(value) => return Ok(Some(value)),
+                None => {
+                    self.messages_rejected.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+    }
+}
+
+
+#[async_trait]
+impl RecordSource for KafkaSource {
+
+    async fn next_record(&self) -> anyhow::Result<Option<Value>> {
+        self.receive(None).await
+    }
+
+    /// Waits for a first message, then flushes the batch once it is full or once the flush interval has elapsed
+    async fn next_batch(&self, batch_size: u32) -> anyhow::Result<Option<Vec<Value>>> {
+        let Some(first_value) = self.receive(None).await? else {
+            return Ok(None);
+        };
+
+        let deadline = Instant::now() + self.flush_interval;
+        let mut batch = vec![first_value];
+        while batch.len() < batch_size as usize {
+            match self.receive(Some(deadline)).await? {
+                Some(value) => batch.push(value),
+                None => break,
+            }
+        }
+
+        tracing::debug!(rows=batch.len(), batch_size, "Batch consumed from Kafka");
+        Ok(Some(batch))
+    }
+
+    /// Commits the offsets following the last message of each partition received so far
+    async fn commit(&self) -> anyhow::Result<()> {
+        let next_offsets = std::mem::take(&mut *self.next_offsets.lock().await);
+        if next_offsets.is_empty() {
+            return Ok(());
+        }
+
+        let mut offsets = TopicPartitionList::new();
+        for ((topic, partition), offset) in next_offsets {
+            offsets.add_partition_offset(&topic, partition, Offset::Offset(offset))?;
+        }
+
+        // A synchronous commit blocks until the broker acknowledges it
+        let consumer = self.consumer.clone();
+        tokio::task::spawn_blocking(move || consumer.commit(&offsets, CommitMode::Sync)).await??;
+        Ok(())
+    }
+
+    /// Bytes of the message payloads, and messages that are not valid JSON as rejected rows
+    fn statistics(&self) -> SourceStatistics {
+        SourceStatistics {
+            bytes_read: self.bytes_read.load(Ordering::Relaxed),
+            rows_rejected: self.messages_rejected.load(Ordering::Relaxed),
+            retries: 0,
+        }
+    }
+}
+
+
+fn parse_message(message: &BorrowedMessage<'_>) -> Option<Value> {
+    let payload = message.payload()?;
+    match serde_json::from_slice(payload) {
+        Ok(value) => Some(value),
+        Err(error) => {
+            tracing::warn!("Skipping message {offset} of partition {partition} of {topic}, it is not valid JSON: {error}",
+                offset=message.offset(), partition=message.partition(), topic=message.topic());
+            None
+        }
+    }
+}
+
+async fn sleep_until(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
@@ -1,6 +1,9 @@
 pub mod vector_field_name;
 mod database;
 pub mod files_system;
+#[cfg(feature = "kafka")]
+pub mod kafka;
 
 
-pub use database::DatabaseClient;
+pub(crate) use database::{AliasSwitch, BulkLoadGuard};
+pub use database::{CollectionSettings, ConnectionOptions, DatabaseClient, PointMapper, PointMapperBuilder, QuantizationKind, TlsSettings, UploadVerifier, VectorDistance, WriteOrderingKind};
@@ -0,0 +1,88 @@
+use async_trait::async_trait;
+
+/// A source of rows to be uploaded, each row being a JSON object.
+///
+/// Implementors only need to provide [`RecordSource::next_record`], batching is done on top of it.
+#[async_trait]
+pub trait RecordSource: Send + Sync {
+
+    /// Returns the next row, or `None` once the source is exhausted
+    async fn next_record(&self) -> anyhow::Result<Option<serde_json::Value>>;
+
+    /// Returns up to `batch_size` rows, or `None` once the source is exhausted
+    async fn next_batch(&self, batch_size: u32) -> anyhow::Result<Option<Vec<serde_json::Value>>> {
+        let mut batch = Vec::new();
+
+        for _ in 0..batch_size {
+            let next_value = self.next_record().await?;
+            
+            if let Some(value) = next_value {
+                batch.push(value);
+            } else {
+                break;
+            }
+        }
+        
+        if batch.is_empty() {
+            Ok(None)
+        } else {
+            tracing::debug!(rows=batch.len(), batch_size, "Batch read from the source");
+            Ok(Some(batch))
+        }
+    }
+
+    /// Called once every row returned so far has been written to the sink. Sources that acknowledge what they
+    /// read, such as a message queue, do it here so that no row is lost if the upload fails
+    async fn commit(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// Totals since the source was opened, reported to the metrics after each batch
+    fn statistics(&self) -> SourceStatistics {
+        SourceStatistics::default()
+    }
+
+    /// Hex encoded SHA-256 of the bytes read so far, for sources read from a file
+    fn checksum(&self) -> Option<String> {
+        None
+    }
+
+}
+
+
+/// What a [`RecordSource`] read besides the rows it returned
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SourceStatistics {
+    /// Bytes received from the transport, before decompression
+    pub bytes_read: u64,
+    /// Rows skipped because they could not be parsed
+    pub rows_rejected: u64,
+    /// Interrupted downloads that were resumed
+    pub retries: u64,
+}
+
+
+/// Lets a source chosen at runtime be used wherever a [`RecordSource`] is expected
+#[async_trait]
+impl<S: RecordSource + ?Sized> RecordSource for Box<S> {
+
+    async fn next_record(&self) -> anyhow::Result<Option<serde_json::Value>> {
+        (**self).next_record().await
+    }
+
+    async fn next_batch(&self, batch_size: u32) -> anyhow::Result<Option<Vec<serde_json::Value>>> {
+        (**self).next_batch(batch_size).await
+    }
+
+    async fn commit(&self) -> anyhow::Result<()> {
+        (**self).commit().await
+    }
+
+    fn statistics(&self) -> SourceStatistics {
+        (**self).statistics()
+    }
+
+    fn checksum(&self) -> Option<String> {
+        (**self).checksum()
+    }
+}
@@ -0,0 +1,118 @@
+use std::process::ExitCode;
+use std::time::Duration;
+
+use qdrant_uploader::FailureKind;
+use qdrant_uploader::monitoring::MetricsSnapshot;
+use serde_json::{json, Value};
+
+/// How a run ended, each outcome having its own process exit code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunOutcome {
+    Succeeded,
+    /// Any failure that is not one of the kinds below, such as a failed verification
+    Failed,
+    ConfigError,
+    SourceError,
+    /// Every batch was uploaded, but some rows of the source were rejected, or uploads of watched objects failed
+    PartialFailure,
+    QdrantError,
+}
+
+impl RunOutcome {
+    fn exit_code(self) -> u8 {
+        match self {
+            RunOutcome::Succeeded => 0,
+            RunOutcome::Failed => 1,
+            RunOutcome::ConfigError => 2,
+            RunOutcome::SourceError => 3,
+            RunOutcome::PartialFailure => 4,
+            RunOutcome::QdrantError => 5,
+        }
+    }
+
+    fn status(self) -> &'static str {
+        match self {
+            RunOutcome::Succeeded => "succeeded",
+            RunOutcome::Failed => "failed",
+            RunOutcome::ConfigError => "config_error",
+            RunOutcome::SourceError => "source_error",
+            RunOutcome::PartialFailure => "partial_failure",
+            RunOutcome::QdrantError => "qdrant_error",
+        }
+    }
+}
+
+
+/// What is only known by the upload job at the end of a run
+#[derive(Debug, Default)]
+pub struct SourceReport {
+    pub checksum: Option<String>,
+    /// Only counted with --verify
+    pub duplicate_ids: Option<u64>,
+}
+
+
+/// Totals of a run, written as a JSON object once it ends, whether it succeeded or not
+pub struct RunSummary {
+    outcome: RunOutcome,
+    error: Option<String>,
+    metrics: MetricsSnapshot,
+    source_report: SourceReport,
+    elapsed: Duration,
+}
+
+impl RunSummary {
+    pub fn new(result: anyhow::Result<SourceReport>, metrics: MetricsSnapshot, elapsed: Duration) -> Self {
+        let (outcome, error, source_report) = match result {
+            Ok(source_report) if metrics.rows_rejected > 0 || metrics.objects_failed > 0 =>
+                (RunOutcome::PartialFailure, None, source_report),
+            Ok(source_report) => (RunOutcome::Succeeded, None, source_report),
+            Err(error) => {
+                let outcome = match FailureKind::of(&error) {
+                    Some(FailureKind::Config) => RunOutcome::ConfigError,
+                    Some(FailureKind::Source) => RunOutcome::SourceError,
+                    Some(FailureKind::Qdrant) => RunOutcome::QdrantError,
+                    None => RunOutcome::Failed,
+                };
+                (outcome, Some(format!("{error:#}")), SourceReport::default())
+            },
+        };
+
+        RunSummary { outcome, error, metrics, source_report, elapsed }
+    }
+
+    pub fn exit_code(&self) -> ExitCode {
+        ExitCode::from(self.outcome.exit_code())
+    }
+
+    pub fn to_json(&self) -> Value {
+        let elapsed_seconds = self.elapsed.as_secs_f64();
+        let points_per_second = if elapsed_seconds > 0.0 { self.metrics.points_upserted as f64 / elapsed_seconds } else { 0.0 };
+
+        json!({
+            "status": self.outcome.status(),
+            "exit_code": self.outcome.exit_code(),
+            "error": self.error,
+            "rows_read": self.metrics.rows_read,
+            "points_upserted": self.metrics.points_upserted,
+            "rows_rejected": self.metrics.rows_rejected,
+            "duplicate_ids": self.source_report.duplicate_ids,
+            "retries": self.metrics.retries,
+            "bytes_read": self.metrics.bytes_read,
+            "objects_uploaded": self.metrics.objects_uploaded,
+            "objects_failed": self.metrics.objects_failed,
+            "elapsed_seconds": elapsed_seconds,
+            "points_per_second": points_per_second,
+            "source_checksum": self.source_report.checksum,
+        })
+    }
+
+    /// Writes the summary to the file if given, otherwise as a single line on stdout
+    pub async fn write(&self, summary_path: Option<&str>) -> anyhow::Result<()> {
+        match summary_path {
+            Some(summary_path) => tokio::fs::write(summary_path, serde_json::to_vec_pretty(&self.to_json())?).await?,
+            None => println!("{}", self.to_json()),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,39 @@
+use tokio_util::sync::CancellationToken;
+
+/// Cancels the token on a termination signal, logging the message. Long-running work checks the token
+/// to stop once what is in progress is done
+pub(crate) fn cancel_on_termination(stopped: CancellationToken, message: &'static str) {
+    tokio::spawn(async move {
+        tokio::select! {
+            _ = termination_signal() => {
+                tracing::info!("{message}");
+                stopped.cancel();
+            },
+            _ = stopped.cancelled() => {},
+        }
+    });
+}
+
+/// Resolves on Ctrl-C, or on SIGTERM as sent by `docker stop`
+async fn termination_signal() {
+    #[cfg(unix)]
+    let mut terminate = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()).ok();
+    #[cfg(unix)]
+    let terminated = async {
+        match terminate.as_mut() {
+            Some(terminate) => { terminate.recv().await; },
+            None => std::future::pending().await,
+        }
+    };
+    #[cfg(not(unix))]
+    let terminated = std::future::pending::<()>();
+
+    tokio::select! {
+        interrupted = tokio::signal::ctrl_c() => {
+            if interrupted.is_err() {
+                std::future::pending::<()>().await;
+            }
+        },
+        _ = terminated => {},
+    }
+}
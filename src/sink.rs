@@ -0,0 +1,18 @@
+use async_trait::async_trait;
+use qdrant_client::qdrant::PointStruct;
+use qdrant_client::qdrant::shard_key::Key as ShardKey;
+
+/// Destination of the points produced by a [`crate::PointMapper`].
+///
+/// [`crate::persistence::DatabaseClient`] is the Qdrant implementation.
+#[async_trait]
+pub trait Sink: Send + Sync {
+
+    /// Writes the points of a batch. When a shard key is given, every point belongs to that shard key
+    async fn write_points(&self, shard_key: Option<ShardKey>, points: Vec<PointStruct>) -> anyhow::Result<()>;
+
+    /// Waits for the points written so far to be stored, called once the last batch is written
+    async fn flush(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
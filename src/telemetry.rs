@@ -0,0 +1,65 @@
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::{trace, Resource};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
+
+const SERVICE_NAME: &str = "qdrant-uploader";
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    Text,
+    /// One JSON object per line, with the fields of the event and of its spans
+    Json,
+}
+
+/// Flushes the spans not exported yet when dropped
+pub struct TelemetryGuard {
+    exports_spans: bool,
+}
+
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        if self.exports_spans {
+            opentelemetry::global::shutdown_tracer_provider();
+        }
+    }
+}
+
+/// Logs to stderr, filtered by `RUST_LOG`, and exports spans to an OTLP gRPC collector if an endpoint is given.
+/// Lines written with the `log` crate by dependencies are logged too
+pub fn init_telemetry(log_format: LogFormat, otlp_endpoint: Option<&str>) -> anyhow::Result<TelemetryGuard> {
+    let log_layer = match log_format {
+        LogFormat::Text => tracing_subscriber::fmt::layer()
+            .with_writer(std::io::stderr)
+            .boxed(),
+        LogFormat::Json => tracing_subscriber::fmt::layer()
+            .json()
+            .flatten_event(true)
+            .with_current_span(false)
+            .with_span_list(true)
+            .with_writer(std::io::stderr)
+            .boxed(),
+    };
+
+    let span_layer = match otlp_endpoint {
+        Some(endpoint) => {
+            let tracer = opentelemetry_otlp::new_pipeline()
+                .tracing()
+                .with_exporter(opentelemetry_otlp::new_exporter().tonic().with_endpoint(endpoint))
+                .with_trace_config(trace::config().with_resource(Resource::new(vec![KeyValue::new("service.name", SERVICE_NAME)])))
+                .install_batch(opentelemetry_sdk::runtime::TokioCurrentThread)?;
+            Some(tracing_opentelemetry::layer().with_tracer(tracer).with_filter(LevelFilter::INFO))
+        },
+        None => None,
+    };
+
+    tracing_subscriber::registry()
+        .with(log_layer.with_filter(EnvFilter::from_default_env()))
+        .with(span_layer)
+        .try_init()?;
+
+    Ok(TelemetryGuard { exports_spans: otlp_endpoint.is_some() })
+}
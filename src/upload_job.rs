@@ -0,0 +1,189 @@
+use std::sync::Arc;
+use std::time::Instant;
+
+use anyhow::Context;
+use tracing::Instrument;
+
+use crate::embedding::EmbeddingStage;
+use crate::failure::FailureKind;
+use crate::monitoring::Metrics;
+use crate::persistence::{PointMapper, UploadVerifier};
+use crate::record_source::{RecordSource, SourceStatistics};
+use crate::sink::Sink;
+
+const DEFAULT_BATCH_SIZE: u32 = 256;
+
+/// Reads batches from a [`RecordSource`], fills missing vectors with an optional [`EmbeddingStage`],
+/// maps the rows with a [`PointMapper`] and writes the points to a [`Sink`].
+pub struct UploadJob {
+    source: Box<dyn RecordSource>,
+    point_mapper: PointMapper,
+    sink: Box<dyn Sink>,
+    batch_size: u32,
+
+    embedding_stage: Option<Arc<EmbeddingStage>>,
+    verifier: Option<UploadVerifier>,
+    metrics: Option<Arc<Metrics>>,
+}
+
+impl UploadJob {
+
+    pub fn builder() -> UploadJobBuilder {
+        UploadJobBuilder::default()
+    }
+
+    /// Uploads every batch of the source, flushes the sink and returns the number of points written to it.
+    /// Errors of the source, of the conversion of its rows and of their embedding are tagged with [`FailureKind::Source`]
+    /// and errors of the sink with [`FailureKind::Qdrant`]
+    pub async fn run(&mut self) -> anyhow::Result<u64> {
+        let mut batches_uploaded = 0;
+        let mut points_uploaded = 0;
+        let mut reported_statistics = SourceStatistics::default();
+
+        while let Some(batch) = self.source.next_batch(self.batch_size).instrument(tracing::info_span!("read_source")).await
+            .context(FailureKind::Source)? {
+            let batch_start = Instant::now();
+            let batch_number = batches_uploaded + 1;
+            let batch_length = batch.len() as u64;
+
+            let batch_span = tracing::info_span!("batch", batch=batch_number, rows=batch_length);
+            let points_written = self.upload_batch(batch).instrument(batch_span).await?;
+            batches_uploaded = batch_number;
+            points_uploaded += points_written;
+            let duration = batch_start.elapsed();
+
+            if let Some(metrics) = self.metrics.as_ref() {
+                metrics.record_batch(batch_length, points_written, duration);
+                let statistics = self.source.statistics();
+                metrics.record_source_progress(SourceStatistics {
+                    bytes_read: statistics.bytes_read - reported_statistics.bytes_read,
+                    rows_rejected: statistics.rows_rejected - reported_statistics.rows_rejected,
+                    retries: statistics.retries - reported_statistics.retries,
+                });
+                reported_statistics = statistics;
+            }
+
+            tracing::info!(batch=batch_number, rows=batch_length, points=points_written, duration_ms=duration.as_millis() as u64, "Batch uploaded");
+        }
+
+        self.sink.flush().await.context(FailureKind::Qdrant)?;
+        Ok(points_uploaded)
+    }
+
+    /// Writes a batch to the sink, then commits it, and returns the number of points written
+    async fn upload_batch(&mut self, mut batch: Vec<serde_json::Value>) -> anyhow::Result<u64> {
+        if let Some(embedding_stage) = self.embedding_stage.as_ref() {
+            embedding_stage.fill_missing_vectors(&mut batch).await.context(FailureKind::Source)?;
+        }
+
+        if let Some(verifier) = self.verifier.as_mut() {
+            verifier.observe_batch(&self.point_mapper, &batch);
+        }
+
+        let sharded_points = tracing::info_span!("convert_points").in_scope(|| self.point_mapper.to_points(batch))
+            .context(FailureKind::Source)?;
+        let mut points_written = 0;
+        for (shard_key, points) in sharded_points {
+            points_written += points.len() as u64;
+            self.sink.write_points(shard_key, points).await.context(FailureKind::Qdrant)?;
+        }
+        self.source.commit().await.context(FailureKind::Source)?;
+
+        Ok(points_written)
+    }
+
+    /// Hex encoded SHA-256 of the bytes read from the source, see [`RecordSource::checksum`]
+    pub fn source_checksum(&self) -> Option<String> {
+        self.source.checksum()
+    }
+
+    /// The verifier that observed the uploaded rows, to be checked against the collection after [`UploadJob::run`]
+    pub fn verifier(&self) -> Option<&UploadVerifier> {
+        self.verifier.as_ref()
+    }
+}
+
+
+#[derive(Default)]
+pub struct UploadJobBuilder {
+    source: Option<Box<dyn RecordSource>>,
+    point_mapper: Option<PointMapper>,
+    sink: Option<Box<dyn Sink>>,
+    batch_size: Option<u32>,
+
+    embedding_stage: Option<Arc<EmbeddingStage>>,
+    verifier: Option<UploadVerifier>,
+    metrics: Option<Arc<Metrics>>,
+}
+
+impl UploadJobBuilder {
+
+    pub fn source(mut self, source: impl RecordSource + 'static) -> Self {
+        self.source = Some(Box::new(source));
+        self
+    }
+
+    pub fn point_mapper(mut self, point_mapper: PointMapper) -> Self {
+        self.point_mapper = Some(point_mapper);
+        self
+    }
+
+    pub fn sink(mut self, sink: impl Sink + 'static) -> Self {
+        self.sink = Some(Box::new(sink));
+        self
+    }
+
+    /// Number of rows read from the source at once, 256 if not set
+    pub fn batch_size(mut self, batch_size: u32) -> Self {
+        self.batch_size = Some(batch_size);
+        self
+    }
+
+    /// Shared by the jobs of a run, so that they use one embedding model and one cache
+    pub fn embedding_stage(mut self, embedding_stage: Arc<EmbeddingStage>) -> Self {
+        self.embedding_stage = Some(embedding_stage);
+        self
+    }
+
+    pub fn verifier(mut self, verifier: UploadVerifier) -> Self {
+        self.verifier = Some(verifier);
+        self
+    }
+
+    pub fn has_verifier(&self) -> bool {
+        self.verifier.is_some()
+    }
+
+    /// Metrics updated after each batch written to the sink
+    pub fn metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Fails with [`FailureKind::Config`] if the source, the point mapper or the sink were not set
+    pub fn build(self) -> anyhow::Result<UploadJob> {
+        let Some(source) = self.source else {
+            return Err(anyhow::anyhow!("An upload job requires a source").context(FailureKind::Config));
+        };
+
+        let Some(point_mapper) = self.point_mapper else {
+            return Err(anyhow::anyhow!("An upload job requires a point mapper").context(FailureKind::Config));
+        };
+
+        let Some(sink) = self.sink else {
+            return Err(anyhow::anyhow!("An upload job requires a sink").context(FailureKind::Config));
+        };
+
+        let upload_job = UploadJob {
+            source,
+            point_mapper,
+            sink,
+            batch_size: self.batch_size.unwrap_or(DEFAULT_BATCH_SIZE),
+            embedding_stage: self.embedding_stage,
+            verifier: self.verifier,
+            metrics: self.metrics,
+        };
+
+        Ok(upload_job)
+    }
+}
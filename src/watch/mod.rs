@@ -0,0 +1,173 @@
+mod watch_state;
+
+use std::collections::HashSet;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio_util::sync::CancellationToken;
+
+use crate::monitoring::Metrics;
+use crate::persistence::files_system::{ListedObject, ObjectListing};
+use crate::shutdown;
+
+pub use watch_state::WatchState;
+
+/// What is done to an object once uploaded, besides recording it in the [`WatchState`]
+#[derive(Debug, Clone, Default)]
+pub enum ProcessedAction {
+    #[default]
+    Keep,
+    /// Moves the object into a directory or prefix, which must not be under the watched location
+    MoveTo(String),
+    /// Sets tags on the object, S3 only
+    Tag(Vec<(String, String)>),
+}
+
+/// Failed uploads of an object after which it is skipped until it changes
+const DEFAULT_MAX_ATTEMPTS: u32 = 5;
+
+/// Polls an [`ObjectListing`] and uploads each object once per ETag: an object is recorded in the state
+/// right after its upload succeeds, a failed upload is retried on the next polls until it failed `max_attempts` times
+pub struct Watcher {
+    listing: Box<dyn ObjectListing>,
+    state: WatchState,
+    /// Objects of the last listing, to tell which ones did not change since
+    previous_objects: HashSet<ListedObject>,
+    poll_interval: Duration,
+    suffix: Option<String>,
+    processed_action: ProcessedAction,
+    max_attempts: u32,
+    metrics: Arc<Metrics>,
+    stopped: CancellationToken,
+}
+
+impl Watcher {
+
+    pub fn new(listing: impl ObjectListing + 'static, state: WatchState, poll_interval: Duration) -> Self {
+        Watcher {
+            listing: Box::new(listing),
+            state,
+            previous_objects: HashSet::new(),
+            poll_interval,
+            suffix: None,
+            processed_action: ProcessedAction::Keep,
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            metrics: Arc::new(Metrics::default()),
+            stopped: CancellationToken::new(),
+        }
+    }
+
+    /// Only objects whose path ends with the suffix are uploaded, such as `.jsonl`
+    pub fn suffix(mut self, suffix: &str) -> Self {
+        self.suffix = Some(suffix.to_owned());
+        self
+    }
+
+    pub fn processed_action(mut self, processed_action: ProcessedAction) -> Self {
+        self.processed_action = processed_action;
+        self
+    }
+
+    /// Failed uploads of an object after which it is skipped, until its ETag changes
+    pub fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    pub fn metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Ends [`Watcher::run`] once the object in progress is uploaded
+    pub fn stop(&self) {
+        self.stopped.cancel();
+    }
+
+    /// Polls until stopped or until a termination signal. `upload` receives the source path of each new object
+    /// and returns the number of points it upserted
+    pub async fn run<F, U>(&mut self, mut upload: F) -> anyhow::Result<()>
+        where F: FnMut(String) -> U, U: Future<Output = anyhow::Result<u64>> {
+        shutdown::cancel_on_termination(self.stopped.clone(), "Stopping the watcher after the object in progress");
+
+        while !self.stopped.is_cancelled() {
+            if let Err(error) = self.poll(&mut upload).await {
+                tracing::error!("Could not poll the watched location: {error:#}");
+            }
+
+            tokio::select! {
+                _ = tokio::time::sleep(self.poll_interval) => {},
+                _ = self.stopped.cancelled() => {},
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Uploads the objects not processed yet and returns how many were uploaded. Fails only if the location
+    /// can not be listed or the state can not be saved, failed uploads are logged, counted in the state and
+    /// retried on the next polls
+    pub async fn poll<F, U>(&mut self, upload: &mut F) -> anyhow::Result<usize>
+        where F: FnMut(String) -> U, U: Future<Output = anyhow::Result<u64>> {
+        let objects = match self.listing.list().await {
+            Ok(objects) => objects,
+            Err(error) => {
+                self.metrics.record_poll(false);
+                return Err(error);
+            }
+        };
+        self.metrics.record_poll(true);
+
+        let pending_objects: Vec<ListedObject> = objects.iter().filter(|object| self.is_pending(object)).cloned().collect();
+        self.previous_objects = objects.into_iter().collect();
+        let mut objects_uploaded = 0;
+        for object in pending_objects.iter() {
+            if self.stopped.is_cancelled() {
+                break;
+            }
+
+            tracing::info!("Uploading new object {path}", path=object.path);
+            match upload(object.path.clone()).await {
+                Ok(points_upserted) => {
+                    self.state.record(object).await?;
+                    self.metrics.record_object_uploaded();
+                    objects_uploaded += 1;
+                    tracing::info!("{points_upserted} points upserted from {path}", path=object.path);
+
+                    if let Err(error) = self.apply_processed_action(object).await {
+                        tracing::error!("Uploaded {path}, but could not move or tag it: {error:#}", path=object.path);
+                    }
+                },
+                Err(error) => {
+                    self.metrics.record_object_failed();
+                    let attempts = self.state.record_failure(object).await?;
+                    if attempts < self.max_attempts {
+                        tracing::error!("Could not upload {path}, it will be retried on the next poll after {attempts} of {max_attempts} attempts: {error:#}",
+                            path=object.path, max_attempts=self.max_attempts);
+                    } else {
+                        tracing::error!("Could not upload {path} after {attempts} attempts, it is skipped until it changes: {error:#}", path=object.path);
+                    }
+                }
+            }
+        }
+
+        Ok(objects_uploaded)
+    }
+
+    /// Files still being written are left for a later poll, until their ETag is the one of the previous listing
+    fn is_pending(&self, object: &ListedObject) -> bool {
+        let has_suffix = self.suffix.as_ref().map(|suffix| object.path.ends_with(suffix)).unwrap_or(true);
+        let is_complete = !self.listing.lists_partial_objects() || self.previous_objects.contains(object);
+        let is_retried = self.state.failed_attempts(object) < self.max_attempts;
+        has_suffix && is_complete && is_retried && !self.state.contains(object)
+    }
+
+    async fn apply_processed_action(&self, object: &ListedObject) -> anyhow::Result<()> {
+        match &self.processed_action {
+            ProcessedAction::Keep => Ok(()),
+            ProcessedAction::MoveTo(destination) => self.listing.move_object(object, destination).await,
+            ProcessedAction::Tag(tags) => self.listing.tag_object(object, tags).await,
+        }
+    }
+}
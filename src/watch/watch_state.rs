@@ -0,0 +1,114 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use serde_json::{json, Value};
+
+use crate::persistence::files_system::ListedObject;
+
+/// Objects already uploaded and failed upload attempts, keyed by path and ETag, kept in a local JSON file that is
+/// rewritten after each upload
+pub struct WatchState {
+    state_path: PathBuf,
+    processed: BTreeSet<(String, String)>,
+    failed_attempts: BTreeMap<(String, String), u32>,
+}
+
+impl WatchState {
+    /// Reads the state file, starting from an empty state if it does not exist yet
+    pub async fn load(state_path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let state_path = state_path.as_ref().to_path_buf();
+        let (processed, failed_attempts) = match tokio::fs::read(&state_path).await {
+            Ok(content) => {
+                let state = serde_json::from_slice(&content)?;
+                (parse_processed(&state)?, parse_failed_attempts(&state)?)
+            },
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => (BTreeSet::new(), BTreeMap::new()),
+            Err(error) => return Err(error.into()),
+        };
+
+        tracing::info!("{count} objects already processed according to {path}", count=processed.len(), path=state_path.display());
+        Ok(WatchState { state_path, processed, failed_attempts })
+    }
+
+    pub fn contains(&self, object: &ListedObject) -> bool {
+        self.processed.contains(&key(object))
+    }
+
+    /// Number of failed uploads of the object, since it has its current ETag
+    pub fn failed_attempts(&self, object: &ListedObject) -> u32 {
+        self.failed_attempts.get(&key(object)).copied().unwrap_or(0)
+    }
+
+    /// Adds the object and saves the state
+    pub async fn record(&mut self, object: &ListedObject) -> anyhow::Result<()> {
+        self.failed_attempts.remove(&key(object));
+        self.processed.insert(key(object));
+        self.save().await
+    }
+
+    /// Counts a failed upload of the object, saves the state and returns the number of failed uploads so far
+    pub async fn record_failure(&mut self, object: &ListedObject) -> anyhow::Result<u32> {
+        let attempts = self.failed_attempts.entry(key(object)).or_insert(0);
+        *attempts += 1;
+        let attempts = *attempts;
+        self.save().await?;
+        Ok(attempts)
+    }
+
+    /// Replaces the file only once the new one is completely written
+    async fn save(&self) -> anyhow::Result<()> {
+        let processed: Vec<Value> = self.processed.iter()
+            .map(|(path, etag)| json!({"path": path, "etag": etag}))
+            .collect();
+        let failed: Vec<Value> = self.failed_attempts.iter()
+            .map(|((path, etag), attempts)| json!({"path": path, "etag": etag, "attempts": attempts}))
+            .collect();
+        let content = serde_json::to_vec_pretty(&json!({"processed": processed, "failed": failed}))?;
+
+        let mut temporary_path = self.state_path.clone().into_os_string();
+        temporary_path.push(".tmp");
+        tokio::fs::write(&temporary_path, content).await?;
+        tokio::fs::rename(&temporary_path, &self.state_path).await?;
+        Ok(())
+    }
+}
+
+
+fn key(object: &ListedObject) -> (String, String) {
+    (object.path.clone(), object.etag.clone())
+}
+
+fn parse_processed(state: &Value) -> anyhow::Result<BTreeSet<(String, String)>> {
+    let Some(entries) = state.get("processed").and_then(Value::as_array) else {
+        anyhow::bail!("Invalid watch state file, a processed array is expected");
+    };
+
+    entries.iter()
+        .map(|entry| match (entry.get("path").and_then(Value::as_str), entry.get("etag").and_then(Value::as_str)) {
+            (Some(path), Some(etag)) => Ok((path.to_owned(), etag.to_owned())),
+            _ => anyhow::bail!("Invalid watch state entry {entry}"),
+        })
+        .collect()
+}
+
+/// State files written before failed uploads were counted have no failed array
+fn parse_failed_attempts(state: &Value) -> anyhow::Result<BTreeMap<(String, String), u32>> {
+    let Some(entries) = state.get("failed") else {
+        return Ok(BTreeMap::new());
+    };
+    let Some(entries) = entries.as_array() else {
+        anyhow::bail!("Invalid watch state file, the failed entries must be an array");
+    };
+
+    entries.iter()
+        .map(|entry| {
+            let path = entry.get("path").and_then(Value::as_str);
+            let etag = entry.get("etag").and_then(Value::as_str);
+            let attempts = entry.get("attempts").and_then(Value::as_u64).and_then(|attempts| u32::try_from(attempts).ok());
+            match (path, etag, attempts) {
+                (Some(path), Some(etag), Some(attempts)) => Ok(((path.to_owned(), etag.to_owned()), attempts)),
+                _ => anyhow::bail!("Invalid watch state entry {entry}"),
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,104 @@
+mod common;
+
+use std::process::Output;
+
+use qdrant_client::qdrant::shard_key::Key;
+use qdrant_client::qdrant::vectors_config::Config;
+use qdrant_client::qdrant::{CreateCollection, Distance, ShardKey, ShardingMethod, SparseVectorConfig, SparseVectorParams,
+                            VectorParams, VectorsConfig};
+
+use common::{fixture, run_uploader, QdrantMock};
+
+const ALIAS: &str = "items";
+const PREVIOUS_COLLECTION: &str = "items_1";
+
+/// Runs the uploader binary on a fixture of items, with the given extra arguments
+async fn upload(qdrant_url: &str, fixture_name: &str, arguments: &[&str]) -> Output {
+    let source_path = fixture(fixture_name);
+    let mut all_arguments = vec!["--source-path", &source_path, "--source-file-type", "jsonl", "--connection-string", qdrant_url,
+                                 "--database-collection", ALIAS, "--batch-size", "2", "--id-field-name", "id", "--vector-field-name", "embedding"];
+    all_arguments.extend(arguments);
+    run_uploader(&all_arguments).await
+}
+
+fn shard_key(key: &str) -> ShardKey {
+    ShardKey { key: Some(Key::Keyword(key.to_owned())) }
+}
+
+/// A collection with custom sharding on the `category` of the items and a sparse vector, behind the alias
+fn custom_sharded_qdrant(qdrant: &QdrantMock) {
+    let vector_params = VectorParams { size: 3, distance: Distance::Dot as i32, ..Default::default() };
+    let sparse_vectors = SparseVectorConfig { map: [("keywords".to_owned(), SparseVectorParams::default())].into() };
+    let previous_collection = CreateCollection {
+        collection_name: PREVIOUS_COLLECTION.to_owned(),
+        vectors_config: Some(VectorsConfig { config: Some(Config::Params(vector_params)) }),
+        sparse_vectors_config: Some(sparse_vectors),
+        sharding_method: Some(ShardingMethod::Custom as i32),
+        shard_number: Some(2),
+        ..Default::default()
+    };
+    qdrant.add_collection(previous_collection, vec![shard_key("books"), shard_key("music")]);
+    qdrant.add_alias(ALIAS, PREVIOUS_COLLECTION);
+}
+
+
+#[tokio::test]
+async fn switches_alias_to_custom_sharded_copy() {
+    let (qdrant, qdrant_url) = QdrantMock::start().await;
+    custom_sharded_qdrant(&qdrant);
+
+    let output = upload(&qdrant_url, "items.jsonl", &["--blue-green", "--shard-key-field", "category"]).await;
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let target_collection = qdrant.alias(ALIAS).unwrap();
+    assert_ne!(target_collection, PREVIOUS_COLLECTION);
+    let create_collection = qdrant.collection(&target_collection).unwrap();
+    assert_eq!(create_collection.sharding_method, Some(ShardingMethod::Custom as i32));
+    assert_eq!(create_collection.shard_number, Some(2));
+    assert!(create_collection.sparse_vectors_config.unwrap().map.contains_key("keywords"));
+    assert_eq!(qdrant.shard_keys(&target_collection), vec![shard_key("books"), shard_key("music")]);
+
+    let upserts = qdrant.upserts();
+    assert!(upserts.iter().all(|upsert| upsert.collection_name == target_collection));
+    assert!(upserts.iter().all(|upsert| upsert.shard_key_selector.is_some()));
+    assert_eq!(qdrant.upserted_points().len(), 3);
+}
+
+#[tokio::test]
+async fn switches_alias_when_rows_repeat_an_id() {
+    let (qdrant, qdrant_url) = QdrantMock::start().await;
+    custom_sharded_qdrant(&qdrant);
+
+    let output = upload(&qdrant_url, "items_repeated_ids.jsonl", &["--blue-green", "--verify"]).await;
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    assert_ne!(qdrant.alias(ALIAS).unwrap(), PREVIOUS_COLLECTION);
+}
+
+#[tokio::test]
+async fn keeps_alias_when_points_are_missing_without_verify() {
+    let (qdrant, qdrant_url) = QdrantMock::start().await;
+    custom_sharded_qdrant(&qdrant);
+    qdrant.lose_points(1);
+
+    let output = upload(&qdrant_url, "items.jsonl", &["--blue-green", "--shard-key-field", "category"]).await;
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("distinct ids were uploaded"));
+    assert_eq!(qdrant.alias(ALIAS).unwrap(), PREVIOUS_COLLECTION);
+    assert_eq!(qdrant.collections(), vec![PREVIOUS_COLLECTION.to_owned()]);
+}
+
+#[tokio::test]
+async fn deletes_copy_when_restoring_indexing_fails() {
+    let (qdrant, qdrant_url) = QdrantMock::start().await;
+    custom_sharded_qdrant(&qdrant);
+    qdrant.reject_collection_updates_after(1);
+
+    let output = upload(&qdrant_url, "items.jsonl", &["--blue-green", "--defer-indexing", "--shard-key-field", "category"]).await;
+
+    assert_eq!(output.status.code(), Some(5));
+    assert_eq!(qdrant.upserted_points().len(), 3);
+    assert_eq!(qdrant.alias(ALIAS).unwrap(), PREVIOUS_COLLECTION);
+    assert_eq!(qdrant.collections(), vec![PREVIOUS_COLLECTION.to_owned()]);
+}
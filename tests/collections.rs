@@ -0,0 +1,170 @@
+mod common;
+
+use std::time::Duration;
+
+use qdrant_client::qdrant::payload_index_params::IndexParams;
+use qdrant_client::qdrant::quantization_config::Quantization;
+use qdrant_client::qdrant::vectors_config::Config;
+use qdrant_client::qdrant::{CollectionStatus, CreateCollection, FieldType, QuantizationType};
+use tonic::Code;
+use qdrant_uploader::persistence::{CollectionSettings, DatabaseClient, VectorDistance};
+use qdrant_uploader::persistence::vector_field_name::FieldName;
+
+use common::{fixture, run_uploader, QdrantMock, DEFAULT_INDEXING_THRESHOLD};
+
+const COLLECTION: &str = "items";
+
+/// Runs the uploader binary recreating the collection from `items.jsonl`, with the given extra arguments
+async fn upload_into_recreated_collection(qdrant_url: &str, arguments: &[&str]) -> std::process::Output {
+    let source_path = fixture("items.jsonl");
+    let mut all_arguments = vec!["--source-path", &source_path, "--source-file-type", "jsonl", "--connection-string", qdrant_url,
+                                 "--database-collection", COLLECTION, "--batch-size", "2", "--id-field-name", "id",
+                                 "--vector-field-name", "embedding", "--recreate-collection", "--vector-size", "3"];
+    all_arguments.extend(arguments);
+    run_uploader(&all_arguments).await
+}
+
+fn indexing_thresholds(qdrant: &QdrantMock) -> Vec<Option<u64>> {
+    qdrant.collection_updates().iter()
+        .map(|update| update.optimizers_config.and_then(|config| config.indexing_threshold))
+        .collect()
+}
+
+fn settings() -> CollectionSettings {
+    CollectionSettings {
+        vector_sizes: vec![3],
+        distance: Some(VectorDistance::Cosine),
+        ..CollectionSettings::default()
+    }
+}
+
+#[tokio::test]
+async fn creates_tenant_index_on_recreated_collection() {
+    let (qdrant, qdrant_url) = QdrantMock::start().await;
+    let database_client = DatabaseClient::new(&qdrant_url, &None, COLLECTION, 2).await.unwrap();
+
+    database_client.recreate_collection(&settings(), &FieldName::Single("embedding".to_owned())).await.unwrap();
+    database_client.create_tenant_index("tenant").await.unwrap();
+
+    assert!(qdrant.collection(COLLECTION).is_some());
+    let field_indexes = qdrant.field_indexes();
+    assert_eq!(field_indexes.len(), 1);
+    assert_eq!(field_indexes[0].collection_name, COLLECTION);
+    assert_eq!(field_indexes[0].field_name, "tenant");
+    assert_eq!(field_indexes[0].field_type, Some(FieldType::Keyword as i32));
+    match field_indexes[0].field_index_params.as_ref().and_then(|params| params.index_params.as_ref()) {
+        Some(IndexParams::KeywordIndexParams(params)) => assert_eq!(params.is_tenant, Some(true)),
+        other => panic!("unexpected index params {other:?}"),
+    }
+}
+
+/// Validates the tenant index of a collection with a payload index of the given type on the `tenant` field
+async fn validate_tenant_index(field_type: Option<FieldType>) -> anyhow::Result<()> {
+    let (qdrant, qdrant_url) = QdrantMock::start().await;
+    let database_client = DatabaseClient::new(&qdrant_url, &None, COLLECTION, 2).await.unwrap();
+    database_client.recreate_collection(&settings(), &FieldName::Single("embedding".to_owned())).await.unwrap();
+    if let Some(field_type) = field_type {
+        qdrant.add_field_index(COLLECTION, "tenant", field_type);
+    }
+
+    database_client.validate_payload_index("tenant").await
+}
+
+#[tokio::test]
+async fn accepts_keyword_and_uuid_tenant_indexes() {
+    validate_tenant_index(Some(FieldType::Keyword)).await.unwrap();
+    validate_tenant_index(Some(FieldType::Uuid)).await.unwrap();
+}
+
+#[tokio::test]
+async fn rejects_tenant_index_of_other_type() {
+    let error = validate_tenant_index(Some(FieldType::Integer)).await.unwrap_err();
+
+    assert!(error.to_string().contains("has a Integer payload index for the tenant field tenant"), "{error}");
+}
+
+#[tokio::test]
+async fn rejects_missing_tenant_index() {
+    let error = validate_tenant_index(None).await.unwrap_err();
+
+    assert!(error.to_string().contains("has no payload index for the tenant field tenant"), "{error}");
+}
+
+#[tokio::test]
+#[allow(deprecated)]
+async fn creates_collection_with_hnsw_quantization_and_on_disk_options() {
+    let (qdrant, qdrant_url) = QdrantMock::start().await;
+
+    let output = upload_into_recreated_collection(&qdrant_url, &["--hnsw-m", "32", "--hnsw-ef-construct", "200", "--on-disk-vectors",
+                                                                 "--quantization", "scalar", "--quantization-always-ram"]).await;
+    let collection = qdrant.collection(COLLECTION).unwrap();
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let hnsw_config = collection.hnsw_config.unwrap();
+    assert_eq!((hnsw_config.m, hnsw_config.ef_construct), (Some(32), Some(200)));
+    match collection.vectors_config.and_then(|vectors_config| vectors_config.config) {
+        Some(Config::ParamsMap(params_map)) => {
+            let params = &params_map.map["embedding"];
+            assert_eq!((params.size, params.on_disk), (3, Some(true)));
+        },
+        other => panic!("unexpected vectors config {other:?}"),
+    }
+    match collection.quantization_config.and_then(|quantization_config| quantization_config.quantization) {
+        Some(Quantization::Scalar(scalar)) => {
+            assert_eq!(scalar.r#type, QuantizationType::Int8 as i32);
+            assert_eq!(scalar.always_ram, Some(true));
+        },
+        other => panic!("unexpected quantization {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn restores_indexing_threshold_after_failed_deferred_upload() {
+    let (qdrant, qdrant_url) = QdrantMock::start().await;
+    qdrant.fail_upserts(&[Code::InvalidArgument]);
+
+    let output = upload_into_recreated_collection(&qdrant_url, &["--defer-indexing"]).await;
+
+    assert!(!output.status.success());
+    assert_eq!(indexing_thresholds(&qdrant), vec![Some(0), Some(DEFAULT_INDEXING_THRESHOLD)]);
+}
+
+#[tokio::test]
+async fn sets_given_indexing_threshold_after_deferred_upload() {
+    let (qdrant, qdrant_url) = QdrantMock::start().await;
+
+    let output = upload_into_recreated_collection(&qdrant_url, &["--defer-indexing", "--indexing-threshold", "5000"]).await;
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(indexing_thresholds(&qdrant), vec![Some(0), Some(5000)]);
+    assert_eq!(qdrant.upserted_points().len(), 3);
+}
+
+#[tokio::test]
+async fn reports_upload_and_restore_errors_of_failed_bulk_load() {
+    let (qdrant, qdrant_url) = QdrantMock::start().await;
+    qdrant.add_collection(CreateCollection { collection_name: COLLECTION.to_owned(), ..Default::default() }, Vec::new());
+    qdrant.fail_upserts(&[Code::InvalidArgument]);
+    qdrant.reject_collection_updates_after(1);
+
+    let source_path = fixture("items.jsonl");
+    let output = run_uploader(&["--source-path", &source_path, "--source-file-type", "jsonl", "--connection-string", &qdrant_url,
+                                "--database-collection", COLLECTION, "--batch-size", "2", "--id-field-name", "id",
+                                "--vector-field-name", "embedding", "--bulk-load"]).await;
+    let summary = String::from_utf8_lossy(&output.stdout);
+
+    assert_eq!(output.status.code(), Some(5));
+    assert!(summary.contains("collection update failure injected by the Qdrant mock"), "{summary}");
+    assert!(summary.contains("upsert failure injected by the Qdrant mock"), "{summary}");
+}
+
+#[tokio::test]
+async fn gives_up_waiting_for_green_after_timeout() {
+    let (qdrant, qdrant_url) = QdrantMock::start().await;
+    qdrant.set_collection_status(CollectionStatus::Yellow);
+    let database_client = DatabaseClient::new(&qdrant_url, &None, COLLECTION, 2).await.unwrap();
+
+    let error = database_client.wait_for_green(Duration::from_millis(10), Duration::from_millis(50)).await.unwrap_err();
+
+    assert_eq!(error.to_string(), format!("Collection {COLLECTION} is still Yellow after 50ms"));
+}
@@ -0,0 +1,911 @@
+//! Local stand-ins for the services the uploader talks to: an S3-compatible object store and the Qdrant gRPC points and
+//! collections APIs, optionally over TLS
+#![allow(dead_code)]
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::process::{Command, Output, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, StatusCode};
+use qdrant_client::qdrant::collections_server::{Collections, CollectionsServer};
+use qdrant_client::qdrant::points_server::{Points, PointsServer};
+use qdrant_client::qdrant::{
+    AliasDescription, ChangeAliases, CollectionClusterInfoRequest, CollectionClusterInfoResponse, CollectionConfig, CollectionExists,
+    CollectionExistsRequest, CollectionExistsResponse, CollectionInfo, CollectionOperationResponse, CollectionParams, CollectionStatus,
+    CreateCollection, CreateShardKeyRequest, OptimizersConfigDiff,
+    CreateShardKeyResponse, DeleteCollection, DeleteShardKeyRequest, DeleteShardKeyResponse, GetCollectionInfoRequest,
+    GetCollectionInfoResponse, ListAliasesRequest, ListAliasesResponse, ListCollectionAliasesRequest, ListCollectionsRequest,
+    ListCollectionsResponse, ListShardKeysRequest, ListShardKeysResponse, UpdateCollection, UpdateCollectionClusterSetupRequest,
+    ShardKey, ShardKeyDescription, UpdateCollectionClusterSetupResponse, UpdateQueueInfo, PayloadSchemaInfo,
+};
+use qdrant_client::qdrant::alias_operations::Action;
+use qdrant_client::qdrant::{
+    ClearPayloadPoints, CountPoints, CountResponse, CountResult, CreateFieldIndexCollection, CreateVectorNameRequest, FieldType, DeleteFieldIndexCollection,
+    DeletePayloadPoints, DeletePointVectors, DeletePoints, DeleteVectorNameRequest, DiscoverBatchPoints, DiscoverBatchResponse,
+    DiscoverPoints, DiscoverResponse, FacetCounts, FacetResponse, GetPoints, GetResponse, PointStruct, PointsOperationResponse,
+    QueryBatchPoints, QueryBatchResponse, QueryGroupsResponse, QueryPointGroups, QueryPoints, QueryResponse, RecommendBatchPoints,
+    RecommendBatchResponse, RecommendGroupsResponse, RecommendPointGroups, RecommendPoints, RecommendResponse, ScrollPoints,
+    ScrollResponse, SearchBatchPoints, SearchBatchResponse, SearchGroupsResponse, SearchMatrixOffsetsResponse, SearchMatrixPairsResponse,
+    SearchMatrixPoints, SearchPointGroups, SearchPoints, SearchResponse, SetPayloadPoints, UpdateBatchPoints, UpdateBatchResponse,
+    UpdatePointVectors, UpdateResult, UpdateStatus, UpsertPoints,
+};
+use qdrant_client::qdrant::{vector, vector_output, NamedVectorsOutput, RetrievedPoint, Vector, VectorOutput, VectorsOutput};
+use qdrant_client::qdrant::vectors::VectorsOptions;
+use qdrant_client::qdrant::vectors_output::VectorsOptions as OutputOptions;
+use qdrant_uploader::persistence::files_system::{Dataset, FileType, S3Options, S3Transport, TransportRegistry};
+use qdrant_uploader::persistence::vector_field_name::FieldName;
+use qdrant_uploader::persistence::DatabaseClient;
+use qdrant_uploader::{PointMapper, UploadJob};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_stream::wrappers::TcpListenerStream;
+use tonic::transport::{Certificate, Identity, ServerTlsConfig};
+use tonic::{Code, Status, async_trait};
+
+pub const BUCKET: &str = "datasets";
+
+/// Indexing threshold reported by [`QdrantMock`] for collections created without one
+pub const DEFAULT_INDEXING_THRESHOLD: u64 = 15000;
+/// ETag of the files served by the stand-ins
+pub const E_TAG: &str = "\"fixture\"";
+
+/// `Range` and `If-Match` headers of a GET
+type ObjectRequest = (Option<String>, Option<String>);
+
+pub fn fixture(name: &str) -> String {
+    format!("{manifest}/tests/data/{name}", manifest=env!("CARGO_MANIFEST_DIR"))
+}
+
+pub fn fixture_bytes(name: &str) -> Vec<u8> {
+    std::fs::read(fixture(name)).expect("fixture exists")
+}
+
+/// Runs the uploader binary with the given arguments
+pub async fn run_uploader(arguments: &[&str]) -> Output {
+    run(uploader_command(arguments)).await
+}
+
+pub fn uploader_command(arguments: &[&str]) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_qdrant-uploader"));
+    command.args(arguments);
+    command
+}
+
+/// Waits for the command without blocking the mocks it talks to
+pub async fn run(mut command: Command) -> Output {
+    tokio::task::spawn_blocking(move || command.output().unwrap()).await.unwrap()
+}
+
+/// Runs the command with the input written to its stdin, which is closed once written
+pub async fn run_with_stdin(mut command: Command, input: Vec<u8>) -> Output {
+    tokio::task::spawn_blocking(move || {
+        let mut child = command.stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn().unwrap();
+        let mut stdin = child.stdin.take().unwrap();
+        let writer = std::thread::spawn(move || std::io::Write::write_all(&mut stdin, &input).unwrap());
+        let output = child.wait_with_output().unwrap();
+        writer.join().unwrap();
+        output
+    }).await.unwrap()
+}
+
+/// Lets a command that runs until terminated, such as `--watch`, run for `duration`, then stops it with SIGTERM
+pub async fn run_for(mut command: Command, duration: Duration) -> Output {
+    let child = command.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn().unwrap();
+    tokio::time::sleep(duration).await;
+    let terminated = Command::new("kill").args(["-TERM", &child.id().to_string()]).status().unwrap();
+    assert!(terminated.success());
+    tokio::task::spawn_blocking(move || child.wait_with_output().unwrap()).await.unwrap()
+}
+
+/// Uploads the ids and vectors of the three rows of `items.jsonl` into the sink, returning the points written
+pub async fn upload_items(sink: DatabaseClient) -> anyhow::Result<u64> {
+    let dataset = Dataset::load(&fixture("items.jsonl"), &FileType::JSON, &TransportRegistry::default()).await?;
+    let point_mapper = PointMapper::builder().id_field("id").vector_field(FieldName::Single("embedding".to_owned())).build()?;
+
+    UploadJob::builder()
+        .source(dataset)
+        .point_mapper(point_mapper)
+        .sink(sink)
+        .build()?
+        .run()
+        .await
+}
+
+
+/// Serves objects with path-style requests: GETs honouring `Range` and `If-Match`, listings of a prefix two keys per
+/// page, copies, deletes and tagging. When `drop_after` is set, the first GET response is cut after that many bytes,
+/// the way a dropped connection would
+#[derive(Clone)]
+pub struct S3StandIn {
+    pub endpoint: String,
+    state: Arc<S3State>,
+}
+
+#[derive(Default)]
+struct S3State {
+    objects: Mutex<HashMap<String, Vec<u8>>>,
+    tags: Mutex<HashMap<String, Vec<(String, String)>>>,
+    requests: Mutex<Vec<ObjectRequest>>,
+    authorizations: Mutex<Vec<String>>,
+    drop_after: Mutex<Option<usize>>,
+}
+
+/// Keys returned by each page of a listing
+const LIST_PAGE_SIZE: usize = 2;
+
+impl S3StandIn {
+    pub async fn start(objects: HashMap<String, Vec<u8>>, drop_after: Option<usize>) -> S3StandIn {
+        let state = Arc::new(S3State { objects: Mutex::new(objects), drop_after: Mutex::new(drop_after), ..S3State::default() });
+
+        let service_state = state.clone();
+        let make_service = make_service_fn(move |_| {
+            let state = service_state.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| serve_s3(request, state.clone())))
+            }
+        });
+
+        let server = hyper::Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0))).serve(make_service);
+        let endpoint = format!("http://{address}", address=server.local_addr());
+        tokio::spawn(server);
+
+        S3StandIn { endpoint, state }
+    }
+
+    /// `Range` and `If-Match` headers of every object GET received
+    pub fn requests(&self) -> Vec<ObjectRequest> {
+        self.state.requests.lock().unwrap().clone()
+    }
+
+    /// `Authorization` header of every request received, naming the access key and region that signed it
+    pub fn authorizations(&self) -> Vec<String> {
+        self.state.authorizations.lock().unwrap().clone()
+    }
+
+    /// `bucket/key` of every object, sorted
+    pub fn objects(&self) -> Vec<String> {
+        let mut objects: Vec<String> = self.state.objects.lock().unwrap().keys().cloned().collect();
+        objects.sort();
+        objects
+    }
+
+    /// Tags last set on the `bucket/key` object
+    pub fn tags(&self, object: &str) -> Vec<(String, String)> {
+        self.state.tags.lock().unwrap().get(object).cloned().unwrap_or_default()
+    }
+
+    pub fn options(&self) -> S3Options {
+        S3Options {
+            access_key: Some("test".to_owned()),
+            secret_key: Some("test".to_owned()),
+            region: Some("us-east-1".to_owned()),
+            endpoint: Some(self.endpoint.clone()),
+            max_retries: 3,
+            ..S3Options::default()
+        }
+    }
+
+    pub async fn transports(&self) -> TransportRegistry {
+        let mut transports = TransportRegistry::default();
+        transports.register("s3", S3Transport::new(self.options()).await.expect("S3 transport"));
+        transports
+    }
+}
+
+
+async fn serve_s3(request: Request<Body>, state: Arc<S3State>) -> Result<Response<Body>, Infallible> {
+    let header = |name: &str| request.headers().get(name).and_then(|value| value.to_str().ok()).map(str::to_owned);
+    state.authorizations.lock().unwrap().extend(header("authorization"));
+
+    let path = request.uri().path().trim_matches('/').to_owned();
+    let query: HashMap<String, String> = request.uri().query().unwrap_or_default()
+        .split('&')
+        .filter_map(|parameter| parameter.split_once('=').or(Some((parameter, ""))))
+        .map(|(name, value)| (name.to_owned(), value.replace("%2F", "/")))
+        .collect();
+
+    let response = match (request.method().clone(), header("x-amz-copy-source")) {
+        (Method::GET, _) if query.get("list-type").map(String::as_str) == Some("2") => list_objects(&state, &path, &query),
+        (Method::GET, _) => return serve_object(&request, &state, &path),
+        (Method::PUT, Some(copy_source)) => copy_object(&state, &path, &copy_source, header("x-amz-copy-source-if-match")),
+        (Method::PUT, None) if query.contains_key("tagging") => {
+            let body = hyper::body::to_bytes(request.into_body()).await.unwrap();
+            tag_object(&state, &path, &String::from_utf8_lossy(&body))
+        },
+        (Method::DELETE, _) => {
+            state.objects.lock().unwrap().remove(&path);
+            Response::builder().status(StatusCode::NO_CONTENT).body(Body::empty()).unwrap()
+        },
+        _ => Response::builder().status(StatusCode::NOT_IMPLEMENTED).body(Body::empty()).unwrap(),
+    };
+    Ok(response)
+}
+
+/// Keys directly under the prefix, as with a `/` delimiter, the continuation token being the index of the next key
+fn list_objects(state: &S3State, bucket: &str, query: &HashMap<String, String>) -> Response<Body> {
+    let prefix = format!("{bucket}/{prefix}", prefix=query.get("prefix").map(String::as_str).unwrap_or_default());
+    let mut keys: Vec<String> = state.objects.lock().unwrap().keys()
+        .filter_map(|object| object.strip_prefix(&prefix).filter(|name| !name.contains('/')).map(|_| object[bucket.len() + 1..].to_owned()))
+        .collect();
+    keys.sort();
+
+    let start = query.get("continuation-token").and_then(|token| token.parse::<usize>().ok()).unwrap_or(0);
+    let end = keys.len().min(start + LIST_PAGE_SIZE);
+    let contents: String = keys[start..end].iter()
+        .map(|key| format!("<Contents><Key>{key}</Key><ETag>&quot;fixture&quot;</ETag></Contents>"))
+        .collect();
+    let continuation = if end < keys.len() {
+        format!("<IsTruncated>true</IsTruncated><NextContinuationToken>{end}</NextContinuationToken>")
+    } else {
+        "<IsTruncated>false</IsTruncated>".to_owned()
+    };
+
+    let body = format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?><ListBucketResult><Name>{bucket}</Name>{continuation}{contents}</ListBucketResult>");
+    Response::new(Body::from(body))
+}
+
+fn copy_object(state: &S3State, destination: &str, copy_source: &str, if_match: Option<String>) -> Response<Body> {
+    let mut objects = state.objects.lock().unwrap();
+    let Some(content) = objects.get(copy_source.trim_start_matches('/')).cloned() else {
+        return Response::builder().status(StatusCode::NOT_FOUND).body(Body::from("<Error><Code>NoSuchKey</Code></Error>")).unwrap();
+    };
+    if if_match.map(|e_tag| e_tag != E_TAG).unwrap_or(false) {
+        return Response::builder().status(StatusCode::PRECONDITION_FAILED).body(Body::empty()).unwrap();
+    }
+
+    objects.insert(destination.to_owned(), content);
+    Response::new(Body::from("<CopyObjectResult><ETag>&quot;fixture&quot;</ETag></CopyObjectResult>"))
+}
+
+fn tag_object(state: &S3State, object: &str, tagging: &str) -> Response<Body> {
+    let element = |tag: &str, name: &str| tag.split_once(&format!("<{name}>"))
+        .and_then(|(_, rest)| rest.split_once(&format!("</{name}>")))
+        .map(|(value, _)| value.to_owned())
+        .unwrap_or_default();
+    let tags = tagging.split("<Tag>").skip(1).map(|tag| (element(tag, "Key"), element(tag, "Value"))).collect();
+
+    state.tags.lock().unwrap().insert(object.to_owned(), tags);
+    Response::new(Body::empty())
+}
+
+fn serve_object(request: &Request<Body>, state: &S3State, path: &str) -> Result<Response<Body>, Infallible> {
+    let header = |name: &str| request.headers().get(name).and_then(|value| value.to_str().ok()).map(str::to_owned);
+    let (range, if_match) = (header("range"), header("if-match"));
+    state.requests.lock().unwrap().push((range.clone(), if_match.clone()));
+
+    let Some(object) = state.objects.lock().unwrap().get(path).cloned() else {
+        return Ok(Response::builder().status(StatusCode::NOT_FOUND).body(Body::from("<Error><Code>NoSuchKey</Code></Error>")).unwrap());
+    };
+
+    if if_match.as_deref().map(|e_tag| e_tag != E_TAG).unwrap_or(false) {
+        return Ok(Response::builder().status(StatusCode::PRECONDITION_FAILED).body(Body::empty()).unwrap());
+    }
+
+    let start = range.as_deref()
+        .and_then(|range| range.strip_prefix("bytes="))
+        .and_then(|range| range.trim_end_matches('-').parse::<usize>().ok())
+        .unwrap_or(0);
+    let content = object[start..].to_vec();
+
+    let mut response = Response::builder()
+        .header("ETag", E_TAG)
+        .header("Content-Length", content.len());
+    response = if range.is_some() {
+        response.status(StatusCode::PARTIAL_CONTENT)
+            .header("Content-Range", format!("bytes {start}-{end}/{total}", end=object.len() - 1, total=object.len()))
+    } else {
+        response.status(StatusCode::OK)
+    };
+
+    let Some(cut) = state.drop_after.lock().unwrap().take() else {
+        return Ok(response.body(Body::from(content)).unwrap());
+    };
+    Ok(response.body(dropped_body(content, cut)).unwrap())
+}
+
+/// A body sending the first `cut` bytes of `content`, then dropping the connection
+fn dropped_body(content: Vec<u8>, cut: usize) -> Body {
+    let (mut sender, body) = Body::channel();
+    tokio::spawn(async move {
+        let _ = sender.send_data(content[..cut].to_vec().into()).await;
+        // Let the first bytes reach the client before the connection is dropped
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        sender.abort();
+    });
+    body
+}
+
+
+/// Serves `/items.jsonl` over plain HTTP, honouring `Range` with `If-Range`, and redirects `/redirect/<n>` to it
+/// through `n` redirects, and `/moved?location=<url>` to any URL. When `drop_after` is set, the first response is cut after that many bytes. When
+/// `replaced` is set, the file gets another ETag after the first response, as if it was written again
+#[derive(Clone)]
+pub struct HttpStandIn {
+    pub url: String,
+    requests: Arc<Mutex<Vec<HttpRequest>>>,
+}
+
+/// Path and headers of a GET
+#[derive(Debug, Clone)]
+pub struct HttpRequest {
+    pub path: String,
+    pub headers: hyper::HeaderMap,
+}
+
+impl HttpRequest {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).and_then(|value| value.to_str().ok())
+    }
+}
+
+impl HttpStandIn {
+    pub async fn start(drop_after: Option<usize>, replaced: bool) -> HttpStandIn {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let drop_after = Arc::new(Mutex::new(drop_after));
+        let responses = Arc::new(Mutex::new(0));
+
+        let service_requests = requests.clone();
+        let make_service = make_service_fn(move |_| {
+            let (requests, drop_after, responses) = (service_requests.clone(), drop_after.clone(), responses.clone());
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    serve_http(request, requests.clone(), drop_after.clone(), responses.clone(), replaced)
+                }))
+            }
+        });
+
+        let server = hyper::Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0))).serve(make_service);
+        let url = format!("http://{address}", address=server.local_addr());
+        tokio::spawn(server);
+
+        HttpStandIn { url, requests }
+    }
+
+    pub fn requests(&self) -> Vec<HttpRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+
+async fn serve_http(request: Request<Body>, requests: Arc<Mutex<Vec<HttpRequest>>>, drop_after: Arc<Mutex<Option<usize>>>,
+                    responses: Arc<Mutex<usize>>, replaced: bool) -> Result<Response<Body>, Infallible> {
+    let path = request.uri().path().to_owned();
+    requests.lock().unwrap().push(HttpRequest { path: path.clone(), headers: request.headers().clone() });
+
+    if let Some(hops) = path.strip_prefix("/redirect/").and_then(|hops| hops.parse::<usize>().ok()) {
+        let location = if hops > 1 { format!("/redirect/{next}", next=hops - 1) } else { "/items.jsonl".to_owned() };
+        return Ok(Response::builder().status(StatusCode::FOUND).header("Location", location).body(Body::empty()).unwrap());
+    }
+    if let Some(location) = request.uri().query().and_then(|query| query.strip_prefix("location=")).filter(|_| path == "/moved") {
+        return Ok(Response::builder().status(StatusCode::FOUND).header("Location", location).body(Body::empty()).unwrap());
+    }
+    if path != "/items.jsonl" {
+        return Ok(Response::builder().status(StatusCode::NOT_FOUND).body(Body::empty()).unwrap());
+    }
+
+    let e_tag = {
+        let mut responses = responses.lock().unwrap();
+        *responses += 1;
+        if replaced && *responses > 1 { "\"replaced\"" } else { E_TAG }
+    };
+
+    // A range is only served while the file still has the entity tag given in If-Range
+    let header = |name: &str| request.headers().get(name).and_then(|value| value.to_str().ok());
+    let start = header("range")
+        .filter(|_| header("if-range").map(|if_range| if_range == e_tag).unwrap_or(true))
+        .and_then(|range| range.strip_prefix("bytes="))
+        .and_then(|range| range.trim_end_matches('-').parse::<usize>().ok());
+
+    let object = fixture_bytes("items.jsonl");
+    let content = object[start.unwrap_or(0)..].to_vec();
+    let mut response = Response::builder()
+        .header("ETag", e_tag)
+        .header("Content-Length", content.len());
+    response = match start {
+        Some(start) => response.status(StatusCode::PARTIAL_CONTENT)
+            .header("Content-Range", format!("bytes {start}-{end}/{total}", end=object.len() - 1, total=object.len())),
+        None => response.status(StatusCode::OK)
+    };
+
+    let Some(cut) = drop_after.lock().unwrap().take() else {
+        return Ok(response.body(Body::from(content)).unwrap());
+    };
+    Ok(response.body(dropped_body(content, cut)).unwrap())
+}
+
+
+/// OpenAI-compatible `POST /embeddings` answering every text with `[its length]`, with the `data` entries in reverse
+/// order. `missing_embeddings` entries are left out of every response
+#[derive(Clone)]
+pub struct EmbeddingsStandIn {
+    pub endpoint: String,
+    requests: Arc<Mutex<Vec<Vec<String>>>>,
+}
+
+impl EmbeddingsStandIn {
+    pub async fn start(missing_embeddings: usize) -> EmbeddingsStandIn {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let service_requests = requests.clone();
+        let make_service = make_service_fn(move |_| {
+            let requests = service_requests.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| serve_embeddings(request, requests.clone(), missing_embeddings)))
+            }
+        });
+
+        let server = hyper::Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0))).serve(make_service);
+        let endpoint = format!("http://{address}", address=server.local_addr());
+        tokio::spawn(server);
+
+        EmbeddingsStandIn { endpoint, requests }
+    }
+
+    /// Input texts of every request received
+    pub fn requests(&self) -> Vec<Vec<String>> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+
+async fn serve_embeddings(request: Request<Body>, requests: Arc<Mutex<Vec<Vec<String>>>>, missing_embeddings: usize)
+                          -> Result<Response<Body>, Infallible> {
+    if request.method() != Method::POST || request.uri().path() != "/embeddings" {
+        return Ok(Response::builder().status(StatusCode::NOT_FOUND).body(Body::empty()).unwrap());
+    }
+
+    let body = hyper::body::to_bytes(request.into_body()).await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let texts: Vec<String> = body["input"].as_array().unwrap().iter().map(|text| text.as_str().unwrap().to_owned()).collect();
+    requests.lock().unwrap().push(texts.clone());
+
+    let data: Vec<serde_json::Value> = texts.iter().enumerate().rev()
+        .skip(missing_embeddings)
+        .map(|(index, text)| serde_json::json!({"index": index, "embedding": [text.len() as f32]}))
+        .collect();
+    let response = serde_json::json!({"object": "list", "data": data});
+    Ok(Response::new(Body::from(response.to_string())))
+}
+
+
+/// Qdrant points and collections services recording every upsert, delete, payload index, collection, shard key and
+/// alias created, every other call is rejected. Upserts can be made to fail or to take longer
+#[derive(Clone, Default)]
+pub struct QdrantMock {
+    upserts: Arc<Mutex<Vec<UpsertPoints>>>,
+    failed_upserts: Arc<Mutex<Vec<UpsertPoints>>>,
+    upsert_failures: Arc<Mutex<VecDeque<Code>>>,
+    upsert_delay: Arc<Mutex<Duration>>,
+    update_queue_lengths: Arc<Mutex<VecDeque<u64>>>,
+    collection_info_requests: Arc<Mutex<usize>>,
+    deletes: Arc<Mutex<Vec<DeletePoints>>>,
+    field_indexes: Arc<Mutex<Vec<CreateFieldIndexCollection>>>,
+    collections: Arc<Mutex<HashMap<String, CreateCollection>>>,
+    shard_keys: Arc<Mutex<HashMap<String, Vec<ShardKey>>>>,
+    aliases: Arc<Mutex<HashMap<String, String>>>,
+    collection_updates: Arc<Mutex<Vec<UpdateCollection>>>,
+    accepted_collection_updates: Arc<Mutex<Option<usize>>>,
+    collection_status: Arc<Mutex<Option<CollectionStatus>>>,
+    lost_points: Arc<Mutex<u64>>,
+}
+
+impl QdrantMock {
+    /// Returns the mock and its gRPC url
+    pub async fn start() -> (QdrantMock, String) {
+        QdrantMock::serve(None).await
+    }
+
+    /// Serves over TLS with the `tls/server.pem` certificate, issued for `qdrant.test`, and requires a client certificate
+    /// issued by `tls/ca.pem`. Returns the mock and its `https://` url
+    pub async fn start_tls() -> (QdrantMock, String) {
+        let tls_config = ServerTlsConfig::new()
+            .identity(Identity::from_pem(fixture_bytes("tls/server.pem"), fixture_bytes("tls/server.key")))
+            .client_ca_root(Certificate::from_pem(fixture_bytes("tls/ca.pem")));
+        QdrantMock::serve(Some(tls_config)).await
+    }
+
+    async fn serve(tls_config: Option<ServerTlsConfig>) -> (QdrantMock, String) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind mock Qdrant");
+        let scheme = if tls_config.is_some() { "https" } else { "http" };
+        let url = format!("{scheme}://{address}", address=listener.local_addr().unwrap());
+        let mock = QdrantMock::default();
+
+        let mut server_builder = tonic::transport::Server::builder();
+        if let Some(tls_config) = tls_config {
+            server_builder = server_builder.tls_config(tls_config).expect("mock Qdrant TLS settings");
+        }
+        let server = server_builder
+            .add_service(PointsServer::new(mock.clone()))
+            .add_service(CollectionsServer::new(mock.clone()))
+            .serve_with_incoming(TcpListenerStream::new(listener));
+        tokio::spawn(server);
+
+        (mock, url)
+    }
+
+    /// The next upserts fail with these status codes, one each, and are not recorded by [`QdrantMock::upserts`]
+    pub fn fail_upserts(&self, codes: &[Code]) {
+        self.upsert_failures.lock().unwrap().extend(codes);
+    }
+
+    /// Every upsert answers after `delay`
+    pub fn delay_upserts(&self, delay: Duration) {
+        *self.upsert_delay.lock().unwrap() = delay;
+    }
+
+    /// Lengths of the update queue reported by the next collection info requests, one each, then 0
+    pub fn queue_updates(&self, lengths: &[u64]) {
+        self.update_queue_lengths.lock().unwrap().extend(lengths);
+    }
+
+    pub fn collection_info_requests(&self) -> usize {
+        *self.collection_info_requests.lock().unwrap()
+    }
+
+    /// Upserts that succeeded
+    pub fn upserts(&self) -> Vec<UpsertPoints> {
+        self.upserts.lock().unwrap().clone()
+    }
+
+    pub fn failed_upserts(&self) -> Vec<UpsertPoints> {
+        self.failed_upserts.lock().unwrap().clone()
+    }
+
+    pub fn upserted_points(&self) -> Vec<PointStruct> {
+        self.upserts().into_iter().flat_map(|upsert| upsert.points).collect()
+    }
+
+    pub fn deletes(&self) -> Vec<DeletePoints> {
+        self.deletes.lock().unwrap().clone()
+    }
+
+    pub fn field_indexes(&self) -> Vec<CreateFieldIndexCollection> {
+        self.field_indexes.lock().unwrap().clone()
+    }
+
+    pub fn collection(&self, collection_name: &str) -> Option<CreateCollection> {
+        self.collections.lock().unwrap().get(collection_name).cloned()
+    }
+
+    pub fn collections(&self) -> Vec<String> {
+        let mut collection_names: Vec<String> = self.collections.lock().unwrap().keys().cloned().collect();
+        collection_names.sort();
+        collection_names
+    }
+
+    /// Adds a collection, with its shard keys when it uses custom sharding, as if it had been created beforehand
+    pub fn add_collection(&self, create_collection: CreateCollection, shard_keys: Vec<ShardKey>) {
+        self.shard_keys.lock().unwrap().insert(create_collection.collection_name.clone(), shard_keys);
+        self.collections.lock().unwrap().insert(create_collection.collection_name.clone(), create_collection);
+    }
+
+    /// Adds a payload index of the given type, as if it had been created before the upload
+    pub fn add_field_index(&self, collection_name: &str, field_name: &str, field_type: FieldType) {
+        let create_field_index = CreateFieldIndexCollection {
+            collection_name: collection_name.to_owned(),
+            field_name: field_name.to_owned(),
+            field_type: Some(field_type as i32),
+            ..Default::default()
+        };
+        self.field_indexes.lock().unwrap().push(create_field_index);
+    }
+
+    pub fn shard_keys(&self, collection_name: &str) -> Vec<ShardKey> {
+        self.shard_keys.lock().unwrap().get(collection_name).cloned().unwrap_or_default()
+    }
+
+    pub fn collection_updates(&self) -> Vec<UpdateCollection> {
+        self.collection_updates.lock().unwrap().clone()
+    }
+
+    /// Collection updates after the first `accepted` fail as if Qdrant were unavailable
+    pub fn reject_collection_updates_after(&self, accepted: usize) {
+        *self.accepted_collection_updates.lock().unwrap() = Some(accepted);
+    }
+
+    /// Status reported for every collection, green if not set
+    pub fn set_collection_status(&self, status: CollectionStatus) {
+        *self.collection_status.lock().unwrap() = Some(status);
+    }
+
+    /// Counts report this many points fewer than were upserted, as if Qdrant had lost them
+    pub fn lose_points(&self, lost_points: u64) {
+        *self.lost_points.lock().unwrap() = lost_points;
+    }
+
+    pub fn add_alias(&self, alias_name: &str, collection_name: &str) {
+        self.aliases.lock().unwrap().insert(alias_name.to_owned(), collection_name.to_owned());
+    }
+
+    pub fn alias(&self, alias_name: &str) -> Option<String> {
+        self.aliases.lock().unwrap().get(alias_name).cloned()
+    }
+}
+
+
+type RpcResult<T> = Result<tonic::Response<T>, Status>;
+
+fn completed() -> RpcResult<PointsOperationResponse> {
+    let result = UpdateResult { status: UpdateStatus::Completed as i32, ..Default::default() };
+    Ok(tonic::Response::new(PointsOperationResponse { result: Some(result), ..Default::default() }))
+}
+
+fn vectors_output(vectors: VectorsOptions) -> VectorsOutput {
+    let vector_output = |vector: Vector| VectorOutput {
+        vector: match vector.vector {
+            Some(vector::Vector::Dense(dense)) => Some(vector_output::Vector::Dense(dense)),
+            _ => None,
+        },
+        ..Default::default()
+    };
+
+    let vectors_options = match vectors {
+        VectorsOptions::Vector(vector) => OutputOptions::Vector(vector_output(vector)),
+        VectorsOptions::Vectors(named) => OutputOptions::Vectors(NamedVectorsOutput {
+            vectors: named.vectors.into_iter().map(|(name, vector)| (name, vector_output(vector))).collect(),
+        }),
+    };
+    VectorsOutput { vectors_options: Some(vectors_options) }
+}
+
+fn unimplemented<T>() -> RpcResult<T> {
+    Err(Status::unimplemented("not supported by the Qdrant mock"))
+}
+
+#[async_trait]
+impl Points for QdrantMock {
+
+    async fn upsert(&self, request: tonic::Request<UpsertPoints>) -> RpcResult<PointsOperationResponse> {
+        let delay = *self.upsert_delay.lock().unwrap();
+        tokio::time::sleep(delay).await;
+
+        let failure = self.upsert_failures.lock().unwrap().pop_front();
+        if let Some(code) = failure {
+            self.failed_upserts.lock().unwrap().push(request.into_inner());
+            return Err(Status::new(code, "upsert failure injected by the Qdrant mock"));
+        }
+        self.upserts.lock().unwrap().push(request.into_inner());
+        completed()
+    }
+
+    async fn delete(&self, request: tonic::Request<DeletePoints>) -> RpcResult<PointsOperationResponse> {
+        self.deletes.lock().unwrap().push(request.into_inner());
+        completed()
+    }
+
+    /// Returns the last upserted version of each point, with dense vectors only
+    async fn get(&self, request: tonic::Request<GetPoints>) -> RpcResult<GetResponse> {
+        let request = request.into_inner();
+        let mut points = HashMap::new();
+        for point in self.upserts().into_iter().filter(|upsert| upsert.collection_name == request.collection_name).flat_map(|upsert| upsert.points) {
+            points.insert(format!("{:?}", point.id), point);
+        }
+
+        let result = request.ids.into_iter()
+            .filter_map(|id| points.remove(&format!("{:?}", Some(id))))
+            .map(|point| RetrievedPoint {
+                id: point.id,
+                payload: point.payload,
+                vectors: point.vectors.and_then(|vectors| vectors.vectors_options).map(vectors_output),
+                ..Default::default()
+            })
+            .collect();
+        Ok(tonic::Response::new(GetResponse { result, ..Default::default() }))
+    }
+    async fn update_vectors(&self, _request: tonic::Request<UpdatePointVectors>) -> RpcResult<PointsOperationResponse> { unimplemented() }
+    async fn delete_vectors(&self, _request: tonic::Request<DeletePointVectors>) -> RpcResult<PointsOperationResponse> { unimplemented() }
+    async fn set_payload(&self, _request: tonic::Request<SetPayloadPoints>) -> RpcResult<PointsOperationResponse> { unimplemented() }
+    async fn overwrite_payload(&self, _request: tonic::Request<SetPayloadPoints>) -> RpcResult<PointsOperationResponse> { unimplemented() }
+    async fn delete_payload(&self, _request: tonic::Request<DeletePayloadPoints>) -> RpcResult<PointsOperationResponse> { unimplemented() }
+    async fn clear_payload(&self, _request: tonic::Request<ClearPayloadPoints>) -> RpcResult<PointsOperationResponse> { unimplemented() }
+    async fn create_field_index(&self, request: tonic::Request<CreateFieldIndexCollection>) -> RpcResult<PointsOperationResponse> {
+        self.field_indexes.lock().unwrap().push(request.into_inner());
+        completed()
+    }
+    async fn delete_field_index(&self, _request: tonic::Request<DeleteFieldIndexCollection>) -> RpcResult<PointsOperationResponse> { unimplemented() }
+    async fn create_vector_name(&self, _request: tonic::Request<CreateVectorNameRequest>) -> RpcResult<PointsOperationResponse> { unimplemented() }
+    async fn delete_vector_name(&self, _request: tonic::Request<DeleteVectorNameRequest>) -> RpcResult<PointsOperationResponse> { unimplemented() }
+    async fn search(&self, _request: tonic::Request<SearchPoints>) -> RpcResult<SearchResponse> { unimplemented() }
+    async fn search_batch(&self, _request: tonic::Request<SearchBatchPoints>) -> RpcResult<SearchBatchResponse> { unimplemented() }
+    async fn search_groups(&self, _request: tonic::Request<SearchPointGroups>) -> RpcResult<SearchGroupsResponse> { unimplemented() }
+    async fn scroll(&self, _request: tonic::Request<ScrollPoints>) -> RpcResult<ScrollResponse> { unimplemented() }
+    async fn recommend(&self, _request: tonic::Request<RecommendPoints>) -> RpcResult<RecommendResponse> { unimplemented() }
+    async fn recommend_batch(&self, _request: tonic::Request<RecommendBatchPoints>) -> RpcResult<RecommendBatchResponse> { unimplemented() }
+    async fn recommend_groups(&self, _request: tonic::Request<RecommendPointGroups>) -> RpcResult<RecommendGroupsResponse> { unimplemented() }
+    async fn discover(&self, _request: tonic::Request<DiscoverPoints>) -> RpcResult<DiscoverResponse> { unimplemented() }
+    async fn discover_batch(&self, _request: tonic::Request<DiscoverBatchPoints>) -> RpcResult<DiscoverBatchResponse> { unimplemented() }
+    async fn count(&self, request: tonic::Request<CountPoints>) -> RpcResult<CountResponse> {
+        let collection_name = request.into_inner().collection_name;
+        let point_ids = self.upserts().into_iter()
+            .filter(|upsert| upsert.collection_name == collection_name)
+            .flat_map(|upsert| upsert.points)
+            .map(|point| format!("{:?}", point.id))
+            .collect::<HashSet<_>>();
+        let count = (point_ids.len() as u64).saturating_sub(*self.lost_points.lock().unwrap());
+        Ok(tonic::Response::new(CountResponse { result: Some(CountResult { count }), ..Default::default() }))
+    }
+    async fn update_batch(&self, _request: tonic::Request<UpdateBatchPoints>) -> RpcResult<UpdateBatchResponse> { unimplemented() }
+    async fn query(&self, _request: tonic::Request<QueryPoints>) -> RpcResult<QueryResponse> { unimplemented() }
+    async fn query_batch(&self, _request: tonic::Request<QueryBatchPoints>) -> RpcResult<QueryBatchResponse> { unimplemented() }
+    async fn query_groups(&self, _request: tonic::Request<QueryPointGroups>) -> RpcResult<QueryGroupsResponse> { unimplemented() }
+    async fn facet(&self, _request: tonic::Request<FacetCounts>) -> RpcResult<FacetResponse> { unimplemented() }
+    async fn search_matrix_pairs(&self, _request: tonic::Request<SearchMatrixPoints>) -> RpcResult<SearchMatrixPairsResponse> { unimplemented() }
+    async fn search_matrix_offsets(&self, _request: tonic::Request<SearchMatrixPoints>) -> RpcResult<SearchMatrixOffsetsResponse> { unimplemented() }
+}
+
+fn collection_operation_completed() -> RpcResult<CollectionOperationResponse> {
+    Ok(tonic::Response::new(CollectionOperationResponse { result: true, ..Default::default() }))
+}
+
+#[async_trait]
+impl Collections for QdrantMock {
+
+    async fn create(&self, request: tonic::Request<CreateCollection>) -> RpcResult<CollectionOperationResponse> {
+        let create_collection = request.into_inner();
+        self.collections.lock().unwrap().insert(create_collection.collection_name.clone(), create_collection);
+        collection_operation_completed()
+    }
+
+    async fn delete(&self, request: tonic::Request<DeleteCollection>) -> RpcResult<CollectionOperationResponse> {
+        self.collections.lock().unwrap().remove(&request.into_inner().collection_name);
+        collection_operation_completed()
+    }
+
+    async fn collection_exists(&self, request: tonic::Request<CollectionExistsRequest>) -> RpcResult<CollectionExistsResponse> {
+        let exists = self.collections.lock().unwrap().contains_key(&request.into_inner().collection_name);
+        Ok(tonic::Response::new(CollectionExistsResponse { result: Some(CollectionExists { exists }), ..Default::default() }))
+    }
+
+    /// Reports the configuration the collection was created with, if it was, and the payload indexes created on it
+    async fn get(&self, request: tonic::Request<GetCollectionInfoRequest>) -> RpcResult<GetCollectionInfoResponse> {
+        *self.collection_info_requests.lock().unwrap() += 1;
+        let length = self.update_queue_lengths.lock().unwrap().pop_front().unwrap_or_default();
+        let collection_name = request.into_inner().collection_name;
+        // Payload schema types follow the field types shifted by one, past the unknown type
+        let payload_schema = self.field_indexes.lock().unwrap().iter()
+            .filter(|field_index| field_index.collection_name == collection_name)
+            .map(|field_index| (field_index.field_name.clone(), PayloadSchemaInfo {
+                data_type: field_index.field_type.unwrap_or_default() + 1,
+                params: field_index.field_index_params.clone(),
+                points: None,
+            }))
+            .collect();
+        let config = self.collection(&collection_name).map(|collection| CollectionConfig {
+            params: Some(CollectionParams {
+                shard_number: collection.shard_number.unwrap_or(1),
+                vectors_config: collection.vectors_config,
+                replication_factor: collection.replication_factor,
+                sharding_method: collection.sharding_method,
+                sparse_vectors_config: collection.sparse_vectors_config,
+                ..Default::default()
+            }),
+            optimizer_config: Some(OptimizersConfigDiff {
+                indexing_threshold: collection.optimizers_config.and_then(|config| config.indexing_threshold).or(Some(DEFAULT_INDEXING_THRESHOLD)),
+                ..collection.optimizers_config.unwrap_or_default()
+            }),
+            ..Default::default()
+        });
+        let status = self.collection_status.lock().unwrap().unwrap_or(CollectionStatus::Green);
+        let collection_info = CollectionInfo {
+            status: status as i32,
+            config,
+            update_queue: Some(UpdateQueueInfo { length, deferred_points: None }),
+            payload_schema,
+            ..Default::default()
+        };
+        Ok(tonic::Response::new(GetCollectionInfoResponse { result: Some(collection_info), ..Default::default() }))
+    }
+    async fn list(&self, _request: tonic::Request<ListCollectionsRequest>) -> RpcResult<ListCollectionsResponse> { unimplemented() }
+    /// Only the indexing threshold is applied to the collection
+    async fn update(&self, request: tonic::Request<UpdateCollection>) -> RpcResult<CollectionOperationResponse> {
+        let update_collection = request.into_inner();
+        let updates = self.collection_updates.lock().unwrap().len();
+        if self.accepted_collection_updates.lock().unwrap().is_some_and(|accepted| updates >= accepted) {
+            return Err(Status::unavailable("collection update failure injected by the Qdrant mock"));
+        }
+        let indexing_threshold = update_collection.optimizers_config.and_then(|config| config.indexing_threshold);
+        if let Some(collection) = self.collections.lock().unwrap().get_mut(&update_collection.collection_name) {
+            let optimizers_config = collection.optimizers_config.get_or_insert_with(OptimizersConfigDiff::default);
+            optimizers_config.indexing_threshold = indexing_threshold.or(optimizers_config.indexing_threshold);
+        }
+        self.collection_updates.lock().unwrap().push(update_collection);
+        collection_operation_completed()
+    }
+    async fn update_aliases(&self, request: tonic::Request<ChangeAliases>) -> RpcResult<CollectionOperationResponse> {
+        let mut aliases = self.aliases.lock().unwrap();
+        for action in request.into_inner().actions.into_iter().filter_map(|operation| operation.action) {
+            match action {
+                Action::CreateAlias(create_alias) => { aliases.insert(create_alias.alias_name, create_alias.collection_name); },
+                Action::DeleteAlias(delete_alias) => { aliases.remove(&delete_alias.alias_name); },
+                Action::RenameAlias(_) => return unimplemented(),
+            }
+        }
+        collection_operation_completed()
+    }
+    async fn list_collection_aliases(&self, _request: tonic::Request<ListCollectionAliasesRequest>) -> RpcResult<ListAliasesResponse> { unimplemented() }
+    async fn list_aliases(&self, _request: tonic::Request<ListAliasesRequest>) -> RpcResult<ListAliasesResponse> {
+        let aliases = self.aliases.lock().unwrap().iter()
+            .map(|(alias_name, collection_name)| AliasDescription { alias_name: alias_name.clone(), collection_name: collection_name.clone() })
+            .collect();
+        Ok(tonic::Response::new(ListAliasesResponse { aliases, ..Default::default() }))
+    }
+    async fn collection_cluster_info(&self, _request: tonic::Request<CollectionClusterInfoRequest>) -> RpcResult<CollectionClusterInfoResponse> { unimplemented() }
+    async fn update_collection_cluster_setup(&self, _request: tonic::Request<UpdateCollectionClusterSetupRequest>) -> RpcResult<UpdateCollectionClusterSetupResponse> { unimplemented() }
+    async fn create_shard_key(&self, request: tonic::Request<CreateShardKeyRequest>) -> RpcResult<CreateShardKeyResponse> {
+        let request = request.into_inner();
+        let shard_key = request.request.and_then(|create_shard_key| create_shard_key.shard_key)
+            .ok_or_else(|| Status::invalid_argument("missing shard key"))?;
+        self.shard_keys.lock().unwrap().entry(request.collection_name).or_default().push(shard_key);
+        Ok(tonic::Response::new(CreateShardKeyResponse { result: true, ..Default::default() }))
+    }
+    async fn delete_shard_key(&self, _request: tonic::Request<DeleteShardKeyRequest>) -> RpcResult<DeleteShardKeyResponse> { unimplemented() }
+    async fn list_shard_keys(&self, request: tonic::Request<ListShardKeysRequest>) -> RpcResult<ListShardKeysResponse> {
+        let shard_keys = self.shard_keys(&request.into_inner().collection_name).into_iter()
+            .map(|key| ShardKeyDescription { key: Some(key) })
+            .collect();
+        Ok(tonic::Response::new(ListShardKeysResponse { shard_keys, ..Default::default() }))
+    }
+}
+
+
+/// Forwards plain HTTP/2 connections to a server, counting the PING frames sent by the client (not their acks)
+pub struct Http2PingCounter {
+    pub url: String,
+    pings: Arc<AtomicUsize>,
+}
+
+const HTTP2_PREFACE_LENGTH: usize = 24;
+const HTTP2_FRAME_HEADER_LENGTH: usize = 9;
+const HTTP2_PING_FRAME: u8 = 0x6;
+const HTTP2_ACK_FLAG: u8 = 0x1;
+
+impl Http2PingCounter {
+    /// `server_url` is an `http://` url
+    pub async fn start(server_url: &str) -> Http2PingCounter {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind HTTP/2 proxy");
+        let url = format!("http://{address}", address=listener.local_addr().unwrap());
+        let server_address = server_url.trim_start_matches("http://").to_owned();
+        let pings = Arc::new(AtomicUsize::new(0));
+
+        let proxy_pings = pings.clone();
+        tokio::spawn(async move {
+            while let Ok((client, _)) = listener.accept().await {
+                let server = TcpStream::connect(&server_address).await.expect("connect to the proxied server");
+                tokio::spawn(forward_http2(client, server, proxy_pings.clone()));
+            }
+        });
+
+        Http2PingCounter { url, pings }
+    }
+
+    pub fn pings(&self) -> usize {
+        self.pings.load(Ordering::SeqCst)
+    }
+}
+
+async fn forward_http2(client: TcpStream, server: TcpStream, pings: Arc<AtomicUsize>) -> std::io::Result<()> {
+    let (mut client_reader, mut client_writer) = client.into_split();
+    let (mut server_reader, mut server_writer) = server.into_split();
+    tokio::spawn(async move { tokio::io::copy(&mut server_reader, &mut client_writer).await });
+
+    let mut preface = [0; HTTP2_PREFACE_LENGTH];
+    client_reader.read_exact(&mut preface).await?;
+    server_writer.write_all(&preface).await?;
+    loop {
+        let mut header = [0; HTTP2_FRAME_HEADER_LENGTH];
+        client_reader.read_exact(&mut header).await?;
+        let mut payload = vec![0; u32::from_be_bytes([0, header[0], header[1], header[2]]) as usize];
+        client_reader.read_exact(&mut payload).await?;
+
+        if header[3] == HTTP2_PING_FRAME && header[4] & HTTP2_ACK_FLAG == 0 {
+            pings.fetch_add(1, Ordering::SeqCst);
+        }
+        server_writer.write_all(&header).await?;
+        server_writer.write_all(&payload).await?;
+    }
+}
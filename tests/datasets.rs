@@ -0,0 +1,376 @@
+mod common;
+
+use std::collections::HashMap;
+
+use qdrant_uploader::{RecordSource, SourceStatistics};
+use qdrant_uploader::persistence::files_system::{ByteReader, Dataset, FileType, TransportRegistry};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use common::{fixture, fixture_bytes, S3StandIn, BUCKET};
+
+async fn read_all(dataset: Dataset) -> Vec<Value> {
+    let mut rows = Vec::new();
+    while let Some(row) = dataset.next_record().await.expect("row") {
+        rows.push(row);
+    }
+    rows
+}
+
+async fn s3_stand_in(drop_after: Option<usize>) -> S3StandIn {
+    let objects = HashMap::from([
+        (format!("{BUCKET}/items.jsonl"), fixture_bytes("items.jsonl")),
+        (format!("{BUCKET}/nested/items.csv"), fixture_bytes("items.csv")),
+        (format!("{BUCKET}/items.avro"), fixture_bytes("items.avro")),
+    ]);
+    S3StandIn::start(objects, drop_after).await
+}
+
+async fn read_bytes(bytes: &str, file_type: &FileType) -> anyhow::Result<Vec<Value>> {
+    let reader: ByteReader = Box::new(std::io::Cursor::new(bytes.as_bytes().to_vec()));
+    let dataset = Dataset::from_reader(reader, file_type).await?;
+
+    let mut rows = Vec::new();
+    while let Some(row) = dataset.next_record().await? {
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+/// The header of `items.avro` followed by a block announcing `rows` rows of `length` bytes, holding a single byte
+async fn read_avro_block(rows: i64, length: i64) -> anyhow::Result<Vec<Value>> {
+    let avro = fixture_bytes("items.avro");
+    let marker = &avro[avro.len() - 16..];
+    let header_length = avro.windows(16).position(|window| window == marker).unwrap() + 16;
+
+    let mut bytes = avro[..header_length].to_vec();
+    bytes.extend(zigzag_long(rows));
+    bytes.extend(zigzag_long(length));
+    bytes.push(0);
+    bytes.extend(marker);
+
+    let reader: ByteReader = Box::new(std::io::Cursor::new(bytes));
+    let dataset = Dataset::from_reader(reader, &FileType::Avro).await?;
+    let mut rows = Vec::new();
+    while let Some(row) = dataset.next_record().await? {
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+/// The Arrow IPC stream fixture, whose second message, the record batch, announces `body_length` bytes of body
+async fn read_arrow_stream_with_body_length(body_length: i64) -> anyhow::Result<Vec<Value>> {
+    let mut bytes = fixture_bytes("items.arrows");
+    let schema_length = u32::from_le_bytes(bytes[4..8].try_into().unwrap()) as usize;
+    let batch_metadata_start = 8 + schema_length + 8;
+    let batch_metadata_length = u32::from_le_bytes(bytes[batch_metadata_start - 4..batch_metadata_start].try_into().unwrap()) as usize;
+
+    let batch_metadata = &bytes[batch_metadata_start..batch_metadata_start + batch_metadata_length];
+    let message = arrow_ipc::root_as_message(batch_metadata).unwrap();
+    let field_offset = message._tab.vtable().get(arrow_ipc::Message::VT_BODYLENGTH) as usize;
+    assert_ne!(field_offset, 0);
+    let body_length_start = batch_metadata_start + message._tab.loc() + field_offset;
+    bytes[body_length_start..body_length_start + 8].copy_from_slice(&body_length.to_le_bytes());
+
+    let reader: ByteReader = Box::new(std::io::Cursor::new(bytes));
+    let dataset = Dataset::from_reader(reader, &FileType::ArrowStream).await?;
+    let mut rows = Vec::new();
+    while let Some(row) = dataset.next_record().await? {
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+fn zigzag_long(value: i64) -> Vec<u8> {
+    let mut encoded = ((value << 1) ^ (value >> 63)) as u64;
+    let mut bytes = Vec::new();
+    while encoded >= 0x80 {
+        bytes.push((encoded as u8 & 0x7f) | 0x80);
+        encoded >>= 7;
+    }
+    bytes.push(encoded as u8);
+    bytes
+}
+
+fn expected_csv_rows() -> Vec<Value> {
+    vec![
+        json!({"id": 1, "name": "first", "category": "books"}),
+        json!({"id": 2, "name": "second", "category": "music"}),
+        json!({"id": 3, "name": "third", "category": "books"}),
+    ]
+}
+
+/// Checks the typed columns written in the Arrow and Avro fixtures
+fn assert_columnar_rows(rows: &[Value]) {
+    assert_eq!(rows.len(), 3);
+
+    let embedding: Vec<f32> = rows[2]["embedding"].as_array().unwrap().iter().map(|value| value.as_f64().unwrap() as f32).collect();
+    assert_eq!(embedding, vec![0.7, 0.8, 0.9]);
+    assert_eq!(rows[0]["id"], json!(1));
+    assert_eq!(rows[1]["name"], json!("second"));
+    assert_eq!(rows[1]["category"], json!("music"));
+    assert_eq!(rows[2]["created"], json!("2023-11-14T22:15:20.500Z"));
+    assert_eq!(rows[0]["rating"], json!(4.5));
+    assert_eq!(rows[1]["rating"], Value::Null);
+}
+
+
+#[tokio::test]
+async fn reads_local_json_lines() {
+    let dataset = Dataset::load(&fixture("items.jsonl"), &FileType::JSON, &TransportRegistry::default()).await.unwrap();
+    let rows = read_all(dataset).await;
+
+    assert_eq!(rows.len(), 3);
+    assert_eq!(rows[0], json!({"id": 1, "embedding": [0.1, 0.2, 0.3], "name": "first", "category": "books"}));
+}
+
+#[tokio::test]
+async fn reads_local_file_url() {
+    let source_path = format!("file://{path}", path=fixture("items.jsonl"));
+    let dataset = Dataset::load(&source_path, &FileType::JSON, &TransportRegistry::default()).await.unwrap();
+
+    assert_eq!(read_all(dataset).await.len(), 3);
+}
+
+#[tokio::test]
+async fn reads_local_csv_with_header() {
+    let dataset = Dataset::load(&fixture("items.csv"), &FileType::CSV, &TransportRegistry::default()).await.unwrap();
+
+    assert_eq!(read_all(dataset).await, expected_csv_rows());
+}
+
+#[tokio::test]
+async fn fails_on_missing_local_file() {
+    let result = Dataset::load(&fixture("missing.jsonl"), &FileType::JSON, &TransportRegistry::default()).await;
+
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn reads_s3_json_lines() {
+    let s3 = s3_stand_in(None).await;
+    let transports = s3.transports().await;
+
+    let dataset = Dataset::load(&format!("s3://{BUCKET}/items.jsonl"), &FileType::JSON, &transports).await.unwrap();
+    let rows = read_all(dataset).await;
+
+    assert_eq!(rows.len(), 3);
+    assert_eq!(rows[2]["name"], json!("third"));
+}
+
+#[tokio::test]
+async fn reads_s3_csv_with_header() {
+    let s3 = s3_stand_in(None).await;
+    let transports = s3.transports().await;
+
+    let dataset = Dataset::load(&format!("s3://{BUCKET}/nested/items.csv"), &FileType::CSV, &transports).await.unwrap();
+
+    assert_eq!(read_all(dataset).await, expected_csv_rows());
+}
+
+#[tokio::test]
+async fn fails_on_missing_s3_object() {
+    let s3 = s3_stand_in(None).await;
+    let transports = s3.transports().await;
+
+    let result = Dataset::load(&format!("s3://{BUCKET}/missing.jsonl"), &FileType::JSON, &transports).await;
+
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn resumes_s3_download_after_dropped_connection() {
+    let s3 = s3_stand_in(Some(100)).await;
+    let transports = s3.transports().await;
+
+    let dataset = Dataset::load(&format!("s3://{BUCKET}/items.jsonl"), &FileType::JSON, &transports).await.unwrap();
+    let rows = read_all(dataset).await;
+
+    assert_eq!(rows.len(), 3);
+    assert_eq!(s3.requests(), vec![(None, None), (Some("bytes=100-".to_owned()), Some("\"fixture\"".to_owned()))]);
+}
+
+#[tokio::test]
+async fn counts_bytes_read_and_retries() {
+    let s3 = s3_stand_in(Some(100)).await;
+    let transports = s3.transports().await;
+
+    let dataset = Dataset::load(&format!("s3://{BUCKET}/items.jsonl"), &FileType::JSON, &transports).await.unwrap();
+    while dataset.next_record().await.unwrap().is_some() {}
+
+    let expected_statistics = SourceStatistics { bytes_read: fixture_bytes("items.jsonl").len() as u64, rows_rejected: 0, retries: 1 };
+    assert_eq!(dataset.statistics(), expected_statistics);
+}
+
+#[tokio::test]
+async fn computes_checksum_of_bytes_read() {
+    let dataset = Dataset::load(&fixture("items.jsonl"), &FileType::JSON, &TransportRegistry::default()).await.unwrap();
+    while dataset.next_record().await.unwrap().is_some() {}
+
+    let expected_checksum: String = Sha256::digest(fixture_bytes("items.jsonl")).iter().map(|byte| format!("{byte:02x}")).collect();
+    assert_eq!(dataset.checksum(), Some(expected_checksum));
+}
+
+#[tokio::test]
+async fn fails_on_unregistered_scheme() {
+    let result = Dataset::load(&format!("s3://{BUCKET}/items.jsonl"), &FileType::JSON, &TransportRegistry::default()).await;
+
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn reads_pretty_printed_json_array() {
+    let dataset = Dataset::load(&fixture("items.json"), &FileType::JSONArray, &TransportRegistry::default()).await.unwrap();
+    let rows = read_all(dataset).await;
+
+    assert_eq!(rows.len(), 3);
+    assert_eq!(rows[1], json!({"id": 2, "embedding": [0.4, 0.5, 0.6], "name": "second", "category": "music"}));
+}
+
+#[tokio::test]
+async fn detects_json_array_from_first_byte() {
+    let dataset = Dataset::load(&fixture("items.json"), &FileType::JSON, &TransportRegistry::default()).await.unwrap();
+
+    assert_eq!(read_all(dataset).await.len(), 3);
+}
+
+#[tokio::test]
+async fn detects_json_lines_as_concatenated_values() {
+    let dataset = Dataset::load(&fixture("items.jsonl"), &FileType::JSON, &TransportRegistry::default()).await.unwrap();
+
+    assert_eq!(read_all(dataset).await.len(), 3);
+}
+
+#[tokio::test]
+async fn reads_concatenated_pretty_printed_objects() {
+    let rows = read_bytes("{\n  \"id\": 1\n}\n{\"id\": 2}{\"id\": 3}\n\n", &FileType::JSONStream).await.unwrap();
+
+    assert_eq!(rows, vec![json!({"id": 1}), json!({"id": 2}), json!({"id": 3})]);
+}
+
+#[tokio::test]
+async fn keeps_brackets_and_escaped_quotes_inside_strings() {
+    let text = format!("}}]\\\" {{[,{padding}", padding="x".repeat(20_000));
+    let source = format!("[{row}, {{\"id\": 2}}]", row=json!({"id": 1, "text": text}));
+    let rows = read_bytes(&source, &FileType::JSON).await.unwrap();
+
+    assert_eq!(rows, vec![json!({"id": 1, "text": text}), json!({"id": 2})]);
+}
+
+#[tokio::test]
+async fn reads_empty_json_array() {
+    assert!(read_bytes("  [ ]  ", &FileType::JSONArray).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn fails_on_truncated_json_array() {
+    assert!(read_bytes("[{\"id\": 1}, {\"id\": 2}", &FileType::JSONArray).await.is_err());
+    assert!(read_bytes("[{\"id\": 1}, {\"id\":", &FileType::JSONArray).await.is_err());
+}
+
+#[tokio::test]
+async fn fails_on_json_array_without_single_commas_between_elements() {
+    for source in ["[{\"id\": 1},, {\"id\": 2}]", "[, {\"id\": 1}]", "[{\"id\": 1} {\"id\": 2}]", "[{\"id\": 1},]", "[,]"] {
+        assert!(read_bytes(source, &FileType::JSONArray).await.is_err(), "{source}");
+    }
+}
+
+#[tokio::test]
+async fn fails_on_json_array_format_without_array() {
+    assert!(read_bytes("{\"id\": 1}", &FileType::JSONArray).await.is_err());
+}
+
+#[tokio::test]
+async fn json_lines_format_rejects_pretty_printed_objects() {
+    assert!(read_bytes("{\n  \"id\": 1\n}\n", &FileType::JSONL).await.is_err());
+}
+
+#[tokio::test]
+async fn reads_arrow_ipc_file() {
+    let dataset = Dataset::load(&fixture("items.arrow"), &FileType::ArrowFile, &TransportRegistry::default()).await.unwrap();
+
+    assert_columnar_rows(&read_all(dataset).await);
+}
+
+#[tokio::test]
+async fn reads_arrow_ipc_stream_across_batches() {
+    let dataset = Dataset::load(&fixture("items.arrows"), &FileType::ArrowStream, &TransportRegistry::default()).await.unwrap();
+
+    assert_columnar_rows(&read_all(dataset).await);
+}
+
+#[tokio::test]
+async fn fails_on_arrow_file_format_without_magic() {
+    let result = Dataset::load(&fixture("items.arrows"), &FileType::ArrowFile, &TransportRegistry::default()).await;
+
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn reads_deflated_avro_blocks() {
+    let dataset = Dataset::load(&fixture("items.avro"), &FileType::Avro, &TransportRegistry::default()).await.unwrap();
+
+    assert_columnar_rows(&read_all(dataset).await);
+}
+
+#[tokio::test]
+async fn resumes_interrupted_s3_avro() {
+    let stand_in = s3_stand_in(Some(300)).await;
+    let transports = stand_in.transports().await;
+
+    let dataset = Dataset::load(&format!("s3://{BUCKET}/items.avro"), &FileType::Avro, &transports).await.unwrap();
+
+    assert_columnar_rows(&read_all(dataset).await);
+}
+
+#[tokio::test]
+async fn fails_on_avro_block_with_negative_rows() {
+    let error = read_avro_block(-1, 1).await.unwrap_err();
+
+    assert!(error.to_string().contains("Invalid Avro block of -1 rows"), "{error}");
+}
+
+#[tokio::test]
+async fn fails_on_avro_block_with_negative_length() {
+    let error = read_avro_block(1, -1).await.unwrap_err();
+
+    assert!(error.to_string().contains("Invalid Avro block of -1 bytes"), "{error}");
+}
+
+#[tokio::test]
+async fn fails_on_oversized_avro_block_before_allocating_it() {
+    let error = read_avro_block(1, 1 << 40).await.unwrap_err();
+
+    assert!(error.to_string().contains(&format!("Invalid Avro block of {} bytes", 1i64 << 40)), "{error}");
+}
+
+#[tokio::test]
+async fn fails_on_json_read_as_avro() {
+    assert!(read_bytes("{\"id\": 1}", &FileType::Avro).await.is_err());
+}
+
+#[tokio::test]
+async fn fails_on_arrow_message_with_negative_metadata_length() {
+    let mut bytes = vec![0xff; 4];
+    bytes.extend((-8i32).to_le_bytes());
+    let reader: ByteReader = Box::new(std::io::Cursor::new(bytes));
+    let dataset = Dataset::from_reader(reader, &FileType::ArrowStream).await.unwrap();
+
+    let error = dataset.next_record().await.unwrap_err();
+
+    assert!(error.to_string().contains("Invalid Arrow IPC message metadata of -8 bytes"), "{error}");
+}
+
+#[tokio::test]
+async fn fails_on_arrow_message_with_negative_body_length() {
+    let error = read_arrow_stream_with_body_length(-1).await.unwrap_err();
+
+    assert!(error.to_string().contains("Invalid Arrow IPC message body of -1 bytes"), "{error}");
+}
+
+#[tokio::test]
+async fn fails_on_oversized_arrow_message_body_before_allocating_it() {
+    let error = read_arrow_stream_with_body_length(1 << 40).await.unwrap_err();
+
+    assert!(error.to_string().contains("Invalid Arrow IPC message body of 1099511627776 bytes"), "{error}");
+}
@@ -0,0 +1,69 @@
+mod common;
+
+use qdrant_uploader::embedding::{Embedder, EmbedderExt, EmbeddingStage, ProviderType};
+use serde_json::json;
+
+use common::EmbeddingsStandIn;
+
+fn texts(texts: &[&str]) -> Vec<String> {
+    texts.iter().map(|text| text.to_string()).collect()
+}
+
+fn http_embedder(embeddings: &EmbeddingsStandIn) -> Embedder {
+    Embedder::load(&ProviderType::Http, "test-model", &embeddings.endpoint, None, None).unwrap()
+}
+
+fn embedding_stage(embeddings: &EmbeddingsStandIn) -> EmbeddingStage {
+    EmbeddingStage::new(http_embedder(embeddings), "name", "embedding", 16, 100)
+}
+
+
+#[tokio::test]
+async fn orders_embeddings_by_index() {
+    let embeddings = EmbeddingsStandIn::start(0).await;
+
+    let vectors = http_embedder(&embeddings).embed(&texts(&["a", "bbb", "cc"])).await.unwrap();
+
+    assert_eq!(vectors, vec![vec![1.0], vec![3.0], vec![2.0]]);
+}
+
+#[tokio::test]
+async fn fails_when_embeddings_are_missing_from_response() {
+    let embeddings = EmbeddingsStandIn::start(1).await;
+
+    let error = http_embedder(&embeddings).embed(&texts(&["a", "bbb", "cc"])).await.unwrap_err();
+
+    assert!(error.to_string().contains("3 embeddings were expected but 2 were received"), "{error}");
+}
+
+#[tokio::test]
+async fn fills_only_missing_vectors() {
+    let embeddings = EmbeddingsStandIn::start(0).await;
+    let mut batch = vec![
+        json!({"id": 1, "name": "first", "embedding": [0.5]}),
+        json!({"id": 2, "name": "second", "embedding": null}),
+        json!({"id": 3, "name": "third"}),
+    ];
+
+    embedding_stage(&embeddings).fill_missing_vectors(&mut batch).await.unwrap();
+
+    assert_eq!(embeddings.requests(), vec![texts(&["second", "third"])]);
+    assert_eq!(batch[0]["embedding"], json!([0.5]));
+    assert_eq!(batch[1]["embedding"], json!([6.0]));
+    assert_eq!(batch[2]["embedding"], json!([5.0]));
+}
+
+#[tokio::test]
+async fn embeds_each_distinct_text_once() {
+    let embeddings = EmbeddingsStandIn::start(0).await;
+    let embedding_stage = embedding_stage(&embeddings);
+    let mut first_batch = vec![json!({"id": 1, "name": "same"}), json!({"id": 2, "name": "same"})];
+    let mut second_batch = vec![json!({"id": 3, "name": "same"}), json!({"id": 4, "name": "other"})];
+
+    embedding_stage.fill_missing_vectors(&mut first_batch).await.unwrap();
+    embedding_stage.fill_missing_vectors(&mut second_batch).await.unwrap();
+
+    assert_eq!(embeddings.requests(), vec![texts(&["same"]), texts(&["other"])]);
+    assert!(first_batch.iter().chain(second_batch.iter().take(1)).all(|row| row["embedding"] == json!([4.0])));
+    assert_eq!(second_batch[1]["embedding"], json!([5.0]));
+}
@@ -0,0 +1,103 @@
+mod common;
+
+use qdrant_uploader::RecordSource;
+use qdrant_uploader::persistence::files_system::{parse_header, Dataset, FileType, HttpTransport, TransportRegistry};
+use reqwest::header::HeaderMap;
+use serde_json::Value;
+
+use common::{HttpStandIn, E_TAG};
+
+fn transports(headers: &[&str], max_redirects: usize) -> TransportRegistry {
+    let mut header_map = HeaderMap::new();
+    for header in headers {
+        let (name, value) = parse_header(header).unwrap();
+        header_map.append(name, value);
+    }
+
+    let mut transports = TransportRegistry::default();
+    transports.register("http", HttpTransport::new(header_map, max_redirects, 3).unwrap());
+    transports
+}
+
+async fn read_all(url: &str, transports: &TransportRegistry) -> anyhow::Result<Vec<Value>> {
+    let dataset = Dataset::load(url, &FileType::JSON, transports).await?;
+    let mut rows = Vec::new();
+    while let Some(row) = dataset.next_record().await? {
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+
+#[tokio::test]
+async fn resumes_dropped_download_with_range_of_same_file() {
+    let http = HttpStandIn::start(Some(100), false).await;
+
+    let rows = read_all(&format!("{url}/items.jsonl", url=http.url), &transports(&[], 0)).await.unwrap();
+
+    assert_eq!(rows.len(), 3);
+    let requests = http.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!((requests[0].header("range"), requests[0].header("if-range")), (None, None));
+    assert_eq!((requests[1].header("range"), requests[1].header("if-range")), (Some("bytes=100-"), Some(E_TAG)));
+}
+
+#[tokio::test]
+async fn fails_when_file_changes_during_download() {
+    let http = HttpStandIn::start(Some(100), true).await;
+
+    let error = read_all(&format!("{url}/items.jsonl", url=http.url), &transports(&[], 0)).await.unwrap_err();
+
+    assert!(format!("{error:#}").contains("did not honour the range request"), "{error:#}");
+}
+
+#[tokio::test]
+async fn sends_headers_with_every_request() {
+    let http = HttpStandIn::start(Some(100), false).await;
+    let transports = transports(&["Authorization: Bearer secret", "X-Tenant: books"], 0);
+
+    read_all(&format!("{url}/items.jsonl", url=http.url), &transports).await.unwrap();
+
+    let requests = http.requests();
+    assert_eq!(requests.len(), 2);
+    assert!(requests.iter().all(|request| request.header("authorization") == Some("Bearer secret")));
+    assert!(requests.iter().all(|request| request.header("x-tenant") == Some("books")));
+}
+
+#[tokio::test]
+async fn resumes_from_redirect_target() {
+    let http = HttpStandIn::start(Some(100), false).await;
+
+    let rows = read_all(&format!("{url}/redirect/2", url=http.url), &transports(&[], 2)).await.unwrap();
+
+    assert_eq!(rows.len(), 3);
+    let paths: Vec<String> = http.requests().into_iter().map(|request| request.path).collect();
+    assert_eq!(paths, vec!["/redirect/2", "/redirect/1", "/items.jsonl", "/items.jsonl"]);
+}
+
+#[tokio::test]
+async fn withholds_headers_from_other_hosts_when_resuming_redirect_target() {
+    let origin = HttpStandIn::start(None, false).await;
+    let other_host = HttpStandIn::start(Some(100), false).await;
+    let transports = transports(&["Authorization: Bearer secret", "X-Tenant: books"], 1);
+
+    let source_path = format!("{url}/moved?location={other_host}/items.jsonl", url=origin.url, other_host=other_host.url);
+    let rows = read_all(&source_path, &transports).await.unwrap();
+
+    assert_eq!(rows.len(), 3);
+    assert_eq!(origin.requests()[0].header("authorization"), Some("Bearer secret"));
+    let requests = other_host.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[1].header("range"), Some("bytes=100-"));
+    assert!(requests.iter().all(|request| request.header("authorization").is_none() && request.header("x-tenant").is_none()));
+}
+
+#[tokio::test]
+async fn fails_after_too_many_redirects() {
+    let http = HttpStandIn::start(None, false).await;
+
+    let result = read_all(&format!("{url}/redirect/3", url=http.url), &transports(&[], 2)).await;
+
+    assert!(result.is_err());
+    assert_eq!(http.requests().len(), 3);
+}
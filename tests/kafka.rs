@@ -0,0 +1,155 @@
+//! Runs against the mock cluster embedded in librdkafka, with `cargo test --features kafka`
+#![cfg(feature = "kafka")]
+
+mod common;
+
+use std::time::Duration;
+
+use qdrant_uploader::RecordSource;
+use qdrant_uploader::persistence::kafka::{KafkaOptions, KafkaSource};
+use rdkafka::ClientConfig;
+use rdkafka::mocking::MockCluster;
+use rdkafka::producer::{DefaultProducerContext, FutureProducer, FutureRecord};
+use serde_json::{json, Value};
+
+use common::{run_for, run_uploader, uploader_command, QdrantMock};
+
+const TOPIC: &str = "embeddings";
+const FLUSH_INTERVAL: Duration = Duration::from_millis(500);
+
+async fn cluster_with_messages(payloads: &[String]) -> MockCluster<'static, DefaultProducerContext> {
+    let cluster = MockCluster::new(1).unwrap();
+    cluster.create_topic(TOPIC, 1, 1).unwrap();
+
+    let producer: FutureProducer = ClientConfig::new()
+        .set("bootstrap.servers", cluster.bootstrap_servers())
+        .create()
+        .unwrap();
+    for payload in payloads {
+        producer.send(FutureRecord::<(), _>::to(TOPIC).payload(payload), Duration::from_secs(5)).await.unwrap();
+    }
+
+    cluster
+}
+
+fn items(ids: std::ops::RangeInclusive<u64>) -> Vec<String> {
+    ids.map(|id| json!({"id": id, "embedding": [0.1, 0.2, 0.3]}).to_string()).collect()
+}
+
+fn source(cluster: &MockCluster<'static, DefaultProducerContext>, group_id: &str) -> KafkaSource {
+    let mut options = KafkaOptions::from_source_path(&format!("kafka://{}/{TOPIC}", cluster.bootstrap_servers()), group_id, FLUSH_INTERVAL).unwrap();
+    // A consumer that is dropped leaves its partitions to the next one of the group once its session expires
+    options.client_settings.push(("session.timeout.ms".to_owned(), "6000".to_owned()));
+    KafkaSource::new(options).unwrap()
+}
+
+fn ids(batch: &[Value]) -> Vec<u64> {
+    batch.iter().map(|row| row["id"].as_u64().unwrap()).collect()
+}
+
+
+#[tokio::test]
+async fn flushes_partial_batch_after_interval() {
+    let cluster = cluster_with_messages(&items(1..=3)).await;
+    let source = source(&cluster, "partial");
+
+    let batch = source.next_batch(100).await.unwrap().unwrap();
+
+    assert_eq!(ids(&batch), vec![1, 2, 3]);
+}
+
+#[tokio::test]
+async fn limits_batches_to_batch_size() {
+    let cluster = cluster_with_messages(&items(1..=5)).await;
+    let source = source(&cluster, "size");
+
+    assert_eq!(ids(&source.next_batch(2).await.unwrap().unwrap()), vec![1, 2]);
+    assert_eq!(ids(&source.next_batch(2).await.unwrap().unwrap()), vec![3, 4]);
+}
+
+#[tokio::test]
+async fn resumes_after_committed_offsets() {
+    let cluster = cluster_with_messages(&items(1..=5)).await;
+
+    let first_source = source(&cluster, "resume");
+    assert_eq!(ids(&first_source.next_batch(2).await.unwrap().unwrap()), vec![1, 2]);
+    first_source.commit().await.unwrap();
+    drop(first_source);
+
+    let second_source = source(&cluster, "resume");
+    assert_eq!(ids(&second_source.next_batch(100).await.unwrap().unwrap()), vec![3, 4, 5]);
+}
+
+#[tokio::test]
+async fn redelivers_batch_that_was_not_committed() {
+    let cluster = cluster_with_messages(&items(1..=5)).await;
+
+    let first_source = source(&cluster, "redeliver");
+    assert_eq!(ids(&first_source.next_batch(2).await.unwrap().unwrap()), vec![1, 2]);
+    drop(first_source);
+
+    let second_source = source(&cluster, "redeliver");
+    assert_eq!(ids(&second_source.next_batch(100).await.unwrap().unwrap()), vec![1, 2, 3, 4, 5]);
+}
+
+#[tokio::test]
+async fn skips_messages_that_are_not_json() {
+    let mut payloads = vec!["not json".to_owned()];
+    payloads.extend(items(1..=2));
+    let cluster = cluster_with_messages(&payloads).await;
+    let source = source(&cluster, "invalid");
+
+    assert_eq!(ids(&source.next_batch(100).await.unwrap().unwrap()), vec![1, 2]);
+    assert_eq!(source.statistics().rows_rejected, 1);
+}
+
+#[tokio::test]
+async fn ends_once_stopped() {
+    let cluster = cluster_with_messages(&items(1..=1)).await;
+    let source = source(&cluster, "stop");
+
+    source.stop();
+
+    assert!(source.next_batch(100).await.unwrap().is_none());
+}
+
+#[test]
+fn rejects_source_path_without_topic() {
+    assert!(KafkaOptions::from_source_path("kafka://localhost:9092", "group", FLUSH_INTERVAL).is_err());
+    assert!(KafkaOptions::from_source_path("kafka://localhost:9092/", "group", FLUSH_INTERVAL).is_err());
+}
+
+#[tokio::test]
+async fn rejects_options_of_finite_uploads() {
+    let (qdrant, qdrant_url) = QdrantMock::start().await;
+    let arguments = ["--source-path", "kafka://localhost:9092/embeddings", "--kafka-group-id", "options", "--connection-string", &qdrant_url,
+                     "--database-collection", "items", "--batch-size", "2", "--id-field-name", "id", "--vector-field-name", "embedding"];
+
+    for option in [&["--verify"][..], &["--bulk-load"], &["--defer-indexing"]] {
+        let output = run_uploader(&[&arguments[..], option].concat()).await;
+
+        assert_eq!(output.status.code(), Some(2), "{option:?}");
+        assert!(String::from_utf8_lossy(&output.stdout).contains("A kafka:// source never ends"), "{option:?}");
+    }
+    assert!(qdrant.upserts().is_empty());
+}
+
+#[tokio::test]
+async fn exits_with_partial_failure_after_skipping_messages() {
+    let mut payloads = vec!["not json".to_owned()];
+    payloads.extend(items(1..=2));
+    let cluster = cluster_with_messages(&payloads).await;
+    let (qdrant, qdrant_url) = QdrantMock::start().await;
+    let source_path = format!("kafka://{}/{TOPIC}", cluster.bootstrap_servers());
+
+    let command = uploader_command(&["--source-path", &source_path, "--kafka-group-id", "summary", "--kafka-flush-interval-ms", "500",
+        "--connection-string", &qdrant_url, "--database-collection", "items", "--batch-size", "2", "--id-field-name", "id",
+        "--vector-field-name", "embedding"]);
+    let output = run_for(command, Duration::from_secs(8)).await;
+    let summary: Value = serde_json::from_slice(&output.stdout).unwrap();
+
+    assert_eq!(output.status.code(), Some(4), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(summary["status"], "partial_failure");
+    assert_eq!(summary["rows_rejected"], 1);
+    assert_eq!(qdrant.upserted_points().len(), 2);
+}
@@ -0,0 +1,70 @@
+mod common;
+
+use serde_json::Value;
+use tonic::Code;
+
+use common::{fixture, run, uploader_command, QdrantMock};
+
+/// Uploads `items.jsonl` with JSON logs, returning the lines written on stderr
+async fn json_log_lines(qdrant_url: &str, arguments: &[&str]) -> Vec<Value> {
+    let source_path = fixture("items.jsonl");
+    let mut all_arguments = vec!["--source-path", &source_path, "--source-file-type", "jsonl", "--connection-string", qdrant_url,
+                                 "--database-collection", "items", "--batch-size", "2", "--id-field-name", "id",
+                                 "--vector-field-name", "embedding", "--log-format", "json", "--job-name", "nightly-items"];
+    all_arguments.extend(arguments);
+    let mut command = uploader_command(&all_arguments);
+    command.env("RUST_LOG", "info");
+
+    let output = run(command).await;
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stderr).unwrap().lines()
+        .map(|line| serde_json::from_str(line).unwrap_or_else(|error| panic!("{line} is not JSON: {error}")))
+        .collect()
+}
+
+fn with_message<'a>(lines: &'a [Value], message: &str) -> Vec<&'a Value> {
+    lines.iter().filter(|line| line["message"].as_str().is_some_and(|line_message| line_message.starts_with(message))).collect()
+}
+
+fn span_names(line: &Value) -> Vec<&str> {
+    line["spans"].as_array().unwrap().iter().map(|span| span["name"].as_str().unwrap()).collect()
+}
+
+
+#[tokio::test]
+async fn logs_batches_as_json_with_job_and_collection() {
+    let (_qdrant, qdrant_url) = QdrantMock::start().await;
+
+    let lines = json_log_lines(&qdrant_url, &[]).await;
+    let batch_lines = with_message(&lines, "Batch uploaded");
+
+    assert_eq!(batch_lines.len(), 2);
+    let first_batch = batch_lines[0];
+    assert_eq!(first_batch["level"], "INFO");
+    assert_eq!(first_batch["batch"], 1);
+    assert_eq!(first_batch["rows"], 2);
+    assert_eq!(first_batch["points"], 2);
+    assert!(first_batch["duration_ms"].is_u64());
+    assert!(first_batch["timestamp"].is_string());
+    assert_eq!(span_names(first_batch), vec!["upload"]);
+    assert_eq!(first_batch["spans"][0]["job"], "nightly-items");
+    assert_eq!(first_batch["spans"][0]["collection"], "items");
+    assert_eq!(batch_lines[1]["batch"], 2);
+    assert_eq!(batch_lines[1]["rows"], 1);
+}
+
+#[tokio::test]
+async fn logs_events_within_their_batch_span() {
+    let (qdrant, qdrant_url) = QdrantMock::start().await;
+    qdrant.fail_upserts(&[Code::Unavailable]);
+
+    let lines = json_log_lines(&qdrant_url, &["--adaptive-chunk-size", "--min-chunk-size", "1"]).await;
+    let retry_lines = with_message(&lines, "Upsert of 2 points failed");
+
+    assert_eq!(retry_lines.len(), 1);
+    assert_eq!(retry_lines[0]["level"], "WARN");
+    assert_eq!(span_names(retry_lines[0]), vec!["upload", "batch"]);
+    assert_eq!(retry_lines[0]["spans"][0]["job"], "nightly-items");
+    assert_eq!(retry_lines[0]["spans"][1]["batch"], 1);
+    assert_eq!(retry_lines[0]["spans"][1]["rows"], 2);
+}
@@ -0,0 +1,148 @@
+mod common;
+
+use std::process::Output;
+use std::time::Duration;
+
+use serde_json::Value;
+
+use common::{fixture, fixture_bytes, run_for, run_uploader, uploader_command, QdrantMock};
+
+const COLLECTION: &str = "items";
+
+const SUMMARY_FIELDS: [&str; 14] = ["status", "exit_code", "error", "rows_read", "points_upserted", "rows_rejected", "duplicate_ids",
+    "retries", "bytes_read", "objects_uploaded", "objects_failed", "elapsed_seconds", "points_per_second", "source_checksum"];
+
+/// Runs the uploader binary on `source_path`, with the given extra arguments
+async fn upload(qdrant_url: &str, source_path: &str, arguments: &[&str]) -> Output {
+    let mut all_arguments = vec!["--source-path", source_path, "--source-file-type", "jsonl", "--connection-string", qdrant_url,
+                                 "--database-collection", COLLECTION, "--batch-size", "2", "--id-field-name", "id",
+                                 "--vector-field-name", "embedding"];
+    all_arguments.extend(arguments);
+    run_uploader(&all_arguments).await
+}
+
+/// The summary written on stdout, checking that it has every field
+fn summary(output: &Output) -> Value {
+    let summary: Value = serde_json::from_slice(&output.stdout).expect("JSON summary on stdout");
+    let mut fields = summary.as_object().unwrap().keys().map(String::as_str).collect::<Vec<_>>();
+    fields.sort_unstable();
+    let mut expected_fields = SUMMARY_FIELDS.to_vec();
+    expected_fields.sort_unstable();
+    assert_eq!(fields, expected_fields);
+    summary
+}
+
+
+#[tokio::test]
+async fn summarizes_succeeded_upload() {
+    let (_qdrant, qdrant_url) = QdrantMock::start().await;
+
+    let output = upload(&qdrant_url, &fixture("items.jsonl"), &[]).await;
+    let summary = summary(&output);
+
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(summary["status"], "succeeded");
+    assert_eq!(summary["exit_code"], 0);
+    assert_eq!(summary["error"], Value::Null);
+    assert_eq!(summary["rows_read"], 3);
+    assert_eq!(summary["points_upserted"], 3);
+    assert_eq!(summary["rows_rejected"], 0);
+    assert_eq!(summary["duplicate_ids"], Value::Null);
+    assert_eq!(summary["bytes_read"], std::fs::metadata(fixture("items.jsonl")).unwrap().len());
+    assert_eq!(summary["source_checksum"].as_str().map(str::len), Some(64));
+}
+
+#[tokio::test]
+async fn exits_with_config_error_on_invalid_options() {
+    let (qdrant, qdrant_url) = QdrantMock::start().await;
+
+    let output = upload(&qdrant_url, &fixture("items.jsonl"), &["--recreate-collection"]).await;
+    let summary = summary(&output);
+
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(summary["status"], "config_error");
+    assert!(summary["error"].as_str().unwrap().contains("--vector-size must be provided"));
+    assert!(qdrant.upserts().is_empty());
+}
+
+#[tokio::test]
+async fn rejects_zero_chunk_size() {
+    let (qdrant, qdrant_url) = QdrantMock::start().await;
+
+    let output = upload(&qdrant_url, &fixture("items.jsonl"), &["--chunk-size", "0"]).await;
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--chunk-size"));
+    assert!(qdrant.upserts().is_empty());
+}
+
+#[tokio::test]
+async fn rejects_zero_min_chunk_size() {
+    let (qdrant, qdrant_url) = QdrantMock::start().await;
+
+    let output = upload(&qdrant_url, &fixture("items.jsonl"), &["--adaptive-chunk-size", "--min-chunk-size", "0"]).await;
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--min-chunk-size"));
+    assert!(qdrant.upserts().is_empty());
+}
+
+#[tokio::test]
+async fn exits_with_source_error_on_missing_source() {
+    let (_qdrant, qdrant_url) = QdrantMock::start().await;
+
+    let output = upload(&qdrant_url, &fixture("missing.jsonl"), &[]).await;
+
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(summary(&output)["status"], "source_error");
+}
+
+#[tokio::test]
+async fn exits_with_source_error_on_rows_not_converted_to_points() {
+    let (qdrant, qdrant_url) = QdrantMock::start().await;
+
+    let output = upload(&qdrant_url, &fixture("items.jsonl"), &["--shard-key-field", "embedding"]).await;
+    let summary = summary(&output);
+
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(summary["status"], "source_error");
+    assert!(summary["error"].as_str().unwrap().contains("Invalid shard key"));
+    assert!(qdrant.upserts().is_empty());
+}
+
+#[tokio::test]
+async fn exits_with_qdrant_error_on_rejected_upsert() {
+    let (qdrant, qdrant_url) = QdrantMock::start().await;
+    qdrant.fail_upserts(&[tonic::Code::InvalidArgument]);
+
+    let output = upload(&qdrant_url, &fixture("items.jsonl"), &[]).await;
+    let summary = summary(&output);
+
+    assert_eq!(output.status.code(), Some(5));
+    assert_eq!(summary["status"], "qdrant_error");
+    assert_eq!(summary["rows_read"], 0);
+}
+
+#[tokio::test]
+async fn exits_with_partial_failure_when_watched_files_fail() {
+    let (qdrant, qdrant_url) = QdrantMock::start().await;
+    let directory = std::env::temp_dir().join(format!("qdrant-uploader-summary-watch-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&directory);
+    std::fs::create_dir_all(directory.join("incoming")).unwrap();
+    std::fs::write(directory.join("incoming/valid.jsonl"), fixture_bytes("items.jsonl")).unwrap();
+    std::fs::write(directory.join("incoming/invalid.jsonl"), "not json\n").unwrap();
+    let source_path = directory.join("incoming").to_string_lossy().into_owned();
+    let state_path = directory.join("state.json").to_string_lossy().into_owned();
+
+    let command = uploader_command(&["--watch", "--watch-interval", "1", "--watch-state-path", &state_path, "--source-path", &source_path,
+        "--source-file-type", "jsonl", "--connection-string", &qdrant_url, "--database-collection", COLLECTION, "--batch-size", "2",
+        "--id-field-name", "id", "--vector-field-name", "embedding"]);
+    let output = run_for(command, Duration::from_secs(4)).await;
+    let summary = summary(&output);
+
+    assert_eq!(output.status.code(), Some(4));
+    assert_eq!(summary["status"], "partial_failure");
+    assert_eq!(summary["objects_uploaded"], 1);
+    assert!(summary["objects_failed"].as_u64().unwrap() >= 1);
+    assert_eq!(qdrant.upserted_points().len(), 3);
+}
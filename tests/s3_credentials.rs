@@ -0,0 +1,104 @@
+//! The binary runs with a shared config file of its own and without any AWS variable of the environment, so that
+//! credentials and region come from the profiles below
+
+mod common;
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::Output;
+
+use common::{fixture_bytes, run, uploader_command, QdrantMock, S3StandIn, BUCKET};
+
+const AWS_CONFIG: &str = "\
+[default]
+region = us-west-1
+aws_access_key_id = AKIDDEFAULT
+aws_secret_access_key = default-secret
+
+[profile uploader]
+region = eu-west-3
+aws_access_key_id = AKIDUPLOADER
+aws_secret_access_key = uploader-secret
+";
+
+const AWS_VARIABLES: [&str; 8] = ["AWS_ACCESS_KEY_ID", "AWS_SECRET_ACCESS_KEY", "AWS_SESSION_TOKEN", "AWS_PROFILE", "AWS_REGION",
+    "AWS_DEFAULT_REGION", "AWS_WEB_IDENTITY_TOKEN_FILE", "AWS_ROLE_ARN"];
+
+/// A directory holding the shared config file, the credentials file being left missing
+fn aws_directory(name: &str) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!("qdrant-uploader-s3-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&directory);
+    std::fs::create_dir_all(&directory).unwrap();
+    std::fs::write(directory.join("config"), AWS_CONFIG).unwrap();
+    directory
+}
+
+/// Runs the uploader binary on `items.jsonl` of the S3 stand-in, with the given extra arguments
+async fn upload(name: &str, s3: &S3StandIn, arguments: &[&str]) -> Output {
+    let (_qdrant, qdrant_url) = QdrantMock::start().await;
+    let source_path = format!("s3://{BUCKET}/items.jsonl");
+    let mut all_arguments = vec!["--source-path", &source_path, "--source-file-type", "jsonl", "--s3-endpoint", &s3.endpoint,
+                                 "--connection-string", &qdrant_url, "--database-collection", "items", "--batch-size", "2", "--id-field-name", "id",
+                                 "--vector-field-name", "embedding"];
+    all_arguments.extend(arguments);
+
+    let aws_directory = aws_directory(name);
+    let mut command = uploader_command(&all_arguments);
+    for variable in AWS_VARIABLES {
+        command.env_remove(variable);
+    }
+    command.env("AWS_CONFIG_FILE", aws_directory.join("config"))
+        .env("AWS_SHARED_CREDENTIALS_FILE", aws_directory.join("credentials"))
+        .env("AWS_EC2_METADATA_DISABLED", "true");
+    run(command).await
+}
+
+async fn s3_stand_in() -> S3StandIn {
+    S3StandIn::start(HashMap::from([(format!("{BUCKET}/items.jsonl"), fixture_bytes("items.jsonl"))]), None).await
+}
+
+
+#[tokio::test]
+async fn signs_with_default_profile() {
+    let s3 = s3_stand_in().await;
+
+    let output = upload("default", &s3, &[]).await;
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(s3.authorizations()[0].contains("Credential=AKIDDEFAULT/"));
+    assert!(s3.authorizations()[0].contains("/us-west-1/s3/aws4_request"));
+}
+
+#[tokio::test]
+async fn signs_with_credentials_and_region_of_given_profile() {
+    let s3 = s3_stand_in().await;
+
+    let output = upload("profile", &s3, &["--s3-profile", "uploader"]).await;
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(s3.authorizations()[0].contains("Credential=AKIDUPLOADER/"));
+    assert!(s3.authorizations()[0].contains("/eu-west-3/s3/aws4_request"));
+}
+
+#[tokio::test]
+async fn signs_in_given_region_over_profile_region() {
+    let s3 = s3_stand_in().await;
+
+    let output = upload("region", &s3, &["--s3-profile", "uploader", "--s3-region", "us-east-2"]).await;
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(s3.authorizations()[0].contains("Credential=AKIDUPLOADER/"));
+    assert!(s3.authorizations()[0].contains("/us-east-2/s3/aws4_request"));
+}
+
+#[tokio::test]
+async fn signs_with_assumed_role_rather_than_profile() {
+    let s3 = s3_stand_in().await;
+
+    let output = upload("role", &s3, &["--s3-profile", "uploader", "--s3-role-arn", "arn:aws:iam::123456789012:role/uploader"]).await;
+
+    // The role can not be assumed from the fake profile keys, so no request is signed with them
+    assert_eq!(output.status.code(), Some(3), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("an error occurred while loading credentials"));
+    assert!(s3.authorizations().is_empty());
+}
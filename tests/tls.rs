@@ -0,0 +1,34 @@
+mod common;
+
+use qdrant_uploader::persistence::TlsSettings;
+
+use common::fixture;
+
+#[test]
+fn loads_private_ca_and_client_certificate() {
+    let tls = TlsSettings::load(Some(&fixture("tls/ca.pem")), Some(&fixture("tls/client.pem")), Some(&fixture("tls/client.key")),
+        Some("qdrant.test".to_owned()));
+
+    assert!(tls.is_ok());
+}
+
+#[test]
+fn fails_on_client_certificate_without_key() {
+    let tls = TlsSettings::load(Some(&fixture("tls/ca.pem")), Some(&fixture("tls/client.pem")), None, None);
+
+    assert!(tls.is_err());
+}
+
+#[test]
+fn fails_on_ca_bundle_without_certificate() {
+    let tls = TlsSettings::load(Some(&fixture("tls/client.key")), None, None, None);
+
+    assert!(tls.is_err());
+}
+
+#[test]
+fn fails_on_missing_client_key_file() {
+    let tls = TlsSettings::load(Some(&fixture("tls/ca.pem")), Some(&fixture("tls/client.pem")), Some(&fixture("tls/missing.key")), None);
+
+    assert!(tls.is_err());
+}
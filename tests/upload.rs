@@ -0,0 +1,335 @@
+mod common;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use prost::Message;
+use qdrant_client::qdrant::point_id::PointIdOptions;
+use qdrant_client::qdrant::value::Kind;
+use qdrant_client::qdrant::vector::Vector;
+use qdrant_client::qdrant::vectors::VectorsOptions;
+use qdrant_client::qdrant::{PointStruct, WriteOrderingType};
+use qdrant_uploader::monitoring::Metrics;
+use qdrant_uploader::persistence::{ConnectionOptions, DatabaseClient, TlsSettings, WriteOrderingKind};
+use qdrant_uploader::persistence::files_system::{Dataset, FileType, TransportRegistry};
+use qdrant_uploader::persistence::vector_field_name::FieldName;
+use qdrant_uploader::{PointMapper, UploadJob};
+use tonic::Code;
+
+use common::{fixture, fixture_bytes, run_with_stdin, upload_items, uploader_command, Http2PingCounter, QdrantMock, S3StandIn, BUCKET};
+
+const COLLECTION: &str = "items";
+
+fn point_id(point: &PointStruct) -> u64 {
+    match point.id.as_ref().and_then(|id| id.point_id_options.clone()) {
+        Some(PointIdOptions::Num(id)) => id,
+        other => panic!("unexpected point id {other:?}"),
+    }
+}
+
+fn point_vector(point: &PointStruct) -> Vec<f32> {
+    match point.vectors.as_ref().and_then(|vectors| vectors.vectors_options.clone()) {
+        Some(VectorsOptions::Vector(vector)) => match vector.vector {
+            Some(Vector::Dense(dense)) => dense.data,
+            other => panic!("unexpected vector {other:?}"),
+        },
+        other => panic!("unexpected vectors {other:?}"),
+    }
+}
+
+fn payload_string(point: &PointStruct, field: &str) -> String {
+    match point.payload.get(field).and_then(|value| value.kind.clone()) {
+        Some(Kind::StringValue(value)) => value,
+        other => panic!("unexpected payload {field}: {other:?}"),
+    }
+}
+
+async fn upload(dataset: Dataset, qdrant_url: &str, batch_size: u32) -> u64 {
+    upload_with_metrics(dataset, qdrant_url, batch_size, Arc::new(Metrics::default())).await
+}
+
+async fn upload_with_metrics(dataset: Dataset, qdrant_url: &str, batch_size: u32, metrics: Arc<Metrics>) -> u64 {
+    let point_mapper = PointMapper::builder()
+        .id_field("id")
+        .vector_field(FieldName::Single("embedding".to_owned()))
+        .payload_field(FieldName::Named(vec!["name".to_owned(), "category".to_owned()]))
+        .build()
+        .unwrap();
+    let sink = DatabaseClient::new(qdrant_url, &None, COLLECTION, 2).await.unwrap();
+
+    let mut upload_job = UploadJob::builder()
+        .source(dataset)
+        .point_mapper(point_mapper)
+        .sink(sink)
+        .batch_size(batch_size)
+        .metrics(metrics)
+        .build()
+        .unwrap();
+
+    upload_job.run().await.unwrap()
+}
+
+
+#[tokio::test]
+async fn uploads_local_json_lines_to_qdrant() {
+    let (qdrant, qdrant_url) = QdrantMock::start().await;
+    let dataset = Dataset::load(&fixture("items.jsonl"), &FileType::JSON, &TransportRegistry::default()).await.unwrap();
+
+    let points_uploaded = upload(dataset, &qdrant_url, 10).await;
+    let points = qdrant.upserted_points();
+
+    assert_eq!(points_uploaded, 3);
+    assert!(qdrant.upserts().iter().all(|upsert| upsert.collection_name == COLLECTION && upsert.points.len() <= 2));
+    assert_eq!(points.iter().map(point_id).collect::<Vec<_>>(), vec![1, 2, 3]);
+    assert_eq!(point_vector(&points[1]), vec![0.4, 0.5, 0.6]);
+    assert_eq!(payload_string(&points[0], "name"), "first");
+    assert_eq!(payload_string(&points[1], "category"), "music");
+}
+
+#[tokio::test]
+async fn uploads_json_lines_piped_into_stdin() {
+    let (qdrant, qdrant_url) = QdrantMock::start().await;
+    let command = uploader_command(&["--source-path", "-", "--source-file-type", "jsonl", "--connection-string", &qdrant_url,
+        "--database-collection", COLLECTION, "--batch-size", "2", "--id-field-name", "id", "--vector-field-name", "embedding",
+        "--payload-field", "name"]);
+
+    let output = run_with_stdin(command, fixture_bytes("items.jsonl")).await;
+    let points = qdrant.upserted_points();
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(points.iter().map(point_id).collect::<Vec<_>>(), vec![1, 2, 3]);
+    assert_eq!(payload_string(&points[2], "name"), "third");
+}
+
+#[tokio::test]
+async fn uploads_s3_csv_to_qdrant() {
+    let (qdrant, qdrant_url) = QdrantMock::start().await;
+    let s3 = S3StandIn::start(HashMap::from([(format!("{BUCKET}/items.csv"), fixture_bytes("items.csv"))]), None).await;
+    let transports = s3.transports().await;
+    let dataset = Dataset::load(&format!("s3://{BUCKET}/items.csv"), &FileType::CSV, &transports).await.unwrap();
+
+    let points_uploaded = upload(dataset, &qdrant_url, 2).await;
+    let points = qdrant.upserted_points();
+
+    assert_eq!(points_uploaded, 3);
+    assert_eq!(points.iter().map(point_id).collect::<Vec<_>>(), vec![1, 2, 3]);
+    assert_eq!(payload_string(&points[2], "name"), "third");
+}
+
+#[tokio::test]
+async fn uploads_s3_json_lines_after_dropped_connection() {
+    let (qdrant, qdrant_url) = QdrantMock::start().await;
+    let s3 = S3StandIn::start(HashMap::from([(format!("{BUCKET}/items.jsonl"), fixture_bytes("items.jsonl"))]), Some(90)).await;
+    let transports = s3.transports().await;
+    let dataset = Dataset::load(&format!("s3://{BUCKET}/items.jsonl"), &FileType::JSON, &transports).await.unwrap();
+
+    let points_uploaded = upload(dataset, &qdrant_url, 10).await;
+
+    assert_eq!(points_uploaded, 3);
+    assert_eq!(qdrant.upserted_points().iter().map(point_id).collect::<Vec<_>>(), vec![1, 2, 3]);
+}
+
+#[tokio::test]
+async fn records_metrics_of_each_batch() {
+    let (_qdrant, qdrant_url) = QdrantMock::start().await;
+    let s3 = S3StandIn::start(HashMap::from([(format!("{BUCKET}/items.jsonl"), fixture_bytes("items.jsonl"))]), Some(90)).await;
+    let transports = s3.transports().await;
+    let dataset = Dataset::load(&format!("s3://{BUCKET}/items.jsonl"), &FileType::JSON, &transports).await.unwrap();
+    let metrics = Arc::new(Metrics::default());
+
+    upload_with_metrics(dataset, &qdrant_url, 2, metrics.clone()).await;
+    let metrics_text = metrics.render();
+
+    assert!(metrics_text.contains("qdrant_uploader_rows_read_total 3\n"));
+    assert!(metrics_text.contains("qdrant_uploader_points_upserted_total 3\n"));
+    assert!(metrics_text.contains("qdrant_uploader_retries_total 1\n"));
+    assert!(metrics_text.contains(&format!("qdrant_uploader_source_bytes_read_total {}\n", fixture_bytes("items.jsonl").len())));
+    assert!(metrics_text.contains("qdrant_uploader_batch_latency_seconds_count 2\n"));
+}
+
+#[tokio::test]
+async fn splits_chunks_larger_than_max_bytes() {
+    let (qdrant, qdrant_url) = QdrantMock::start().await;
+    let sink = DatabaseClient::new(&qdrant_url, &None, COLLECTION, 2).await.unwrap()
+        .with_max_chunk_bytes(Some(1));
+
+    upload_items(sink).await.unwrap();
+
+    assert_eq!(qdrant.upserts().iter().map(|upsert| upsert.points.len()).collect::<Vec<_>>(), vec![1, 1, 1]);
+    assert_eq!(qdrant.upserted_points().iter().map(point_id).collect::<Vec<_>>(), vec![1, 2, 3]);
+}
+
+#[tokio::test]
+async fn keeps_upsert_requests_under_max_bytes() {
+    let (reference, reference_url) = QdrantMock::start().await;
+    upload_items(DatabaseClient::new(&reference_url, &None, COLLECTION, 2).await.unwrap()).await.unwrap();
+    let max_bytes = reference.upserts().iter().map(Message::encoded_len).max().unwrap() - 1;
+    let (qdrant, qdrant_url) = QdrantMock::start().await;
+    let sink = DatabaseClient::new(&qdrant_url, &None, COLLECTION, 2).await.unwrap()
+        .with_max_chunk_bytes(Some(max_bytes));
+
+    upload_items(sink).await.unwrap();
+
+    assert!(qdrant.upserts().iter().all(|upsert| upsert.encoded_len() <= max_bytes));
+    assert_eq!(qdrant.upserted_points().iter().map(point_id).collect::<Vec<_>>(), vec![1, 2, 3]);
+}
+
+#[tokio::test]
+async fn limits_points_upserted_per_second() {
+    let (qdrant, qdrant_url) = QdrantMock::start().await;
+    let sink = DatabaseClient::new(&qdrant_url, &None, COLLECTION, 2).await.unwrap()
+        .with_rate_limit(Some(1), None);
+
+    let upload_start = Instant::now();
+    upload_items(sink).await.unwrap();
+
+    // The bucket holds one point, so the two chunks of 2 and 1 points each wait about a second
+    assert!(upload_start.elapsed() >= Duration::from_millis(1500));
+    assert_eq!(qdrant.upserted_points().iter().map(point_id).collect::<Vec<_>>(), vec![1, 2, 3]);
+}
+
+#[tokio::test]
+async fn shrinks_chunks_of_slow_upserts() {
+    let (qdrant, qdrant_url) = QdrantMock::start().await;
+    qdrant.delay_upserts(Duration::from_millis(100));
+    let sink = DatabaseClient::new(&qdrant_url, &None, COLLECTION, 2).await.unwrap()
+        .with_adaptive_chunk_size(1, Duration::from_millis(20));
+
+    upload_items(sink).await.unwrap();
+
+    assert_eq!(qdrant.upserts().iter().map(|upsert| upsert.points.len()).collect::<Vec<_>>(), vec![2, 1]);
+}
+
+#[tokio::test]
+async fn retries_unavailable_upserts_with_smaller_chunks_then_grows_them_back() {
+    let (qdrant, qdrant_url) = QdrantMock::start().await;
+    qdrant.fail_upserts(&[Code::Unavailable]);
+    let metrics = Arc::new(Metrics::default());
+    let sink = DatabaseClient::new(&qdrant_url, &None, COLLECTION, 2).await.unwrap()
+        .with_adaptive_chunk_size(1, Duration::from_secs(10))
+        .with_metrics(metrics.clone());
+
+    upload_items(sink).await.unwrap();
+
+    assert_eq!(qdrant.failed_upserts().iter().map(|upsert| upsert.points.len()).collect::<Vec<_>>(), vec![2]);
+    assert_eq!(qdrant.upserts().iter().map(|upsert| upsert.points.len()).collect::<Vec<_>>(), vec![1, 2]);
+    assert_eq!(qdrant.upserted_points().iter().map(point_id).collect::<Vec<_>>(), vec![1, 2, 3]);
+    assert_eq!(metrics.snapshot().retries, 1);
+    assert!(metrics.render().contains("qdrant_uploader_retries_total 1\n"));
+}
+
+#[tokio::test]
+async fn fails_without_retrying_rejected_upserts() {
+    let (qdrant, qdrant_url) = QdrantMock::start().await;
+    qdrant.fail_upserts(&[Code::InvalidArgument]);
+    let metrics = Arc::new(Metrics::default());
+    let sink = DatabaseClient::new(&qdrant_url, &None, COLLECTION, 2).await.unwrap()
+        .with_adaptive_chunk_size(1, Duration::from_secs(10))
+        .with_metrics(metrics.clone());
+
+    let error = upload_items(sink).await.unwrap_err();
+
+    assert_eq!(error.root_cause().downcast_ref::<tonic::Status>().map(tonic::Status::code), Some(Code::InvalidArgument));
+    assert_eq!(qdrant.failed_upserts().len(), 1);
+    assert!(qdrant.upserts().is_empty());
+    assert_eq!(metrics.snapshot().retries, 0);
+}
+
+#[test]
+fn rejects_rows_without_shard_key() {
+    let point_mapper = PointMapper::builder()
+        .id_field("id")
+        .vector_field(FieldName::Single("embedding".to_owned()))
+        .shard_key_field("category")
+        .build()
+        .unwrap();
+    let rows = vec![
+        serde_json::json!({"id": 1, "embedding": [0.1, 0.2, 0.3], "category": "books"}),
+        serde_json::json!({"id": 2, "embedding": [0.4, 0.5, 0.6]}),
+    ];
+
+    let error = point_mapper.to_points(rows).unwrap_err();
+
+    assert!(format!("{error:#}").contains("Row 2 of the batch can not be routed to a shard: Missing shard key in field category"), "{error:#}");
+}
+
+#[tokio::test]
+async fn waits_for_upserts_sent_without_waiting_once_uploaded() {
+    let (qdrant, qdrant_url) = QdrantMock::start().await;
+    let sink = DatabaseClient::new(&qdrant_url, &None, COLLECTION, 2).await.unwrap()
+        .with_write_ordering(Some(WriteOrderingKind::Strong))
+        .with_wait(false);
+
+    qdrant.queue_updates(&[2, 1]);
+
+    upload_items(sink).await.unwrap();
+    let upserts = qdrant.upserts();
+
+    assert_eq!(upserts.len(), 2);
+    assert!(upserts.iter().all(|upsert| upsert.wait == Some(false)));
+    assert!(upserts.iter().all(|upsert| upsert.ordering.as_ref().map(|ordering| ordering.r#type) == Some(WriteOrderingType::Strong as i32)));
+    assert_eq!(qdrant.collection_info_requests(), 3);
+    assert!(qdrant.deletes().is_empty());
+}
+
+#[tokio::test]
+async fn uploads_over_mutual_tls_with_private_ca() {
+    let (qdrant, tls_url) = QdrantMock::start_tls().await;
+    let tls = TlsSettings::load(Some(&fixture("tls/ca.pem")), Some(&fixture("tls/client.pem")), Some(&fixture("tls/client.key")),
+        Some("qdrant.test".to_owned())).unwrap();
+    let options = ConnectionOptions { tls: Some(tls), ..Default::default() };
+    let sink = DatabaseClient::connect(&tls_url, &options, COLLECTION, 2).await.unwrap();
+
+    upload_items(sink).await.unwrap();
+
+    assert_eq!(qdrant.upserted_points().iter().map(point_id).collect::<Vec<_>>(), vec![1, 2, 3]);
+}
+
+#[tokio::test]
+async fn fails_to_connect_to_server_not_issued_by_private_ca() {
+    let (_qdrant, tls_url) = QdrantMock::start_tls().await;
+    let tls = TlsSettings::load(Some(&fixture("tls/server.pem")), Some(&fixture("tls/client.pem")), Some(&fixture("tls/client.key")),
+        Some("qdrant.test".to_owned())).unwrap();
+    let options = ConnectionOptions { tls: Some(tls), ..Default::default() };
+
+    assert!(DatabaseClient::connect(&tls_url, &options, COLLECTION, 2).await.is_err());
+}
+
+#[tokio::test]
+async fn fails_to_connect_when_server_name_differs_from_certificate() {
+    let (_qdrant, tls_url) = QdrantMock::start_tls().await;
+    let tls = TlsSettings::load(Some(&fixture("tls/ca.pem")), Some(&fixture("tls/client.pem")), Some(&fixture("tls/client.key")),
+        Some("other.test".to_owned())).unwrap();
+    let options = ConnectionOptions { tls: Some(tls), ..Default::default() };
+
+    assert!(DatabaseClient::connect(&tls_url, &options, COLLECTION, 2).await.is_err());
+}
+
+#[tokio::test]
+async fn sends_keep_alive_pings_while_idle() {
+    let (_qdrant, qdrant_url) = QdrantMock::start().await;
+    let proxy = Http2PingCounter::start(&qdrant_url).await;
+    let options = ConnectionOptions {
+        keep_alive_interval: Some(Duration::from_millis(200)),
+        keep_alive_while_idle: true,
+        ..Default::default()
+    };
+
+    let _sink = DatabaseClient::connect(&proxy.url, &options, COLLECTION, 2).await.unwrap();
+    tokio::time::sleep(Duration::from_secs(1)).await;
+
+    assert!(proxy.pings() >= 2, "{} pings", proxy.pings());
+}
+
+#[tokio::test]
+async fn sends_no_keep_alive_pings_while_idle_unless_asked() {
+    let (_qdrant, qdrant_url) = QdrantMock::start().await;
+    let proxy = Http2PingCounter::start(&qdrant_url).await;
+    let options = ConnectionOptions { keep_alive_interval: Some(Duration::from_millis(200)), ..Default::default() };
+
+    let _sink = DatabaseClient::connect(&proxy.url, &options, COLLECTION, 2).await.unwrap();
+    tokio::time::sleep(Duration::from_secs(1)).await;
+
+    assert_eq!(proxy.pings(), 0);
+}
@@ -0,0 +1,121 @@
+//! The binary fixtures hold the embeddings of `items.jsonl`: `items.npy` as float32, `items.npz` as big-endian float64
+//! in a deflated `embeddings` array stored after an `ids` array, `items.fvecs` as float32 and `items.bvecs` as the
+//! first two rows multiplied by 10
+
+mod common;
+
+use qdrant_uploader::RecordSource;
+use qdrant_uploader::persistence::files_system::{FileType, PairedDataset, TransportRegistry, VectorFileType};
+use serde_json::{json, Value};
+
+use common::fixture;
+
+const EMBEDDINGS: [[f32; 3]; 3] = [[0.1, 0.2, 0.3], [0.4, 0.5, 0.6], [0.7, 0.8, 0.9]];
+
+async fn load(vectors: &str, vector_file_type: Option<VectorFileType>, npz_array: &str) -> anyhow::Result<PairedDataset> {
+    PairedDataset::load(&fixture("items.csv"), &FileType::CSV, &fixture(vectors), vector_file_type, npz_array, "embedding",
+        &TransportRegistry::default()).await
+}
+
+async fn read_all(dataset: PairedDataset) -> anyhow::Result<Vec<Value>> {
+    let mut rows = Vec::new();
+    while let Some(row) = dataset.next_record().await? {
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+/// Loads a vector file with the given content, paired with `items.csv`
+async fn load_corrupt(file_name: &str, content: &[u8]) -> anyhow::Result<Vec<Value>> {
+    let directory = std::env::temp_dir().join(format!("qdrant-uploader-vectors-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let path = directory.join(file_name);
+    std::fs::write(&path, content).unwrap();
+
+    let dataset = PairedDataset::load(&fixture("items.csv"), &FileType::CSV, path.to_str().unwrap(), None, "arr_0", "embedding",
+        &TransportRegistry::default()).await?;
+    read_all(dataset).await
+}
+
+/// An .npy file of version 2, whose header length is a 32 bits integer, followed by the given header
+fn npy_with_header(header_length: u32, header: &str) -> Vec<u8> {
+    let mut content = b"\x93NUMPY\x02\x00".to_vec();
+    content.extend(header_length.to_le_bytes());
+    content.extend(header.as_bytes());
+    content
+}
+
+fn embedding(row: &Value) -> Vec<f32> {
+    row["embedding"].as_array().unwrap().iter().map(|coordinate| coordinate.as_f64().unwrap() as f32).collect()
+}
+
+
+#[tokio::test]
+async fn pairs_npy_rows_with_csv_metadata() {
+    let rows = read_all(load("items.npy", None, "arr_0").await.unwrap()).await.unwrap();
+
+    assert_eq!(rows.len(), 3);
+    assert_eq!(rows[1]["id"], json!(2));
+    assert_eq!(rows[1]["name"], json!("second"));
+    assert_eq!(rows.iter().map(embedding).collect::<Vec<_>>(), EMBEDDINGS.map(Vec::from));
+}
+
+#[tokio::test]
+async fn reads_named_array_of_npz_archive() {
+    let rows = read_all(load("items.npz", None, "embeddings").await.unwrap()).await.unwrap();
+
+    assert_eq!(rows.iter().map(embedding).collect::<Vec<_>>(), EMBEDDINGS.map(Vec::from));
+}
+
+#[tokio::test]
+async fn fails_on_missing_npz_array() {
+    assert!(load("items.npz", None, "arr_0").await.is_err());
+}
+
+#[tokio::test]
+async fn reads_fvecs() {
+    let rows = read_all(load("items.fvecs", Some(VectorFileType::Fvecs), "arr_0").await.unwrap()).await.unwrap();
+
+    assert_eq!(rows.iter().map(embedding).collect::<Vec<_>>(), EMBEDDINGS.map(Vec::from));
+}
+
+#[tokio::test]
+async fn fails_when_vector_and_metadata_row_counts_differ() {
+    let dataset = load("items.bvecs", None, "arr_0").await.unwrap();
+
+    assert_eq!(embedding(&dataset.next_record().await.unwrap().unwrap()), vec![1.0, 2.0, 3.0]);
+    assert_eq!(embedding(&dataset.next_record().await.unwrap().unwrap()), vec![4.0, 5.0, 6.0]);
+    assert!(dataset.next_record().await.is_err());
+}
+
+#[tokio::test]
+async fn fails_on_unknown_vector_extension() {
+    assert!(load("items.jsonl", None, "arr_0").await.is_err());
+}
+
+#[tokio::test]
+async fn fails_on_npy_header_too_long() {
+    let error = load_corrupt("long_header.npy", &npy_with_header(u32::MAX, "{")).await.unwrap_err();
+
+    assert!(format!("{error:#}").contains("Invalid NumPy header of 4294967295 bytes"), "{error:#}");
+}
+
+#[tokio::test]
+async fn fails_on_npy_dimension_too_large() {
+    let header = "{'descr': '<f8', 'fortran_order': False, 'shape': (3, 4611686018427387904), }";
+    let error = load_corrupt("large_dimension.npy", &npy_with_header(header.len() as u32, header)).await.unwrap_err();
+
+    assert!(format!("{error:#}").contains("Invalid NumPy vector dimension 4611686018427387904"), "{error:#}");
+}
+
+#[tokio::test]
+async fn fails_on_fvecs_dimension_too_large_or_negative() {
+    for dimension in [i32::MAX, -1] {
+        let mut content = dimension.to_le_bytes().to_vec();
+        content.extend([0u8; 12]);
+
+        let error = load_corrupt("corrupt.fvecs", &content).await.unwrap_err();
+
+        assert!(format!("{error:#}").contains(&format!("Invalid vector dimension {dimension}, it must be between 1 and")), "{error:#}");
+    }
+}
@@ -0,0 +1,122 @@
+mod common;
+
+use qdrant_client::qdrant::vectors_config::Config;
+use qdrant_client::qdrant::{CreateCollection, Distance, VectorParams, VectorsConfig};
+use qdrant_uploader::persistence::vector_field_name::FieldName;
+use qdrant_uploader::persistence::{ConnectionOptions, DatabaseClient, UploadVerifier};
+use qdrant_uploader::{PointMapper, Sink};
+use serde_json::{json, Value};
+
+use common::QdrantMock;
+
+const COLLECTION: &str = "items";
+const TOLERANCE: f32 = 0.01;
+
+fn point_mapper() -> PointMapper {
+    PointMapper::builder()
+        .id_field("id")
+        .vector_field(FieldName::Single("embedding".to_owned()))
+        .payload_field(FieldName::Named(vec!["name".to_owned()]))
+        .build()
+        .unwrap()
+}
+
+fn row(id: u64, embedding: [f32; 3], name: &str) -> Value {
+    json!({"id": id, "embedding": embedding, "name": name})
+}
+
+fn source_rows() -> Vec<Value> {
+    vec![row(1, [0.1, 0.2, 0.3], "first"), row(2, [0.4, 0.5, 0.6], "second"), row(3, [0.7, 0.8, 0.9], "third")]
+}
+
+/// A collection of 3 dimensional vectors with the given distance
+async fn database_client(distance: Distance) -> (QdrantMock, DatabaseClient) {
+    let (qdrant, qdrant_url) = QdrantMock::start().await;
+    let vector_params = VectorParams { size: 3, distance: distance as i32, ..Default::default() };
+    let create_collection = CreateCollection {
+        collection_name: COLLECTION.to_owned(),
+        vectors_config: Some(VectorsConfig { config: Some(Config::Params(vector_params)) }),
+        ..Default::default()
+    };
+    qdrant.add_collection(create_collection, Vec::new());
+
+    let database_client = DatabaseClient::connect(&qdrant_url, &ConnectionOptions::default(), COLLECTION, 100).await.unwrap();
+    (qdrant, database_client)
+}
+
+/// Verifies the collection after the verifier observed `source_rows` while `stored_rows` were upserted
+async fn verify(distance: Distance, source_rows: &[Value], stored_rows: Vec<Value>) -> anyhow::Result<()> {
+    let (_qdrant, database_client) = database_client(distance).await;
+    let point_mapper = point_mapper();
+
+    let mut verifier = UploadVerifier::new(source_rows.len(), TOLERANCE);
+    verifier.observe_batch(&point_mapper, source_rows);
+    for (shard_key, points) in point_mapper.to_points(stored_rows).unwrap() {
+        database_client.write_points(shard_key, points).await.unwrap();
+    }
+    database_client.flush().await.unwrap();
+
+    verifier.verify(&database_client).await
+}
+
+
+#[tokio::test]
+async fn verifies_upload_matching_source() {
+    verify(Distance::Dot, &source_rows(), source_rows()).await.unwrap();
+}
+
+#[tokio::test]
+async fn fails_on_differing_vector() {
+    let mut stored_rows = source_rows();
+    stored_rows[1] = row(2, [0.4, 0.5, 0.7], "second");
+
+    let error = verify(Distance::Dot, &source_rows(), stored_rows).await.unwrap_err();
+
+    assert!(error.to_string().contains("1 of 3 sampled points differ"), "{error}");
+}
+
+#[tokio::test]
+async fn fails_on_differing_payload() {
+    let mut stored_rows = source_rows();
+    stored_rows[2] = row(3, [0.7, 0.8, 0.9], "other");
+
+    let error = verify(Distance::Dot, &source_rows(), stored_rows).await.unwrap_err();
+
+    assert!(error.to_string().contains("1 of 3 sampled points differ"), "{error}");
+}
+
+#[tokio::test]
+async fn fails_on_point_count_mismatch() {
+    let stored_rows = source_rows()[..2].to_vec();
+
+    let error = verify(Distance::Dot, &source_rows(), stored_rows).await.unwrap_err();
+
+    assert!(error.to_string().contains("has 2 points, but 3 distinct ids were uploaded"), "{error}");
+}
+
+#[tokio::test]
+async fn accepts_coordinates_just_inside_tolerance() {
+    let mut stored_rows = source_rows();
+    stored_rows[0] = row(1, [0.1 + 0.009, 0.2, 0.3], "first");
+
+    verify(Distance::Dot, &source_rows(), stored_rows).await.unwrap();
+}
+
+#[tokio::test]
+async fn fails_on_coordinates_just_outside_tolerance() {
+    let mut stored_rows = source_rows();
+    stored_rows[0] = row(1, [0.1 + 0.011, 0.2, 0.3], "first");
+
+    let error = verify(Distance::Dot, &source_rows(), stored_rows).await.unwrap_err();
+
+    assert!(error.to_string().contains("1 of 3 sampled points differ"), "{error}");
+}
+
+#[tokio::test]
+async fn compares_cosine_vectors_normalized() {
+    let source_rows = vec![row(1, [3.0, 4.0, 0.0], "first")];
+    let stored_rows = vec![row(1, [0.6, 0.8, 0.0], "first")];
+
+    verify(Distance::Cosine, &source_rows, stored_rows.clone()).await.unwrap();
+    assert!(verify(Distance::Dot, &source_rows, stored_rows).await.is_err());
+}
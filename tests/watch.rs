@@ -0,0 +1,271 @@
+mod common;
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use qdrant_uploader::monitoring::{serve_metrics, Metrics};
+use qdrant_uploader::persistence::files_system::{Dataset, FileType, ListedObject, LocalDirectory, ObjectListing, S3Prefix,
+                                                 TransportRegistry};
+use qdrant_uploader::persistence::vector_field_name::FieldName;
+use qdrant_uploader::persistence::DatabaseClient;
+use qdrant_uploader::watch::{ProcessedAction, WatchState, Watcher};
+use qdrant_uploader::{PointMapper, UploadJob};
+
+use common::{fixture_bytes, run_uploader, QdrantMock, S3StandIn, BUCKET, E_TAG};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+/// An empty directory holding the watched `incoming` directory and the state file
+fn test_directory(name: &str) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!("qdrant-uploader-watch-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&directory);
+    std::fs::create_dir_all(directory.join("incoming")).unwrap();
+    directory
+}
+
+async fn watcher(directory: &Path) -> Watcher {
+    let listing = LocalDirectory::new(directory.join("incoming").to_str().unwrap());
+    let state = WatchState::load(directory.join("state.json")).await.unwrap();
+    Watcher::new(listing, state, POLL_INTERVAL)
+}
+
+/// A stand-in bucket with three objects directly under `incoming/`, one in a nested prefix and one elsewhere
+async fn s3_stand_in() -> S3StandIn {
+    let objects = ["incoming/a.jsonl", "incoming/b.jsonl", "incoming/c.jsonl", "incoming/nested/d.jsonl", "other/e.jsonl"]
+        .map(|key| (format!("{BUCKET}/{key}"), b"{}\n".to_vec()));
+    S3StandIn::start(HashMap::from(objects), None).await
+}
+
+async fn s3_watcher(directory: &Path, s3: &S3StandIn) -> Watcher {
+    let listing = S3Prefix::new(&format!("s3://{BUCKET}/incoming"), s3.options()).await.unwrap();
+    let state = WatchState::load(directory.join("state.json")).await.unwrap();
+    Watcher::new(listing, state, POLL_INTERVAL)
+}
+
+/// Polls once, returning the file names given to the upload
+async fn poll(watcher: &mut Watcher) -> Vec<String> {
+    let mut uploaded = Vec::new();
+    watcher.poll(&mut |path: String| {
+        uploaded.push(file_name(&path));
+        async { Ok(1) }
+    }).await.unwrap();
+    uploaded
+}
+
+/// Uploads a watched file of items the way the binary does, updating the metrics
+async fn upload_with_metrics(path: String, qdrant_url: &str, metrics: Arc<Metrics>) -> anyhow::Result<u64> {
+    let dataset = Dataset::load(&path, &FileType::JSON, &TransportRegistry::default()).await?;
+    let point_mapper = PointMapper::builder().id_field("id").vector_field(FieldName::Single("embedding".to_owned())).build()?;
+
+    UploadJob::builder()
+        .source(dataset)
+        .point_mapper(point_mapper)
+        .sink(DatabaseClient::new(qdrant_url, &None, "items", 2).await?)
+        .metrics(metrics)
+        .build()?
+        .run()
+        .await
+}
+
+fn file_name(path: &str) -> String {
+    Path::new(path).file_name().unwrap().to_string_lossy().into_owned()
+}
+
+
+#[tokio::test]
+async fn uploads_each_file_once_across_restarts() {
+    let directory = test_directory("once");
+    std::fs::write(directory.join("incoming/a.jsonl"), "{}\n").unwrap();
+    std::fs::write(directory.join("incoming/b.jsonl"), "{}\n").unwrap();
+
+    let mut first_watcher = watcher(&directory).await;
+    assert!(poll(&mut first_watcher).await.is_empty());
+    assert_eq!(poll(&mut first_watcher).await, vec!["a.jsonl", "b.jsonl"]);
+    assert!(poll(&mut first_watcher).await.is_empty());
+
+    std::fs::write(directory.join("incoming/c.jsonl"), "{}\n").unwrap();
+    let mut second_watcher = watcher(&directory).await;
+    assert!(poll(&mut second_watcher).await.is_empty());
+    assert_eq!(poll(&mut second_watcher).await, vec!["c.jsonl"]);
+}
+
+#[tokio::test]
+async fn uploads_changed_file_again() {
+    let directory = test_directory("changed");
+    std::fs::write(directory.join("incoming/a.jsonl"), "{}\n").unwrap();
+
+    let mut watcher = watcher(&directory).await;
+    poll(&mut watcher).await;
+    assert_eq!(poll(&mut watcher).await, vec!["a.jsonl"]);
+
+    std::fs::write(directory.join("incoming/a.jsonl"), "{}\n{}\n").unwrap();
+    assert!(poll(&mut watcher).await.is_empty());
+    assert_eq!(poll(&mut watcher).await, vec!["a.jsonl"]);
+}
+
+#[tokio::test]
+async fn retries_failed_upload_on_next_poll() {
+    let directory = test_directory("retry");
+    std::fs::write(directory.join("incoming/a.jsonl"), "{}\n").unwrap();
+    let metrics = Arc::new(Metrics::default());
+    let mut watcher = watcher(&directory).await.metrics(metrics.clone());
+    poll(&mut watcher).await;
+
+    let uploaded = watcher.poll(&mut |_| async { anyhow::bail!("Qdrant is unavailable") }).await.unwrap();
+    assert_eq!(uploaded, 0);
+    assert!(metrics.render().contains("qdrant_uploader_objects_failed_total 1"));
+
+    assert_eq!(poll(&mut watcher).await, vec!["a.jsonl"]);
+}
+
+#[tokio::test]
+async fn skips_file_failing_max_attempts_until_it_changes() {
+    let directory = test_directory("attempts");
+    std::fs::write(directory.join("incoming/a.jsonl"), "{}\n").unwrap();
+    let mut first_watcher = watcher(&directory).await.max_attempts(2);
+    poll(&mut first_watcher).await;
+
+    for _ in 0..2 {
+        first_watcher.poll(&mut |_| async { anyhow::bail!("Qdrant is unavailable") }).await.unwrap();
+    }
+    assert!(poll(&mut first_watcher).await.is_empty());
+
+    let mut second_watcher = watcher(&directory).await.max_attempts(2);
+    poll(&mut second_watcher).await;
+    assert!(poll(&mut second_watcher).await.is_empty());
+
+    std::fs::write(directory.join("incoming/a.jsonl"), "{}\n{}\n").unwrap();
+    poll(&mut second_watcher).await;
+    assert_eq!(poll(&mut second_watcher).await, vec!["a.jsonl"]);
+}
+
+#[tokio::test]
+async fn waits_for_file_being_written() {
+    let directory = test_directory("written");
+    std::fs::write(directory.join("incoming/a.jsonl"), "{").unwrap();
+
+    let mut watcher = watcher(&directory).await;
+    assert!(poll(&mut watcher).await.is_empty());
+
+    std::fs::write(directory.join("incoming/a.jsonl"), "{}\n{}\n").unwrap();
+    assert!(poll(&mut watcher).await.is_empty());
+    assert_eq!(poll(&mut watcher).await, vec!["a.jsonl"]);
+}
+
+#[tokio::test]
+async fn moves_uploaded_files() {
+    let directory = test_directory("move");
+    std::fs::write(directory.join("incoming/a.jsonl"), "{}\n").unwrap();
+    let processed_directory = directory.join("processed");
+
+    let mut watcher = watcher(&directory).await
+        .processed_action(ProcessedAction::MoveTo(processed_directory.to_string_lossy().into_owned()));
+    poll(&mut watcher).await;
+    assert_eq!(poll(&mut watcher).await, vec!["a.jsonl"]);
+
+    assert!(!directory.join("incoming/a.jsonl").exists());
+    assert!(processed_directory.join("a.jsonl").exists());
+}
+
+#[tokio::test]
+async fn uploads_only_files_with_suffix() {
+    let directory = test_directory("suffix");
+    std::fs::write(directory.join("incoming/a.jsonl"), "{}\n").unwrap();
+    std::fs::write(directory.join("incoming/a.jsonl.part"), "{").unwrap();
+
+    let mut watcher = watcher(&directory).await.suffix(".jsonl");
+    poll(&mut watcher).await;
+
+    assert_eq!(poll(&mut watcher).await, vec!["a.jsonl"]);
+}
+
+#[tokio::test]
+async fn serves_health_and_metrics() {
+    let directory = test_directory("metrics");
+    std::fs::write(directory.join("incoming/a.jsonl"), fixture_bytes("items.jsonl")).unwrap();
+    let (_qdrant, qdrant_url) = QdrantMock::start().await;
+    let metrics = Arc::new(Metrics::default());
+    let address = serve_metrics("127.0.0.1:0".parse().unwrap(), metrics.clone()).unwrap();
+
+    let ready = reqwest::get(format!("http://{address}/ready")).await.unwrap();
+    assert_eq!(ready.status(), 503);
+
+    let mut watcher = watcher(&directory).await.metrics(metrics.clone());
+    metrics.set_ready();
+    poll(&mut watcher).await;
+    watcher.poll(&mut |path: String| upload_with_metrics(path, &qdrant_url, metrics.clone())).await.unwrap();
+
+    let health = reqwest::get(format!("http://{address}/health")).await.unwrap();
+    assert_eq!(health.status(), 200);
+    let ready = reqwest::get(format!("http://{address}/ready")).await.unwrap();
+    assert_eq!(ready.status(), 200);
+    let metrics_text = reqwest::get(format!("http://{address}/metrics")).await.unwrap().text().await.unwrap();
+    assert!(metrics_text.contains("qdrant_uploader_objects_uploaded_total 1\n"));
+    assert!(metrics_text.contains("qdrant_uploader_rows_read_total 3\n"));
+    assert!(metrics_text.contains("qdrant_uploader_points_upserted_total 3\n"));
+
+    std::fs::remove_dir_all(directory.join("incoming")).unwrap();
+    assert!(watcher.poll(&mut |_| async { Ok(0) }).await.is_err());
+    let health = reqwest::get(format!("http://{address}/health")).await.unwrap();
+    assert_eq!(health.status(), 503);
+    let ready = reqwest::get(format!("http://{address}/ready")).await.unwrap();
+    assert_eq!(ready.status(), 503);
+}
+
+#[tokio::test]
+async fn lists_objects_directly_under_s3_prefix_across_pages() {
+    let s3 = s3_stand_in().await;
+    let listing = S3Prefix::new(&format!("s3://{BUCKET}/incoming/"), s3.options()).await.unwrap();
+
+    let objects = listing.list().await.unwrap();
+
+    let expected_objects: Vec<ListedObject> = ["a", "b", "c"].iter()
+        .map(|name| ListedObject { path: format!("s3://{BUCKET}/incoming/{name}.jsonl"), etag: E_TAG.to_owned() })
+        .collect();
+    assert_eq!(objects, expected_objects);
+}
+
+#[tokio::test]
+async fn uploads_s3_objects_on_first_poll_and_moves_them() {
+    let directory = test_directory("s3-move");
+    let s3 = s3_stand_in().await;
+
+    let mut watcher = s3_watcher(&directory, &s3).await
+        .processed_action(ProcessedAction::MoveTo(format!("s3://{BUCKET}/processed/")));
+    assert_eq!(poll(&mut watcher).await, vec!["a.jsonl", "b.jsonl", "c.jsonl"]);
+
+    let expected_objects: Vec<String> = ["incoming/nested/d.jsonl", "other/e.jsonl", "processed/a.jsonl", "processed/b.jsonl", "processed/c.jsonl"]
+        .iter().map(|key| format!("{BUCKET}/{key}")).collect();
+    assert_eq!(s3.objects(), expected_objects);
+}
+
+#[tokio::test]
+async fn tags_uploaded_s3_objects() {
+    let directory = test_directory("s3-tag");
+    let s3 = s3_stand_in().await;
+    let tags = vec![("done".to_owned(), "true".to_owned()), ("by".to_owned(), "uploader".to_owned())];
+
+    let mut watcher = s3_watcher(&directory, &s3).await.processed_action(ProcessedAction::Tag(tags.clone()));
+    poll(&mut watcher).await;
+
+    assert_eq!(s3.tags(&format!("{BUCKET}/incoming/a.jsonl")), tags);
+    assert_eq!(s3.objects().len(), 5);
+}
+
+#[tokio::test]
+async fn rejects_options_ignored_by_watch() {
+    let (_qdrant, qdrant_url) = QdrantMock::start().await;
+    let directory = test_directory("options");
+    let source_path = directory.join("incoming").to_string_lossy().into_owned();
+    let arguments = ["--watch", "--source-path", &source_path, "--source-file-type", "jsonl", "--connection-string", &qdrant_url,
+                     "--database-collection", "items", "--batch-size", "2", "--id-field-name", "id", "--vector-field-name", "embedding"];
+
+    for option in [&["--verify"][..], &["--tenant-index-field", "tenant"], &["--shard-key-field", "category"]] {
+        let output = run_uploader(&[&arguments[..], option].concat()).await;
+
+        assert_eq!(output.status.code(), Some(2), "{option:?}");
+        assert!(String::from_utf8_lossy(&output.stdout).contains("--watch can not be used with"), "{option:?}");
+    }
+}